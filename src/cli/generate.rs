@@ -1,12 +1,15 @@
 use duckdb::{Connection, params};
 use polars::prelude::*;
-use std::{fs, path::Path};
+use rayon::prelude::*;
+use std::{collections::BTreeMap, fs, path::Path};
 
-use super::index_pgn;
+use super::{filter_expr, index_pgn};
 use anyhow::{Result, anyhow};
+use heisenbase::dtz_file::write_dtz_file;
+use heisenbase::dtz_table::DtzTable;
 use heisenbase::material_key::MaterialKey;
 use heisenbase::table_builder::TableBuilder;
-use heisenbase::wdl_file::write_wdl_file;
+use heisenbase::wdl_file::write_wdl_file_with_options;
 use heisenbase::wdl_score_range::WdlScoreRange;
 use heisenbase::wdl_table::WdlTable;
 
@@ -38,10 +41,15 @@ pub(crate) fn run_generate(material: MaterialKey) -> Result<()> {
             missing.join(", ")
         }
     );
+    // With no missing child materials, retrograde analysis should resolve every legal position,
+    // so any `Unknown` left over carries no information worth preserving and is safe to rewrite
+    // for compression.
+    let mask_unknown = missing.is_empty();
     table_builder.solve();
-    let wdl_table: WdlTable = table_builder.into();
+    let dtz_table: DtzTable = table_builder.into();
+    let wdl_table = WdlTable::from(&dtz_table);
     let total = wdl_table.positions.len() as f64;
-    let mut counts = [0usize; 7];
+    let mut counts = [0usize; 9];
     for wdl in &wdl_table.positions {
         counts[*wdl as usize] += 1;
     }
@@ -54,6 +62,8 @@ pub(crate) fn run_generate(material: MaterialKey) -> Result<()> {
         WdlScoreRange::Draw,
         WdlScoreRange::Loss,
         WdlScoreRange::IllegalPosition,
+        WdlScoreRange::CursedWin,
+        WdlScoreRange::BlessedLoss,
     ] {
         let count = counts[variant as usize];
         let percentage = if total > 0.0 {
@@ -63,17 +73,40 @@ pub(crate) fn run_generate(material: MaterialKey) -> Result<()> {
         };
         println!("{variant:?}: {percentage:.2}%");
     }
+    let dtz_values: Vec<i64> = dtz_table
+        .positions
+        .iter()
+        .map(|pos| pos.to_storage_value())
+        .filter(|&value| value != i8::MIN)
+        .map(|value| value as i64)
+        .collect();
+    let dtz_min = dtz_values.iter().copied().min();
+    let dtz_max = dtz_values.iter().copied().max();
+    println!(
+        "DTZ statistics: min={}, max={}",
+        dtz_min.map_or("n/a".to_string(), |v| v.to_string()),
+        dtz_max.map_or("n/a".to_string(), |v| v.to_string())
+    );
+
     let heisenbase_dir = Path::new("./data/heisenbase");
     fs::create_dir_all(heisenbase_dir)?;
     let filename = heisenbase_dir.join(format!("{}.hbt", wdl_table.material));
-    write_wdl_file(&filename, &wdl_table)?;
-    log_stats_to_index(&wdl_table, &counts)?;
+    write_wdl_file_with_options(&filename, &wdl_table, mask_unknown)?;
+    let dtz_filename = heisenbase_dir.join(format!("{}.hbz", dtz_table.material));
+    write_dtz_file(&dtz_filename, &dtz_table)?;
+    log_stats_to_index(&wdl_table, &counts, dtz_min, dtz_max)?;
     println!("Wrote table to {}", filename.display());
+    println!("Wrote table to {}", dtz_filename.display());
     println!();
     Ok(())
 }
 
-fn log_stats_to_index(wdl_table: &WdlTable, counts: &[usize; 7]) -> Result<()> {
+fn log_stats_to_index(
+    wdl_table: &WdlTable,
+    counts: &[usize; 9],
+    dtz_min: Option<i64>,
+    dtz_max: Option<i64>,
+) -> Result<()> {
     let heisenbase_dir = Path::new("./data/heisenbase");
     fs::create_dir_all(heisenbase_dir)?;
 
@@ -101,6 +134,9 @@ fn log_stats_to_index(wdl_table: &WdlTable, counts: &[usize; 7]) -> Result<()> {
         format!("[{}]", items)
     };
 
+    let dtz_min_literal = dtz_min.map_or("NULL".to_string(), |v| v.to_string());
+    let dtz_max_literal = dtz_max.map_or("NULL".to_string(), |v| v.to_string());
+
     let conn = Connection::open(heisenbase_dir.join("index.duckdb"))?;
     conn.execute("DELETE FROM material_keys WHERE name = ?", params![name])?;
     let insert_sql = format!(
@@ -117,8 +153,12 @@ fn log_stats_to_index(wdl_table: &WdlTable, counts: &[usize; 7]) -> Result<()> {
             loss,
             win_or_draw,
             draw_or_loss,
-            unknown
-        ) VALUES ('{}', {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
+            unknown,
+            cursed_win,
+            blessed_loss,
+            dtz_min,
+            dtz_max
+        ) VALUES ('{}', {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
         name.replace('\'', "''"),
         children_literal,
         num_pieces,
@@ -132,13 +172,21 @@ fn log_stats_to_index(wdl_table: &WdlTable, counts: &[usize; 7]) -> Result<()> {
         counts[WdlScoreRange::WinOrDraw as usize],
         counts[WdlScoreRange::DrawOrLoss as usize],
         counts[WdlScoreRange::Unknown as usize],
+        counts[WdlScoreRange::CursedWin as usize],
+        counts[WdlScoreRange::BlessedLoss as usize],
+        dtz_min_literal,
+        dtz_max_literal,
     );
     conn.execute(&insert_sql, [])?;
 
     Ok(())
 }
 
-pub(crate) fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<()> {
+pub(crate) fn run_generate_many(
+    min_games: u64,
+    max_pieces: u32,
+    filter: Option<String>,
+) -> Result<()> {
     let df = LazyFrame::scan_parquet(index_pgn::PARQUET_PATH, Default::default())?
         .filter(col("num_games").gt(1))
         .with_columns([
@@ -154,15 +202,27 @@ pub(crate) fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<()> {
         .collect()?;
 
     let keys = df.column("material_key")?;
+    let games_counts = df.column("num_games")?;
+
+    let predicate = match filter {
+        Some(expr) => Some(filter_expr::parse(&expr)?),
+        None => None,
+    };
 
     let mut candidates = Vec::new();
-    for key in keys.str()?.into_iter() {
+    for (key, num_games) in keys.str()?.into_iter().zip(games_counts.u64()?.into_iter()) {
         let key = key.ok_or_else(|| anyhow!("material_key is null"))?;
+        let num_games = num_games.ok_or_else(|| anyhow!("num_games is null"))?;
         let material_key = MaterialKey::from_string(key)
             .map_err(|err| anyhow!("invalid material key: {key}: {err}"))?;
         if material_key.total_piece_count() > max_pieces {
             continue;
         }
+        if let Some(predicate) = &predicate {
+            if !predicate.matches(&material_key, num_games) {
+                continue;
+            }
+        }
         candidates.push(material_key);
     }
 
@@ -181,15 +241,46 @@ pub(crate) fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<()> {
         max_pieces
     );
 
+    // `TableBuilder::new` loads every child material's `.hbt` from disk, and a child always
+    // has strictly fewer pieces than its parent, so generating in ascending piece-count waves
+    // guarantees a key's children are already on disk (or excluded entirely) by the time its
+    // own wave runs. Tables within a wave are mutually independent and run across a thread
+    // pool; the wave boundary is the only synchronization needed.
+    let mut waves: BTreeMap<u32, Vec<MaterialKey>> = BTreeMap::new();
     for material_key in candidates {
-        let material_str = material_key.to_string();
-        let filename = format!("./data/heisenbase/{}.hbt", material_str);
-        if Path::new(&filename).exists() {
-            println!("Skipping {} (already exists)", material_str);
+        waves
+            .entry(material_key.total_piece_count())
+            .or_default()
+            .push(material_key);
+    }
+
+    for (piece_count, wave) in waves {
+        let wave: Vec<MaterialKey> = wave
+            .into_iter()
+            .filter(|material_key| {
+                let filename = format!("./data/heisenbase/{}.hbt", material_key);
+                if Path::new(&filename).exists() {
+                    println!("Skipping {} (already exists)", material_key);
+                    false
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        if wave.is_empty() {
             continue;
         }
-        println!("Generating {}", material_str);
-        run_generate(material_key)?;
+
+        println!(
+            "Generating wave of {}-piece material keys ({} tables)...",
+            piece_count,
+            wave.len()
+        );
+        wave.into_par_iter().try_for_each(|material_key| {
+            println!("Generating {}", material_key);
+            run_generate(material_key)
+        })?;
     }
 
     Ok(())
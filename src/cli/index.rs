@@ -39,6 +39,30 @@ pub fn run_index_init() -> Result<()> {
         "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS num_non_pawns INTEGER",
         [],
     )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS dtz_min BIGINT",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS dtz_max BIGINT",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS cursed_win INTEGER",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS blessed_loss INTEGER",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS syzygy_checked INTEGER",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE material_keys ADD COLUMN IF NOT EXISTS syzygy_mismatches INTEGER",
+        [],
+    )?;
 
     Ok(())
 }
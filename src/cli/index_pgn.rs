@@ -10,16 +10,18 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use bzip2::read::BzDecoder;
 use flate2::read::MultiGzDecoder;
 use heisenbase::material_key::MaterialKey;
 use heisenbase::position_indexer::PositionIndexer;
-use pgn_reader::{RawTag, Reader, SanPlus, Skip, Visitor};
+use pgn_reader::{RawComment, RawTag, Reader, SanPlus, Skip, Visitor};
 use polars::{
     error::PolarsError,
     prelude::{
         DataFrame, DataType, IntoLazy, LazyFrame, NamedFrom, ParquetWriter, Series, col, lit,
     },
 };
+use rayon::prelude::*;
 use shakmaty::{CastlingMode, Chess, Position, fen::Fen};
 
 const PGN_ROOT: &str = "./data/fishtest_pgns";
@@ -30,38 +32,63 @@ const INVALID_FEN_POSITION_PREFIX: &str = "invalid FEN position:";
 const CORRUPT_GZIP_PREFIX: &str = "corrupt gzip stream";
 pub const RAW_PARQUET_PATH: &str = "./data/pgn_index_raw.parquet";
 pub const PARQUET_PATH: &str = "./data/pgn_index.parquet";
+pub const EVALS_PARQUET_PATH: &str = "./data/pgn_evals.parquet";
+
+/// One `[%eval ...]` annotation observed on a position within [`MAX_NON_PAWN`], so a later
+/// stage can cross-check a built WDL table against what engines actually thought of its
+/// positions during play.
+struct EvalRecord {
+    material_key: MaterialKey,
+    position_index: u64,
+    eval_cp: Option<i32>,
+    eval_mate: Option<i32>,
+}
+
+/// One file's contribution to the index, kept separate so worker threads never share mutable
+/// state; [`run_stage1`] reduce-merges these back together once every file has been processed.
+#[derive(Default)]
+struct FileIndex {
+    counts_games: HashMap<MaterialKey, u64>,
+    counts_positions: HashMap<MaterialKey, u64>,
+    total_positions: u64,
+    games: u64,
+    evals: Vec<EvalRecord>,
+}
 
 pub fn run_stage1() -> io::Result<()> {
     let mut files = Vec::new();
     collect_pgn_files(Path::new(PGN_ROOT), &mut files)?;
     files.sort();
 
+    let worker_count = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .max(8);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(worker_count)
+        .build()
+        .map_err(io::Error::other)?;
+
+    let file_indexes: Vec<io::Result<FileIndex>> =
+        pool.install(|| files.par_iter().map(|path| process_file(path)).collect());
+
     let mut counts_games: HashMap<MaterialKey, u64> = HashMap::new();
     let mut counts_positions: HashMap<MaterialKey, u64> = HashMap::new();
     let mut total_games: u64 = 0;
     let mut total_positions: u64 = 0;
-
-    for path in files {
-        println!("Processing {}", path.display());
-        let file = File::open(&path)?;
-        let game_count = if is_gz(&path) {
-            process_reader(
-                MultiGzDecoder::new(file),
-                &mut counts_games,
-                &mut counts_positions,
-                &mut total_positions,
-                &path,
-            )?
-        } else {
-            process_reader(
-                file,
-                &mut counts_games,
-                &mut counts_positions,
-                &mut total_positions,
-                &path,
-            )?
-        };
-        total_games += game_count;
+    let mut evals: Vec<EvalRecord> = Vec::new();
+
+    for file_index in file_indexes {
+        let file_index = file_index?;
+        total_games += file_index.games;
+        total_positions += file_index.total_positions;
+        for (key, count) in file_index.counts_games {
+            *counts_games.entry(key).or_insert(0) += count;
+        }
+        for (key, count) in file_index.counts_positions {
+            *counts_positions.entry(key).or_insert(0) += count;
+        }
+        evals.extend(file_index.evals);
     }
 
     println!("Processed {total_games} games.");
@@ -70,10 +97,28 @@ pub fn run_stage1() -> io::Result<()> {
     entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
     write_raw_index(&entries, &counts_positions, total_games, total_positions)?;
+    write_evals_index(&evals)?;
 
     Ok(())
 }
 
+fn process_file(path: &Path) -> io::Result<FileIndex> {
+    println!("Processing {}", path.display());
+    process_reader(open_pgn_stream(path)?, path)
+}
+
+/// Open `path` through whichever decoder its extension calls for (`.gz`, `.zst`, `.bz2`, or
+/// none), so [`process_file`] doesn't need to know which codec it's reading.
+fn open_pgn_stream(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Ok(Box::new(MultiGzDecoder::new(file))),
+        Some("zst") => Ok(Box::new(zstd::stream::read::Decoder::new(file)?)),
+        Some("bz2") => Ok(Box::new(BzDecoder::new(file))),
+        _ => Ok(Box::new(file)),
+    }
+}
+
 pub fn run_stage2() -> io::Result<()> {
     let mut df = LazyFrame::scan_parquet(RAW_PARQUET_PATH, Default::default())
         .map_err(polars_to_io_error)?
@@ -108,19 +153,18 @@ pub fn run_stage2() -> io::Result<()> {
     Ok(())
 }
 
-fn process_reader<R: Read>(
-    reader: R,
-    counts_games: &mut HashMap<MaterialKey, u64>,
-    counts_positions: &mut HashMap<MaterialKey, u64>,
-    total_positions: &mut u64,
-    path: &Path,
-) -> io::Result<u64> {
+fn process_reader<R: Read>(reader: R, path: &Path) -> io::Result<FileIndex> {
+    let mut counts_games = HashMap::new();
+    let mut counts_positions = HashMap::new();
+    let mut total_positions = 0u64;
     let mut reader = Reader::new(reader);
     let mut visitor = IndexVisitor {
-        counts_games,
-        counts_positions,
-        total_positions,
+        counts_games: &mut counts_games,
+        counts_positions: &mut counts_positions,
+        total_positions: &mut total_positions,
         games: 0,
+        evals: Vec::new(),
+        malformed_evals: 0,
     };
     let mut skipped = SkipStats::default();
     loop {
@@ -134,9 +178,9 @@ fn process_reader<R: Read>(
                 }
             },
             Ok(None) => break,
-            Err(err) if is_corrupt_gzip_error(&err) => {
+            Err(err) if is_truncated_stream_error(&err) => {
                 eprintln!(
-                    "Stopped early due to corrupt gzip data in {}: {err}",
+                    "Stopped early due to a truncated or corrupted compressed stream in {}: {err}",
                     path.display()
                 );
                 break;
@@ -144,8 +188,17 @@ fn process_reader<R: Read>(
             Err(err) => return Err(err),
         }
     }
+    skipped.malformed_evals += visitor.malformed_evals;
     skipped.report(path);
-    Ok(visitor.games)
+    let games = visitor.games;
+    let evals = visitor.evals;
+    Ok(FileIndex {
+        counts_games,
+        counts_positions,
+        total_positions,
+        games,
+        evals,
+    })
 }
 
 fn write_raw_index(
@@ -188,6 +241,42 @@ fn write_raw_index(
     Ok(())
 }
 
+/// Write the engine-eval annotations gathered across every file to [`EVALS_PARQUET_PATH`], one
+/// row per observed `[%eval ...]` comment. `eval_cp` and `eval_mate` are mutually exclusive
+/// nullable columns rather than a single mixed-unit one, so a later cross-check can filter on
+/// whichever kind it cares about without first parsing the other back out.
+fn write_evals_index(evals: &[EvalRecord]) -> io::Result<()> {
+    let mut material_keys = Vec::with_capacity(evals.len());
+    let mut position_indices = Vec::with_capacity(evals.len());
+    let mut eval_cps = Vec::with_capacity(evals.len());
+    let mut eval_mates = Vec::with_capacity(evals.len());
+    for record in evals {
+        material_keys.push(record.material_key.to_string());
+        position_indices.push(record.position_index);
+        eval_cps.push(record.eval_cp);
+        eval_mates.push(record.eval_mate);
+    }
+
+    if let Some(parent) = Path::new(EVALS_PARQUET_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut df = DataFrame::new(vec![
+        Series::new("material_key", material_keys),
+        Series::new("position_index", position_indices),
+        Series::new("eval_cp", eval_cps),
+        Series::new("eval_mate", eval_mates),
+    ])
+    .map_err(polars_to_io_error)?;
+
+    let file = File::create(EVALS_PARQUET_PATH)?;
+    ParquetWriter::new(file)
+        .finish(&mut df)
+        .map_err(polars_to_io_error)?;
+
+    Ok(())
+}
+
 fn material_key_sizes(df: &DataFrame) -> io::Result<Vec<u64>> {
     let keys = df.column("material_key").map_err(polars_to_io_error)?;
     let keys = keys.str().map_err(polars_to_io_error)?;
@@ -215,6 +304,8 @@ struct IndexVisitor<'a> {
     counts_positions: &'a mut HashMap<MaterialKey, u64>,
     total_positions: &'a mut u64,
     games: u64,
+    evals: Vec<EvalRecord>,
+    malformed_evals: u64,
 }
 
 struct GameState {
@@ -306,6 +397,44 @@ impl<'a> Visitor for IndexVisitor<'a> {
         ControlFlow::Continue(())
     }
 
+    fn comment(
+        &mut self,
+        movetext: &mut Self::Movetext,
+        comment: RawComment<'_>,
+    ) -> ControlFlow<Self::Output> {
+        let Some(key) = MaterialKey::from_position(&movetext.position) else {
+            return ControlFlow::Continue(());
+        };
+        if key.non_pawn_piece_count() > MAX_NON_PAWN {
+            return ControlFlow::Continue(());
+        }
+        let Ok(text) = std::str::from_utf8(comment.as_bytes()) else {
+            self.malformed_evals += 1;
+            return ControlFlow::Continue(());
+        };
+        let (eval_cp, eval_mate) = match parse_eval_comment(text) {
+            EvalToken::NotPresent => return ControlFlow::Continue(()),
+            EvalToken::Malformed => {
+                self.malformed_evals += 1;
+                return ControlFlow::Continue(());
+            }
+            EvalToken::Parsed(eval_cp, eval_mate) => (eval_cp, eval_mate),
+        };
+        let Ok(position_index) =
+            PositionIndexer::new(key.clone()).position_to_index(&movetext.position)
+        else {
+            self.malformed_evals += 1;
+            return ControlFlow::Continue(());
+        };
+        self.evals.push(EvalRecord {
+            material_key: key,
+            position_index: position_index as u64,
+            eval_cp,
+            eval_mate,
+        });
+        ControlFlow::Continue(())
+    }
+
     fn end_game(&mut self, movetext: Self::Movetext) -> Self::Output {
         self.games += 1;
         for key in movetext.seen {
@@ -315,6 +444,39 @@ impl<'a> Visitor for IndexVisitor<'a> {
     }
 }
 
+/// Outcome of looking for a `[%eval ...]` token in a move comment.
+enum EvalToken {
+    /// The comment carries no `%eval` tag at all; nothing to record.
+    NotPresent,
+    /// A `%eval` tag is present but its value didn't parse; counted in [`SkipStats`].
+    Malformed,
+    /// `(eval_cp, eval_mate)`, with exactly one side set.
+    Parsed(Option<i32>, Option<i32>),
+}
+
+/// Parse a `[%eval <cp-or-mate>]` token out of a move comment. Mate scores are written as `#N`
+/// (or `#-N` for a losing mate); everything else is a pawn-unit score, scaled to centipawns.
+fn parse_eval_comment(text: &str) -> EvalToken {
+    let Some(start) = text.find("%eval") else {
+        return EvalToken::NotPresent;
+    };
+    let value = text[start + "%eval".len()..].trim_start();
+    let Some(value) = value.split(|c: char| c.is_whitespace() || c == ']').next() else {
+        return EvalToken::Malformed;
+    };
+    if let Some(mate) = value.strip_prefix('#') {
+        match mate.parse() {
+            Ok(mate) => EvalToken::Parsed(None, Some(mate)),
+            Err(_) => EvalToken::Malformed,
+        }
+    } else {
+        match value.parse::<f64>() {
+            Ok(cp) => EvalToken::Parsed(Some((cp * 100.0).round() as i32), None),
+            Err(_) => EvalToken::Malformed,
+        }
+    }
+}
+
 fn collect_pgn_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -331,7 +493,7 @@ fn collect_pgn_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
 fn is_pgn(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("pgn") => true,
-        Some("gz") => path
+        Some("gz") | Some("zst") | Some("bz2") => path
             .file_stem()
             .and_then(|stem| stem.to_str())
             .map(|stem| stem.ends_with(".pgn"))
@@ -340,19 +502,22 @@ fn is_pgn(path: &Path) -> bool {
     }
 }
 
-fn is_gz(path: &Path) -> bool {
-    path.extension().and_then(|ext| ext.to_str()) == Some("gz")
-}
-
 fn is_illegal_move_error(err: &io::Error) -> bool {
     err.kind() == io::ErrorKind::InvalidData && err.to_string().starts_with(ILLEGAL_MOVE_PREFIX)
 }
 
-fn is_corrupt_gzip_error(err: &io::Error) -> bool {
-    matches!(
+/// Whether `err` is a truncated or otherwise corrupted compressed stream for any of the codecs
+/// [`open_pgn_stream`] can return, so that file can be stopped early rather than aborting the
+/// whole run — mirrors the pre-existing gzip-specific check, generalized to also cover the
+/// zstd/bzip2 decoders. flate2 reports this as an `InvalidData`/`InvalidInput` error carrying
+/// [`CORRUPT_GZIP_PREFIX`]; the zstd and bzip2 readers instead surface a premature end of their
+/// compressed frame as a plain `UnexpectedEof`.
+fn is_truncated_stream_error(err: &io::Error) -> bool {
+    (matches!(
         err.kind(),
         io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput
-    ) && err.to_string().starts_with(CORRUPT_GZIP_PREFIX)
+    ) && err.to_string().starts_with(CORRUPT_GZIP_PREFIX))
+        || err.kind() == io::ErrorKind::UnexpectedEof
 }
 
 fn is_invalid_fen_tag_error(err: &io::Error) -> bool {
@@ -369,6 +534,7 @@ struct SkipStats {
     illegal_moves: u64,
     invalid_fen_tags: u64,
     invalid_fen_positions: u64,
+    malformed_evals: u64,
 }
 
 impl SkipStats {
@@ -394,6 +560,13 @@ impl SkipStats {
                 path.display()
             );
         }
+        if self.malformed_evals > 0 {
+            eprintln!(
+                "Skipped {} malformed [%eval ...] annotations in {}.",
+                self.malformed_evals,
+                path.display()
+            );
+        }
     }
 }
 
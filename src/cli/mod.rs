@@ -1,17 +1,27 @@
+mod filter_expr;
 mod generate;
 mod index;
 mod index_pgn;
 
 use clap::{Parser, Subcommand};
+use duckdb::{Connection, params};
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use shakmaty::{Chess, EnPassantMode, fen::Fen};
+use rayon::prelude::*;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, fen::Fen};
 use shakmaty_syzygy::{SyzygyError, Tablebase, Wdl};
-use std::{collections::HashSet, fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Result, anyhow, bail};
+use heisenbase::dtz_file::read_dtz_file;
+use heisenbase::dtz_table::DtzTable;
 use heisenbase::material_key::MaterialKey;
 use heisenbase::position_indexer::PositionIndexer;
-use heisenbase::wdl_file::read_wdl_file;
+use heisenbase::wdl_file::{WdlFileReader, inspect_wdl_file, verify_wdl_file};
 use heisenbase::wdl_score_range::WdlScoreRange;
 
 #[derive(Parser)]
@@ -36,16 +46,51 @@ enum Commands {
         /// Maximum total number of pieces allowed.
         #[arg(long, required = true)]
         max_pieces: u32,
+        /// Boolean expression over `pawns`, `pieces`, `games` and piece-role fields (`king`,
+        /// `queen`, `rook`, `bishop`, `knight`), e.g. `pawns = 0 AND NOT (rook > 0)`.
+        #[arg(long)]
+        filter: Option<String>,
     },
     /// Index fishtest PGN files into pgn_index_raw.parquet.
     PgnIndexStage1,
     /// Build the filtered PGN index with derived columns.
     PgnIndexStage2,
     /// Sample positions from heisenbase tables and compare against Syzygy WDL tables.
-    CheckAgainstSyzygy,
+    CheckAgainstSyzygy {
+        /// Check every valid position instead of sampling `SAMPLES_PER_TABLE` of them.
+        #[arg(long)]
+        exhaustive: bool,
+        /// Write one line per mismatch (material, index, both WDL values, FEN) to this file.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Keep cursed-win/blessed-loss distinct from true win/loss (probing the position
+        /// as-is via `probe_wdl` rather than after its next zeroing move), so a heisenbase
+        /// `Win`/`Loss` is only accepted against a true Syzygy win/loss, not a 50-move-rule
+        /// draw in disguise.
+        #[arg(long)]
+        fifty_move: bool,
+    },
     /// Initialize the DuckDB material key index.
     #[command(name = "Ã¬ndex-init")]
     IndexInit,
+    /// Read FENs from stdin, one per line, and print the WDL (and DTZ, if generated) for each.
+    Probe {
+        /// Directories to search for `.hbt`/`.hbz` tables, in priority order, joined with the
+        /// platform path separator (the same convention as Syzygy's `SyzygyPath`). Defaults to
+        /// `./data/heisenbase`.
+        #[arg(long)]
+        tables: Option<String>,
+    },
+    /// Print structural statistics about a compressed `.hbt` file.
+    Inspect {
+        /// Path to the `.hbt` file to inspect.
+        path: PathBuf,
+    },
+    /// Decompress every block of a `.hbt` file and confirm it's internally consistent.
+    Verify {
+        /// Path to the `.hbt` file to verify.
+        path: PathBuf,
+    },
 }
 
 /// Parse CLI arguments and execute the requested command.
@@ -61,8 +106,9 @@ pub fn run() -> Result<()> {
         Commands::GenerateMany {
             min_games,
             max_pieces,
+            filter,
         } => {
-            generate::run_generate_many(min_games, max_pieces)?;
+            generate::run_generate_many(min_games, max_pieces, filter)?;
         }
         Commands::PgnIndexStage1 => {
             index_pgn::run_stage1()?;
@@ -70,12 +116,25 @@ pub fn run() -> Result<()> {
         Commands::PgnIndexStage2 => {
             index_pgn::run_stage2()?;
         }
-        Commands::CheckAgainstSyzygy => {
-            run_check_against_syzygy()?;
+        Commands::CheckAgainstSyzygy {
+            exhaustive,
+            output,
+            fifty_move,
+        } => {
+            run_check_against_syzygy(exhaustive, output, fifty_move)?;
         }
         Commands::IndexInit => {
             index::run_index_init()?;
         }
+        Commands::Probe { tables } => {
+            run_probe(tables)?;
+        }
+        Commands::Inspect { path } => {
+            run_inspect(&path)?;
+        }
+        Commands::Verify { path } => {
+            run_verify(&path)?;
+        }
     }
 
     Ok(())
@@ -111,6 +170,26 @@ fn heisenbase_allows(wdl: WdlScoreRange, syzygy: SimpleWdl) -> bool {
     }
 }
 
+/// `--fifty-move` variant of [`heisenbase_allows`]: keeps `CursedWin`/`BlessedLoss` distinct
+/// from a true win/loss, rather than folding them together via [`simplify_wdl`], so a
+/// heisenbase `Win`/`Loss` is only accepted against a Syzygy value that is actually winning or
+/// losing, not one that is a draw in practice under the 50-move rule.
+fn heisenbase_allows_fifty_move(wdl: WdlScoreRange, syzygy: Wdl) -> bool {
+    match wdl {
+        WdlScoreRange::Win => syzygy == Wdl::Win,
+        WdlScoreRange::Draw => matches!(syzygy, Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss),
+        WdlScoreRange::Loss => syzygy == Wdl::Loss,
+        WdlScoreRange::WinOrDraw => {
+            matches!(syzygy, Wdl::Win | Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss)
+        }
+        WdlScoreRange::DrawOrLoss => {
+            matches!(syzygy, Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss | Wdl::Loss)
+        }
+        WdlScoreRange::Unknown => true,
+        WdlScoreRange::IllegalPosition => false,
+    }
+}
+
 fn material_keys_from_dir(dir: &Path) -> Result<Vec<MaterialKey>> {
     let mut keys = HashSet::new();
     for entry in fs::read_dir(dir)? {
@@ -148,7 +227,145 @@ fn collect_valid_indices(indexer: &PositionIndexer) -> Vec<usize> {
     valid
 }
 
-fn run_check_against_syzygy() -> Result<()> {
+/// Record a material key's Syzygy cross-validation result in the DuckDB index, alongside the
+/// WDL/DTZ stats [`generate::run_generate`] logs there.
+fn log_syzygy_check_to_index(
+    material: &MaterialKey,
+    checked: usize,
+    mismatches: usize,
+) -> Result<()> {
+    let heisenbase_dir = Path::new("./data/heisenbase");
+    let conn = Connection::open(heisenbase_dir.join("index.duckdb"))?;
+    conn.execute(
+        "UPDATE material_keys SET syzygy_checked = ?, syzygy_mismatches = ? WHERE name = ?",
+        params![checked as i64, mismatches as i64, material.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Outcome of cross-validating one material key's table against Syzygy.
+struct MaterialCheckOutcome {
+    material: MaterialKey,
+    positions_checked: usize,
+    mismatches: usize,
+    uncertain: usize,
+    missing_table: bool,
+    probe_failed: bool,
+    /// One line per mismatch (material, index, both WDL values, FEN), written out only when
+    /// `--output` is in use.
+    mismatch_lines: Vec<String>,
+}
+
+/// Cross-validate one material key's `.hbt` table against Syzygy, either over
+/// `SAMPLES_PER_TABLE` random indices or, in `exhaustive` mode, every valid index.
+///
+/// Independent of every other material key, so [`run_check_against_syzygy`] calls this once
+/// per table from a `rayon` parallel iterator rather than a sequential loop.
+fn check_material(
+    material: MaterialKey,
+    heisenbase_dir: &Path,
+    tablebase: &Tablebase<Chess>,
+    exhaustive: bool,
+    fifty_move: bool,
+) -> Result<MaterialCheckOutcome> {
+    let table_path = heisenbase_dir.join(format!("{}.hbt", material));
+    let mut table = WdlFileReader::open(&table_path)?;
+    let indexer = PositionIndexer::new(material.clone());
+    let valid_indices = collect_valid_indices(&indexer);
+    if valid_indices.is_empty() {
+        eprintln!("No valid positions for {}", material);
+        return Ok(MaterialCheckOutcome {
+            material,
+            positions_checked: 0,
+            mismatches: 0,
+            uncertain: 0,
+            missing_table: false,
+            probe_failed: false,
+            mismatch_lines: Vec::new(),
+        });
+    }
+
+    let indices: Vec<usize> = if exhaustive {
+        valid_indices
+    } else {
+        let mut rng = StdRng::from_entropy();
+        (0..SAMPLES_PER_TABLE)
+            .map(|_| valid_indices[rng.gen_range(0..valid_indices.len())])
+            .collect()
+    };
+
+    let mut positions_checked = 0usize;
+    let mut mismatches = 0usize;
+    let mut uncertain = 0usize;
+    let mut missing_table = false;
+    let mut probe_failed = false;
+    let mut mismatch_lines = Vec::new();
+
+    for idx in indices {
+        let pos = match indexer.index_to_position(idx) {
+            Ok(pos) => pos,
+            Err(_) => continue,
+        };
+
+        let hb_wdl = table.probe_index(idx)?;
+        if hb_wdl.is_uncertain() {
+            uncertain += 1;
+        }
+
+        let syzygy_wdl = if fifty_move {
+            tablebase.probe_wdl(&pos)
+        } else {
+            tablebase.probe_wdl_after_zeroing(&pos)
+        };
+        let syzygy_wdl = match syzygy_wdl {
+            Ok(wdl) => wdl,
+            Err(SyzygyError::MissingTable { .. }) => {
+                missing_table = true;
+                break;
+            }
+            Err(_) => {
+                probe_failed = true;
+                break;
+            }
+        };
+
+        positions_checked += 1;
+        let allowed = if fifty_move {
+            heisenbase_allows_fifty_move(hb_wdl, syzygy_wdl)
+        } else {
+            heisenbase_allows(hb_wdl, simplify_wdl(syzygy_wdl))
+        };
+        if !allowed {
+            mismatches += 1;
+            let fen = Fen::from_position(&pos, EnPassantMode::Legal).to_string();
+            if !exhaustive && mismatches <= MAX_MISMATCHES_PER_TABLE {
+                println!(
+                    "Mismatch {}: hb={:?}, syzygy={:?}, fen={}",
+                    material, hb_wdl, syzygy_wdl, fen
+                );
+            }
+            mismatch_lines.push(format!(
+                "{material}\t{idx}\thb={hb_wdl:?}\tsyzygy={syzygy_wdl:?}\tfen={fen}"
+            ));
+        }
+    }
+
+    Ok(MaterialCheckOutcome {
+        material,
+        positions_checked,
+        mismatches,
+        uncertain,
+        missing_table,
+        probe_failed,
+        mismatch_lines,
+    })
+}
+
+fn run_check_against_syzygy(
+    exhaustive: bool,
+    output: Option<PathBuf>,
+    fifty_move: bool,
+) -> Result<()> {
     let heisenbase_dir = Path::new("./data/heisenbase");
     let syzygy_dir = Path::new("./data/syzygy");
 
@@ -169,13 +386,20 @@ fn run_check_against_syzygy() -> Result<()> {
         }
     }
 
-    let mut rng = StdRng::from_entropy();
+    if exhaustive {
+        println!("Running exhaustive check over every valid position (parallel across tables)...");
+    }
+    if fifty_move {
+        println!("Running in --fifty-move mode: probing as-is and requiring true win/loss match.");
+    }
+
     let mut total_tables = 0usize;
     let mut total_positions = 0usize;
     let mut total_mismatches = 0usize;
     let mut total_uncertain = 0usize;
     let mut missing_tables = 0usize;
     let mut probe_errors = 0usize;
+    let mut all_mismatch_lines = Vec::new();
 
     for (label, keys) in [("3-man", three_man), ("4-man", four_man)] {
         println!(
@@ -183,83 +407,63 @@ fn run_check_against_syzygy() -> Result<()> {
             label,
             keys.len()
         );
-        for material in keys {
-            total_tables += 1;
-            let table_path = heisenbase_dir.join(format!("{}.hbt", material));
-            let table = read_wdl_file(&table_path)?;
-            let indexer = PositionIndexer::new(material.clone());
-            let valid_indices = collect_valid_indices(&indexer);
-            if valid_indices.is_empty() {
-                eprintln!("No valid positions for {}", material);
-                continue;
-            }
+        total_tables += keys.len();
 
-            let mut mismatches = 0usize;
-            let mut uncertain = 0usize;
-            let mut missing_table = false;
-            let mut probe_failed = false;
-
-            for _ in 0..SAMPLES_PER_TABLE {
-                let idx = valid_indices[rng.gen_range(0..valid_indices.len())];
-                let pos = match indexer.index_to_position(idx) {
-                    Ok(pos) => pos,
-                    Err(_) => continue,
-                };
-
-                let hb_wdl = table.positions[idx];
-                if hb_wdl.is_uncertain() {
-                    uncertain += 1;
-                }
-
-                let syzygy_wdl = match tablebase.probe_wdl_after_zeroing(&pos) {
-                    Ok(wdl) => wdl,
-                    Err(SyzygyError::MissingTable { .. }) => {
-                        missing_table = true;
-                        break;
-                    }
-                    Err(_) => {
-                        probe_failed = true;
-                        break;
-                    }
-                };
-
-                let syzygy_simple = simplify_wdl(syzygy_wdl);
-                if !heisenbase_allows(hb_wdl, syzygy_simple) {
-                    mismatches += 1;
-                    if mismatches <= MAX_MISMATCHES_PER_TABLE {
-                        let fen = Fen::from_position(&pos, EnPassantMode::Legal).to_string();
-                        println!(
-                            "Mismatch {}: hb={:?}, syzygy={:?}, fen={}",
-                            material, hb_wdl, syzygy_wdl, fen
-                        );
-                    }
-                }
-            }
+        let outcomes: Vec<Result<MaterialCheckOutcome>> = keys
+            .into_par_iter()
+            .map(|material| {
+                check_material(material, heisenbase_dir, &tablebase, exhaustive, fifty_move)
+            })
+            .collect();
 
-            if missing_table {
+        for outcome in outcomes {
+            let outcome = outcome?;
+            if outcome.missing_table {
                 missing_tables += 1;
-                eprintln!("Missing Syzygy tables for {}", material);
+                eprintln!("Missing Syzygy tables for {}", outcome.material);
                 continue;
             }
-            if probe_failed {
+            if outcome.probe_failed {
                 probe_errors += 1;
-                eprintln!("Syzygy probe failed for {}", material);
+                eprintln!("Syzygy probe failed for {}", outcome.material);
+                continue;
+            }
+            if outcome.positions_checked == 0 {
                 continue;
             }
 
-            total_positions += SAMPLES_PER_TABLE;
-            total_mismatches += mismatches;
-            total_uncertain += uncertain;
+            total_positions += outcome.positions_checked;
+            total_mismatches += outcome.mismatches;
+            total_uncertain += outcome.uncertain;
+            log_syzygy_check_to_index(
+                &outcome.material,
+                outcome.positions_checked,
+                outcome.mismatches,
+            )?;
 
-            if mismatches > 0 {
+            if outcome.mismatches > 0 {
                 println!(
                     "Found {} mismatches in {} ({} uncertain samples).",
-                    mismatches, material, uncertain
+                    outcome.mismatches, outcome.material, outcome.uncertain
                 );
             }
+            all_mismatch_lines.extend(outcome.mismatch_lines);
         }
     }
 
+    if let Some(output_path) = &output {
+        let mut contents = all_mismatch_lines.join("\n");
+        if !all_mismatch_lines.is_empty() {
+            contents.push('\n');
+        }
+        fs::write(output_path, contents)?;
+        println!(
+            "Wrote {} mismatch line(s) to {}",
+            all_mismatch_lines.len(),
+            output_path.display()
+        );
+    }
+
     println!(
         "Checked {} tables ({} positions).",
         total_tables, total_positions
@@ -279,3 +483,161 @@ fn run_check_against_syzygy() -> Result<()> {
 
     Ok(())
 }
+
+/// Lazily opens and caches `.hbt`/`.hbz` tables across one or more directories, keyed by
+/// [`MaterialKey`], so [`run_probe`] only pays to load a table the first time a FEN needs it.
+struct ProbeTables {
+    dirs: Vec<PathBuf>,
+    wdl_readers: HashMap<MaterialKey, WdlFileReader>,
+    dtz_tables: HashMap<MaterialKey, Option<DtzTable>>,
+}
+
+impl ProbeTables {
+    fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            wdl_readers: HashMap::new(),
+            dtz_tables: HashMap::new(),
+        }
+    }
+
+    /// First existing `{material}.{extension}` across `dirs`, in priority order.
+    fn find_file(&self, material: &MaterialKey, extension: &str) -> Option<PathBuf> {
+        self.dirs.iter().find_map(|dir| {
+            let path = dir.join(format!("{material}.{extension}"));
+            path.exists().then_some(path)
+        })
+    }
+
+    fn wdl_reader(&mut self, material: &MaterialKey) -> Result<&mut WdlFileReader> {
+        if !self.wdl_readers.contains_key(material) {
+            let path = self
+                .find_file(material, "hbt")
+                .ok_or_else(|| anyhow!("no .hbt table found for {material}"))?;
+            self.wdl_readers
+                .insert(material.clone(), WdlFileReader::open(&path)?);
+        }
+        Ok(self.wdl_readers.get_mut(material).unwrap())
+    }
+
+    /// `None` if no `.hbz` file exists for `material`; DTZ tables are optional, unlike WDL.
+    fn dtz_table(&mut self, material: &MaterialKey) -> Result<Option<&DtzTable>> {
+        if !self.dtz_tables.contains_key(material) {
+            let table = match self.find_file(material, "hbz") {
+                Some(path) => Some(read_dtz_file(&path)?),
+                None => None,
+            };
+            self.dtz_tables.insert(material.clone(), table);
+        }
+        Ok(self.dtz_tables.get(material).unwrap().as_ref())
+    }
+
+    /// Probe a single position, returning its WDL value and, if a `.hbz` table is available,
+    /// its DTZ (half-moves to the next zeroing move, signed positive for a win and negative
+    /// for a loss).
+    fn probe(&mut self, position: &Chess) -> Result<(WdlScoreRange, Option<i64>)> {
+        let material = MaterialKey::from_position(position)
+            .ok_or_else(|| anyhow!("position has no valid material key"))?;
+        let indexer = PositionIndexer::new(material.clone());
+        let index = indexer
+            .position_to_index(position)
+            .map_err(|err| anyhow!("position not indexable for {material}: {err:?}"))?;
+
+        let wdl = self.wdl_reader(&material)?.probe_index(index)?;
+        let dtz = self.dtz_table(&material)?.and_then(|table| {
+            let value = table.positions[index].to_storage_value();
+            (value != i8::MIN).then_some(value as i64)
+        });
+
+        Ok((wdl, dtz))
+    }
+}
+
+fn run_probe(table_dirs: Option<String>) -> Result<()> {
+    let dirs: Vec<PathBuf> = match table_dirs {
+        Some(paths) => std::env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("./data/heisenbase")],
+    };
+    let mut tables = ProbeTables::new(dirs);
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let fen = line.trim();
+        if fen.is_empty() {
+            continue;
+        }
+
+        let parsed = Fen::from_ascii(fen.as_bytes())
+            .map_err(|err| anyhow!("invalid FEN: {err}"))
+            .and_then(|f| {
+                f.into_position::<Chess>(CastlingMode::Standard)
+                    .map_err(|err| anyhow!("illegal position: {err}"))
+            });
+        let position = match parsed {
+            Ok(position) => position,
+            Err(err) => {
+                println!("error: {fen}: {err}");
+                continue;
+            }
+        };
+
+        match tables.probe(&position) {
+            Ok((wdl, Some(dtz))) => println!("{fen}: {wdl:?} dtz={dtz}"),
+            Ok((wdl, None)) => println!("{fen}: {wdl:?}"),
+            Err(err) => println!("error: {fen}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn run_inspect(path: &Path) -> Result<()> {
+    let (material, total_positions, blocks) = inspect_wdl_file(path)?;
+
+    println!("Material: {material}");
+    println!("Positions: {total_positions}");
+    println!("Blocks: {}", blocks.len());
+
+    let mut scheme_counts: HashMap<&str, usize> = HashMap::new();
+    let mut code_len_histogram: HashMap<u8, usize> = HashMap::new();
+    let mut total_bitstream_bytes = 0usize;
+    let mut total_generated_symbols = 0usize;
+    for block in &blocks {
+        *scheme_counts.entry(block.scheme_name).or_insert(0) += 1;
+        for (&len, &count) in &block.code_len_histogram {
+            *code_len_histogram.entry(len).or_insert(0) += count;
+        }
+        total_bitstream_bytes += block.bitstream_bytes;
+        total_generated_symbols += block.generated_symbols;
+    }
+
+    println!("Schemes: {scheme_counts:?}");
+    println!("Generated symbols (pair substitution blocks): {total_generated_symbols}");
+    println!("Bitstream bytes: {total_bitstream_bytes}");
+
+    let mut lens: Vec<u8> = code_len_histogram.keys().copied().collect();
+    lens.sort_unstable();
+    println!("Huffman code-length histogram:");
+    for len in lens {
+        println!("  {len} bits: {} symbols", code_len_histogram[&len]);
+    }
+
+    if total_positions > 0 {
+        let ratio = total_bitstream_bytes as f64 / total_positions as f64;
+        println!(
+            "Compression ratio vs 1 byte/position: {:.2}% ({total_bitstream_bytes} / {total_positions} bytes)",
+            ratio * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+fn run_verify(path: &Path) -> Result<()> {
+    let total_positions = verify_wdl_file(path)?;
+    println!(
+        "OK: {total_positions} positions decoded successfully from {}",
+        path.display()
+    );
+    Ok(())
+}
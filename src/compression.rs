@@ -2,6 +2,7 @@
 // Syzygy tablebases.  It performs pair substitution followed by a
 // canonical Huffman coding of the resulting symbol stream.
 
+use crate::crc32::Crc32;
 use crate::wdl_score_range::WdlScoreRange;
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap};
@@ -20,33 +21,125 @@ struct HuffmanNode {
     symbol: Option<u16>,
 }
 
+/// Which encoding a [`CompressedWdl`] uses for its payload.
+#[derive(Debug, Clone)]
+pub enum CompressionScheme {
+    /// RE-PAIR-style pair substitution ([`pair_substitution`]) followed by a single canonical
+    /// Huffman code over the merged symbol alphabet. The better fit for long-range repeats of
+    /// a *small* alphabet, since merged symbols compound across rounds.
+    PairSubstitution {
+        /// Number of base symbols. For `WdlScoreRange` this is fixed but storing it makes the
+        /// format self contained.
+        base_symbols: u16,
+        /// Pairs for newly created symbols. Symbol `i` (>= base_symbols) expands to
+        /// `sym_pairs[i - base_symbols]`.
+        sym_pairs: Vec<(u16, u16)>,
+        /// Huffman code lengths for all symbols (base and generated).
+        code_lens: Vec<u8>,
+        /// Whether [`WdlScoreRange::Unknown`] positions were treated as free to rewrite
+        /// alongside `IllegalPosition`, and so may no longer decode back to `Unknown`.
+        /// Recorded so a reader knows not to rely on `Unknown` having round-tripped.
+        mask_unknown: bool,
+    },
+    /// LZ77/DEFLATE-style back-reference matching ([`lz77_tokens`]): literals and
+    /// `(length, distance)` matches against a sliding window, with the literal/length and
+    /// distance alphabets canonical-Huffman-coded separately. The better fit for long exact
+    /// repeats at a distance pair substitution's symbol-doubling can't reach in one table scan.
+    Lz77 {
+        /// Huffman code lengths over the combined literal/length alphabet: symbol `v < 9` is
+        /// literal WDL value `v`; symbol `9 + (length - MIN_MATCH)` is a match of `length`.
+        literal_code_lens: Vec<u8>,
+        /// Huffman code lengths over the distance alphabet: symbol `d` is a match distance of
+        /// `d + 1`.
+        distance_code_lens: Vec<u8>,
+    },
+}
+
 /// Result of compressing a sequence of `WdlScoreRange` values.
 #[derive(Debug, Clone)]
 pub struct CompressedWdl {
-    /// Number of base symbols. For `WdlScoreRange` this is fixed but storing it
-    /// makes the format self contained.
-    pub base_symbols: u16,
-    /// Pairs for newly created symbols. Symbol `i` (>= base_symbols) expands to
-    /// `sym_pairs[i - base_symbols]`.
-    pub sym_pairs: Vec<(u16, u16)>,
-    /// Huffman code lengths for all symbols (base and generated).
-    pub code_lens: Vec<u8>,
+    pub scheme: CompressionScheme,
     /// Encoded bit stream.
     pub bitstream: Vec<u8>,
     /// Number of valid bits in `bitstream`.
     pub bit_len: usize,
     /// Length of the decompressed table.
     pub orig_len: usize,
+    /// CRC-32 over `scheme`, `bit_len`, `bitstream` and `orig_len`, set by whichever
+    /// `compress_wdl*` function built this block. [`wdl_file`](crate::wdl_file) persists it
+    /// alongside the block and checks it on read, before decoding gets a chance to run on
+    /// corrupted data.
+    pub checksum: u32,
+}
+
+impl CompressedWdl {
+    /// Recompute the CRC-32 of this block's contents from scratch, folding each field in via
+    /// [`Crc32::update`] rather than assembling one contiguous buffer first.
+    pub(crate) fn compute_checksum(&self) -> u32 {
+        let mut crc = Crc32::new();
+        match &self.scheme {
+            CompressionScheme::PairSubstitution {
+                base_symbols,
+                sym_pairs,
+                code_lens,
+                mask_unknown,
+            } => {
+                crc.update(&[SCHEME_TAG_PAIR_SUBSTITUTION]);
+                crc.update(&base_symbols.to_le_bytes());
+                for &(a, b) in sym_pairs {
+                    crc.update(&a.to_le_bytes());
+                    crc.update(&b.to_le_bytes());
+                }
+                crc.update(code_lens);
+                crc.update(&[*mask_unknown as u8]);
+            }
+            CompressionScheme::Lz77 {
+                literal_code_lens,
+                distance_code_lens,
+            } => {
+                crc.update(&[SCHEME_TAG_LZ77]);
+                crc.update(literal_code_lens);
+                crc.update(distance_code_lens);
+            }
+        }
+        crc.update(&self.bit_len.to_le_bytes());
+        crc.update(&self.bitstream);
+        crc.update(&self.orig_len.to_le_bytes());
+        crc.finish()
+    }
 }
 
+/// Tag fed into [`CompressedWdl::compute_checksum`] so switching `scheme` variants always
+/// changes the checksum, even if the rest of the fields happened to coincide.
+const SCHEME_TAG_PAIR_SUBSTITUTION: u8 = 0;
+const SCHEME_TAG_LZ77: u8 = 1;
+
 /// Compress a slice of `WdlScoreRange` values using pair substitution and
 /// Huffman coding.
+///
+/// Equivalent to [`compress_wdl_with_options`] with `mask_unknown: false`, which is the right
+/// choice for most callers: a caller that wants its `Unknown` positions preserved (rather than
+/// overwritten to whatever concrete value compresses best) gets exactly that.
 pub fn compress_wdl(values: &[WdlScoreRange]) -> CompressedWdl {
-    let base_symbols = 7u16; // number of possible WDL values
+    compress_wdl_with_options(values, false)
+}
+
+/// Compress a slice of `WdlScoreRange` values, optionally also treating `Unknown` positions as
+/// free to rewrite (not just `IllegalPosition`, which is always free).
+///
+/// `Unknown` means "could be a win, draw or loss" — callers already treat it as unconstrained
+/// (see `heisenbase_allows`), so overwriting it with whichever concrete value best serves
+/// compression is safe *as long as the caller doesn't need `Unknown` itself to round-trip*.
+/// Set `mask_unknown` only when that's true of your use case.
+pub fn compress_wdl_with_options(values: &[WdlScoreRange], mask_unknown: bool) -> CompressedWdl {
+    let base_symbols = 9u16; // number of possible WDL values
     let mut raw: Vec<u8> = values.iter().map(|&v| u8::from(v)).collect();
     let illegal_code = u8::from(WdlScoreRange::IllegalPosition);
+    let unknown_code = u8::from(WdlScoreRange::Unknown);
 
-    rewrite_illegal_runs(&mut raw, illegal_code);
+    rewrite_free_runs(&mut raw, |v| {
+        v == illegal_code || (mask_unknown && v == unknown_code)
+    });
 
     let seq: Vec<u16> = raw.into_iter().map(u16::from).collect();
 
@@ -72,34 +165,327 @@ pub fn compress_wdl(values: &[WdlScoreRange]) -> CompressedWdl {
         }
     }
 
-    CompressedWdl {
-        base_symbols,
-        sym_pairs,
-        code_lens,
+    let mut compressed = CompressedWdl {
+        scheme: CompressionScheme::PairSubstitution {
+            base_symbols,
+            sym_pairs,
+            code_lens,
+            mask_unknown,
+        },
         bitstream,
         bit_len,
         orig_len: values.len(),
-    }
+        checksum: 0,
+    };
+    compressed.checksum = compressed.compute_checksum();
+    compressed
 }
 
-/// Decompress a previously compressed WDL table.
+/// Decompress a previously compressed WDL table, dispatching on its [`CompressionScheme`].
 pub fn decompress_wdl(data: &CompressedWdl) -> Vec<WdlScoreRange> {
-    let codes = build_codes_from_lengths(&data.code_lens);
+    let raw = match &data.scheme {
+        CompressionScheme::PairSubstitution {
+            base_symbols,
+            sym_pairs,
+            code_lens,
+            ..
+        } => decompress_pair_substitution(data, *base_symbols, sym_pairs, code_lens),
+        CompressionScheme::Lz77 {
+            literal_code_lens,
+            distance_code_lens,
+        } => decompress_lz77(data, literal_code_lens, distance_code_lens),
+    };
+
+    raw.into_iter()
+        .map(|v| WdlScoreRange::try_from(v).expect("invalid wdl value"))
+        .collect()
+}
+
+fn decompress_pair_substitution(
+    data: &CompressedWdl,
+    base_symbols: u16,
+    sym_pairs: &[(u16, u16)],
+    code_lens: &[u8],
+) -> Vec<u8> {
+    let codes = build_codes_from_lengths(code_lens);
     let nodes = build_decoding_tree(&codes);
     let seq = decode_bitstream(&data.bitstream, data.bit_len, &nodes, data.orig_len);
 
     // Expand symbols back to base values
     let mut output: Vec<u16> = Vec::new();
     for sym in seq {
-        expand_symbol(sym, &data.sym_pairs, data.base_symbols, &mut output);
+        expand_symbol(sym, sym_pairs, base_symbols, &mut output);
     }
     assert_eq!(output.len(), data.orig_len);
 
-    output
-        .into_iter()
-        .map(|v| WdlScoreRange::try_from(v as u8).expect("invalid wdl value"))
-        .collect()
+    output.into_iter().map(|v| v as u8).collect()
+}
+/// Match-finding effort for [`compress_wdl_lz77`], mirroring DEFLATE's `Fast`/`Best` levels:
+/// how many prior occurrences of a position's hash key to try before settling for the longest
+/// match found so far, and how long a match is allowed to grow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeflateMode {
+    Fast,
+    Best,
+}
+
+impl DeflateMode {
+    fn chain_depth(self) -> usize {
+        match self {
+            DeflateMode::Fast => 8,
+            DeflateMode::Best => 128,
+        }
+    }
+
+    fn max_match(self) -> usize {
+        match self {
+            DeflateMode::Fast => 32,
+            DeflateMode::Best => MAX_MATCH,
+        }
+    }
+}
+
+/// Shortest and longest back-reference [`lz77_tokens`] will emit. `MIN_MATCH` also sets the
+/// hash key width (three symbols), below which a match can never pay for its own encoding.
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lz77Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+/// Find the longest match starting at `pos`, if any, among the positions chained under `pos`'s
+/// 3-symbol hash key, trying at most `chain_depth` of the most recent ones.
+fn best_match(
+    raw: &[u8],
+    chains: &HashMap<(u8, u8, u8), Vec<usize>>,
+    pos: usize,
+    max_match: usize,
+    chain_depth: usize,
+) -> Option<(usize, usize)> {
+    if pos + MIN_MATCH > raw.len() {
+        return None;
+    }
+    let key = (raw[pos], raw[pos + 1], raw[pos + 2]);
+    let candidates = chains.get(&key)?;
+
+    let max_len = (raw.len() - pos).min(max_match);
+    let mut best: Option<(usize, usize)> = None;
+    for &start in candidates.iter().rev().take(chain_depth) {
+        let mut len = 0;
+        while len < max_len && raw[start + len] == raw[pos + len] {
+            len += 1;
+        }
+        if len >= MIN_MATCH && best.is_none_or(|(best_len, _)| len > best_len) {
+            best = Some((len, pos - start));
+        }
+    }
+    best
+}
+
+/// Tokenize `raw` into literals and `(length, distance)` back-references using LZ77 match
+/// finding over a hash chain keyed on 3-symbol tuples, with one-step lazy matching: a match is
+/// only taken if the position right after it doesn't start an even longer one.
+fn lz77_tokens(raw: &[u8], mode: DeflateMode) -> Vec<Lz77Token> {
+    let chain_depth = mode.chain_depth();
+    let max_match = mode.max_match();
+    let mut chains: HashMap<(u8, u8, u8), Vec<usize>> = HashMap::new();
+    let mut tokens = Vec::new();
+
+    let mut pos = 0;
+    while pos < raw.len() {
+        let current = best_match(raw, &chains, pos, max_match, chain_depth);
+
+        if pos + MIN_MATCH <= raw.len() {
+            let key = (raw[pos], raw[pos + 1], raw[pos + 2]);
+            chains.entry(key).or_default().push(pos);
+        }
+
+        let Some((length, distance)) = current else {
+            tokens.push(Lz77Token::Literal(raw[pos]));
+            pos += 1;
+            continue;
+        };
+
+        let better_next = pos + 1 < raw.len()
+            && best_match(raw, &chains, pos + 1, max_match, chain_depth)
+                .is_some_and(|(next_len, _)| next_len > length);
+        if better_next {
+            tokens.push(Lz77Token::Literal(raw[pos]));
+            pos += 1;
+            continue;
+        }
+
+        tokens.push(Lz77Token::Match { length, distance });
+        for p in (pos + 1)..(pos + length).min(raw.len()) {
+            if p + MIN_MATCH <= raw.len() {
+                chains
+                    .entry((raw[p], raw[p + 1], raw[p + 2]))
+                    .or_default()
+                    .push(p);
+            }
+        }
+        pos += length;
+    }
+
+    tokens
+}
+
+/// Compress a slice of `WdlScoreRange` values with LZ77 back-reference matching instead of
+/// pair substitution, better suited to long exact repeats at a distance (see
+/// [`CompressionScheme::Lz77`]).
+pub fn compress_wdl_lz77(values: &[WdlScoreRange], mode: DeflateMode) -> CompressedWdl {
+    let raw: Vec<u8> = values.iter().map(|&v| u8::from(v)).collect();
+    let tokens = lz77_tokens(&raw, mode);
+
+    let literal_len_symbols = 9 + (MAX_MATCH - MIN_MATCH + 1);
+    let distance_symbols = raw.len().max(1);
+
+    let mut literal_len_seq: Vec<u16> = Vec::with_capacity(tokens.len());
+    let mut distance_seq: Vec<u16> = Vec::new();
+    for tok in &tokens {
+        match *tok {
+            Lz77Token::Literal(v) => literal_len_seq.push(v as u16),
+            Lz77Token::Match { length, distance } => {
+                literal_len_seq.push((9 + (length - MIN_MATCH)) as u16);
+                distance_seq.push((distance - 1) as u16);
+            }
+        }
+    }
+
+    let literal_code_lens = build_huffman_code_lengths(&literal_len_seq, literal_len_symbols);
+    let distance_code_lens = build_huffman_code_lengths(&distance_seq, distance_symbols);
+    let literal_codes = build_codes_from_lengths(&literal_code_lens);
+    let distance_codes = build_codes_from_lengths(&distance_code_lens);
+
+    let mut bits: Vec<u8> = Vec::new();
+    let mut distances = distance_seq.into_iter();
+    for &sym in &literal_len_seq {
+        let (code, len) = literal_codes[sym as usize];
+        for i in (0..len).rev() {
+            bits.push(((code >> i) & 1) as u8);
+        }
+        if sym as usize >= 9 {
+            let dsym = distances
+                .next()
+                .expect("a length symbol always has a matching distance");
+            let (dcode, dlen) = distance_codes[dsym as usize];
+            for i in (0..dlen).rev() {
+                bits.push(((dcode >> i) & 1) as u8);
+            }
+        }
+    }
+
+    let bit_len = bits.len();
+    let mut bitstream = vec![0u8; (bit_len + 7) / 8];
+    for (i, bit) in bits.into_iter().enumerate() {
+        if bit == 1 {
+            bitstream[i / 8] |= 1 << (7 - (i % 8));
+        }
+    }
+
+    let mut compressed = CompressedWdl {
+        scheme: CompressionScheme::Lz77 {
+            literal_code_lens,
+            distance_code_lens,
+        },
+        bitstream,
+        bit_len,
+        orig_len: values.len(),
+        checksum: 0,
+    };
+    compressed.checksum = compressed.compute_checksum();
+    compressed
+}
+
+fn decompress_lz77(
+    data: &CompressedWdl,
+    literal_code_lens: &[u8],
+    distance_code_lens: &[u8],
+) -> Vec<u8> {
+    let literal_nodes = build_decoding_tree(&build_codes_from_lengths(literal_code_lens));
+    let distance_nodes = build_decoding_tree(&build_codes_from_lengths(distance_code_lens));
+
+    let mut out: Vec<u8> = Vec::with_capacity(data.orig_len);
+    let mut bit_index = 0usize;
+    let mut node_idx = 0usize;
+
+    while out.len() < data.orig_len {
+        let byte = data.bitstream[bit_index / 8];
+        let bit = (byte >> (7 - (bit_index % 8))) & 1;
+        bit_index += 1;
+        node_idx = if bit == 0 {
+            literal_nodes[node_idx].left.expect("missing left child")
+        } else {
+            literal_nodes[node_idx].right.expect("missing right child")
+        };
+
+        let Some(sym) = literal_nodes[node_idx].symbol else {
+            continue;
+        };
+        node_idx = 0;
+
+        if (sym as usize) < 9 {
+            out.push(sym as u8);
+            continue;
+        }
+
+        let length = (sym as usize - 9) + MIN_MATCH;
+
+        let mut dist_node_idx = 0usize;
+        let distance = loop {
+            let byte = data.bitstream[bit_index / 8];
+            let bit = (byte >> (7 - (bit_index % 8))) & 1;
+            bit_index += 1;
+            dist_node_idx = if bit == 0 {
+                distance_nodes[dist_node_idx]
+                    .left
+                    .expect("missing left child")
+            } else {
+                distance_nodes[dist_node_idx]
+                    .right
+                    .expect("missing right child")
+            };
+            if let Some(dsym) = distance_nodes[dist_node_idx].symbol {
+                break dsym as usize + 1;
+            }
+        };
+
+        let start = out.len() - distance;
+        for k in 0..length {
+            let v = out[start + k];
+            out.push(v);
+        }
+    }
+
+    out
+}
+
+/// Compress with both backends and keep the smaller result.
+///
+/// `mode` always controls [`compress_wdl_lz77`]'s own match-finding effort; it's only compared
+/// against [`compress_wdl_with_options`]'s pair substitution when `mode` is
+/// [`DeflateMode::Best`], since that comparison costs a second full compression pass that a
+/// `Fast` caller is presumably trying to avoid. `mask_unknown` is forwarded to the pair
+/// substitution side of that comparison, matching whatever the caller passed to the rest of the
+/// write path.
+pub fn compress_wdl_with_scheme(
+    values: &[WdlScoreRange],
+    mode: DeflateMode,
+    mask_unknown: bool,
+) -> CompressedWdl {
+    let lz77 = compress_wdl_lz77(values, mode);
+    if mode == DeflateMode::Best {
+        let pair_substitution = compress_wdl_with_options(values, mask_unknown);
+        if pair_substitution.bitstream.len() < lz77.bitstream.len() {
+            return pair_substitution;
+        }
+    }
+    lz77
 }
+
 /// Build a Huffman decoding tree from `(code, length)` pairs.
 ///
 /// The tree mirrors the canonical code assignment: a `0` bit takes the `left`
@@ -178,18 +564,115 @@ fn expand_symbol(sym: u16, sym_pairs: &[(u16, u16)], base: u16, out: &mut Vec<u1
     }
 }
 
-fn rewrite_illegal_runs(seq: &mut [u8], illegal_code: u8) {
+/// Number of base values each symbol expands to, indexed by symbol.
+///
+/// Base symbols always expand to exactly one value; a generated symbol's length is the sum of
+/// its pair's lengths, and since [`pair_substitution`] only ever mints symbols out of
+/// already-existing ones, a single left-to-right pass over `sym_pairs` suffices.
+fn symbol_expansion_lengths(sym_pairs: &[(u16, u16)], base: u16) -> Vec<usize> {
+    let mut lens = vec![1usize; base as usize + sym_pairs.len()];
+    for (i, &(a, b)) in sym_pairs.iter().enumerate() {
+        lens[base as usize + i] = lens[a as usize] + lens[b as usize];
+    }
+    lens
+}
+
+/// Expand `sym` only as far as needed to find the value at offset `within` into its expansion,
+/// descending into whichever half of the pair contains it instead of materializing the whole
+/// subtree (as [`expand_symbol`] does).
+fn expand_symbol_at(sym: u16, sym_pairs: &[(u16, u16)], base: u16, lens: &[usize], within: usize) -> u16 {
+    if sym < base {
+        debug_assert_eq!(within, 0);
+        return sym;
+    }
+    let (a, b) = sym_pairs[(sym - base) as usize];
+    let left_len = lens[a as usize];
+    if within < left_len {
+        expand_symbol_at(a, sym_pairs, base, lens, within)
+    } else {
+        expand_symbol_at(b, sym_pairs, base, lens, within - left_len)
+    }
+}
+
+/// Look up a single position without decompressing the rest of the table.
+///
+/// For [`CompressionScheme::PairSubstitution`], walks the Huffman-coded bitstream from the
+/// start, but only as far as the symbol covering `idx`: each decoded symbol's expansion length
+/// (precomputed by [`symbol_expansion_lengths`]) tells us whether `idx` falls inside it without
+/// expanding it, so decoding stops as soon as the target symbol is found instead of continuing
+/// through the rest of the block. [`CompressionScheme::Lz77`] back-references can point
+/// arbitrarily far back, so there's no equivalent shortcut yet; that scheme falls back to a
+/// full [`decompress_wdl`].
+pub fn probe(data: &CompressedWdl, idx: usize) -> WdlScoreRange {
+    assert!(idx < data.orig_len, "index out of bounds");
+
+    let (base_symbols, sym_pairs, code_lens) = match &data.scheme {
+        CompressionScheme::PairSubstitution {
+            base_symbols,
+            sym_pairs,
+            code_lens,
+            ..
+        } => (*base_symbols, sym_pairs, code_lens),
+        CompressionScheme::Lz77 { .. } => return decompress_wdl(data)[idx],
+    };
+
+    let codes = build_codes_from_lengths(code_lens);
+    let nodes = build_decoding_tree(&codes);
+    let lens = symbol_expansion_lengths(sym_pairs, base_symbols);
+
+    let mut node_idx = 0usize;
+    let mut consumed = 0usize;
+    for bit_index in 0..data.bit_len {
+        let byte = data.bitstream[bit_index / 8];
+        let bit = (byte >> (7 - (bit_index % 8))) & 1;
+        node_idx = if bit == 0 {
+            nodes[node_idx].left.expect("missing left child")
+        } else {
+            nodes[node_idx].right.expect("missing right child")
+        };
+
+        if let Some(sym) = nodes[node_idx].symbol {
+            let len = lens[sym as usize];
+            if idx < consumed + len {
+                let value = expand_symbol_at(sym, sym_pairs, base_symbols, &lens, idx - consumed);
+                return WdlScoreRange::try_from(value as u8).expect("invalid wdl value");
+            }
+            consumed += len;
+            node_idx = 0;
+        }
+    }
+
+    unreachable!("idx < data.orig_len but the bitstream ran out before reaching it");
+}
+
+/// Rewrite every maximal run of values for which `is_free` returns true to whichever concrete
+/// neighboring value best serves compression, since a free value (by definition) never needs
+/// to decode back to anything in particular.
+///
+/// Prefers the neighbor whose same-value pair (`(l, l)` or `(r, r)`) already occurs more often
+/// elsewhere in `seq` among non-free positions, since extending a pair RE-PAIR already favors
+/// is worth more than an arbitrary tie-break; falls back to whichever neighbor extends the
+/// longer existing run when pair frequency doesn't clearly favor one side.
+fn rewrite_free_runs(seq: &mut [u8], is_free: impl Fn(u8) -> bool) {
     let len = seq.len();
     let fallback = u8::from(WdlScoreRange::Draw);
+
+    let mut pair_freq: HashMap<(u8, u8), usize> = HashMap::new();
+    for w in seq.windows(2) {
+        if !is_free(w[0]) && !is_free(w[1]) {
+            *pair_freq.entry((w[0], w[1])).or_insert(0) += 1;
+        }
+    }
+
     let mut i = 0usize;
     while i < len {
-        if seq[i] != illegal_code {
+        if !is_free(seq[i]) {
             i += 1;
             continue;
         }
 
         let start = i;
-        while i < len && seq[i] == illegal_code {
+        while i < len && is_free(seq[i]) {
             i += 1;
         }
         let end = i;
@@ -204,29 +687,36 @@ fn rewrite_illegal_runs(seq: &mut [u8], illegal_code: u8) {
         let replacement = match (left, right) {
             (Some(l), Some(r)) if l == r => l,
             (Some(l), Some(r)) => {
-                let mut left_len = 0usize;
-                let mut idx = start;
-                while idx > 0 {
-                    idx -= 1;
-                    if seq[idx] == l {
-                        left_len += 1;
-                    } else {
-                        break;
+                let l_score = pair_freq.get(&(l, l)).copied().unwrap_or(0);
+                let r_score = pair_freq.get(&(r, r)).copied().unwrap_or(0);
+
+                if l_score != r_score {
+                    if l_score > r_score { l } else { r }
+                } else {
+                    let mut left_len = 0usize;
+                    let mut idx = start;
+                    while idx > 0 {
+                        idx -= 1;
+                        if seq[idx] == l {
+                            left_len += 1;
+                        } else {
+                            break;
+                        }
                     }
-                }
 
-                let mut right_len = 0usize;
-                let mut idx = end;
-                while idx < len {
-                    if seq[idx] == r {
-                        right_len += 1;
-                        idx += 1;
-                    } else {
-                        break;
+                    let mut right_len = 0usize;
+                    let mut idx = end;
+                    while idx < len {
+                        if seq[idx] == r {
+                            right_len += 1;
+                            idx += 1;
+                        } else {
+                            break;
+                        }
                     }
-                }
 
-                if left_len >= right_len { l } else { r }
+                    if left_len >= right_len { l } else { r }
+                }
             }
             (Some(l), None) => l,
             (None, Some(r)) => r,
@@ -239,40 +729,142 @@ fn rewrite_illegal_runs(seq: &mut [u8], illegal_code: u8) {
     }
 }
 
-fn pair_substitution(mut seq: Vec<u16>, base: u16) -> (Vec<u16>, Vec<(u16, u16)>) {
+/// Subtract one occurrence of `pair` from the live frequency table, dropping it once it hits
+/// zero, and push a fresh heap entry reflecting the new count so a later pop can tell a stale
+/// entry (one whose recorded count no longer matches the table) from a live one.
+fn decrement_pair(pair_count: &mut HashMap<(u16, u16), usize>, heap: &mut BinaryHeap<(usize, u16, u16)>, pair: (u16, u16)) {
+    if let Some(count) = pair_count.get_mut(&pair) {
+        *count -= 1;
+        if *count == 0 {
+            pair_count.remove(&pair);
+        } else {
+            heap.push((*count, pair.0, pair.1));
+        }
+    }
+}
+
+/// Add one occurrence of `pair`, recording `left_slot` (the pair's left element) in its
+/// occurrence list so a future round processing this pair can find it, and push the updated
+/// count onto the heap.
+fn increment_pair(
+    pair_count: &mut HashMap<(u16, u16), usize>,
+    occurrences: &mut HashMap<(u16, u16), Vec<usize>>,
+    heap: &mut BinaryHeap<(usize, u16, u16)>,
+    pair: (u16, u16),
+    left_slot: usize,
+) {
+    let count = pair_count.entry(pair).or_insert(0);
+    *count += 1;
+    heap.push((*count, pair.0, pair.1));
+    occurrences.entry(pair).or_default().push(left_slot);
+}
+
+/// RE-PAIR-style pair substitution (Larsson-Moffat): repeatedly replace the most frequent
+/// adjacent pair of symbols with a freshly minted symbol, until the best remaining pair
+/// occurs at most once.
+///
+/// `seq` is threaded through a doubly linked list over slot indices (`prev`/`next`, with `-1`
+/// as the list-end sentinel) rather than rebuilt on every round: merging a pair only ever
+/// touches that pair's own occurrences and their immediate neighbors, so a round's cost is
+/// proportional to how many times the winning pair actually occurs, not to `seq.len()`.
+/// Frequencies live in `pair_count`, each pair's left-slot occurrences in `occurrences`, and a
+/// max-heap keyed by count picks the next pair to merge; entries go stale whenever a count
+/// changes; a popped entry is only acted on once it's checked against the live count in
+/// `pair_count`; and an occurrence is only consumed once it's checked against the live
+/// `prev/next/active` state, since either may have been invalidated since it was recorded
+/// (including, for a run like `a a a`, by an earlier merge processed earlier in the very same
+/// round).
+fn pair_substitution(seq: Vec<u16>, base: u16) -> (Vec<u16>, Vec<(u16, u16)>) {
+    let n = seq.len();
+    if n < 2 {
+        return (seq, Vec::new());
+    }
+
+    let mut symbol = seq;
+    // `-1` marks the start/end of the list; every other slot holds a valid index.
+    let mut prev: Vec<isize> = (0..n as isize).map(|i| i - 1).collect();
+    let mut next: Vec<isize> = (0..n as isize).map(|i| i + 1).collect();
+    next[n - 1] = -1;
+    let mut active = vec![true; n];
+
+    let mut pair_count: HashMap<(u16, u16), usize> = HashMap::new();
+    let mut occurrences: HashMap<(u16, u16), Vec<usize>> = HashMap::new();
+    for left in 0..n - 1 {
+        let pair = (symbol[left], symbol[left + 1]);
+        *pair_count.entry(pair).or_insert(0) += 1;
+        occurrences.entry(pair).or_default().push(left);
+    }
+
+    let mut heap: BinaryHeap<(usize, u16, u16)> = pair_count
+        .iter()
+        .map(|(&(a, b), &count)| (count, a, b))
+        .collect();
+
     let mut sym_pairs: Vec<(u16, u16)> = Vec::new();
     let mut next_sym = base;
 
-    loop {
-        let mut freq: HashMap<(u16, u16), usize> = HashMap::new();
-        for w in seq.windows(2) {
-            *freq.entry((w[0], w[1])).or_insert(0) += 1;
+    while let Some((count, a, b)) = heap.pop() {
+        let pair = (a, b);
+        if pair_count.get(&pair) != Some(&count) {
+            continue; // Stale: a live entry with the up-to-date count is (or will be) pushed.
         }
-        let (pair, count) = match freq.into_iter().max_by_key(|(_, c)| *c) {
-            Some((p, c)) => (p, c),
-            None => break,
-        };
         if count <= 1 {
             break;
         }
+
         let new_sym = next_sym;
         next_sym += 1;
         sym_pairs.push(pair);
-        let mut new_seq: Vec<u16> = Vec::with_capacity(seq.len());
-        let mut i = 0usize;
-        while i < seq.len() {
-            if i + 1 < seq.len() && (seq[i], seq[i + 1]) == pair {
-                new_seq.push(new_sym);
-                i += 2;
-            } else {
-                new_seq.push(seq[i]);
-                i += 1;
+        pair_count.remove(&pair);
+
+        for left in occurrences.remove(&pair).unwrap_or_default() {
+            let right = next[left];
+            if !active[left] || right == -1 || !active[right as usize] {
+                continue;
+            }
+            let right = right as usize;
+            if symbol[left] != a || symbol[right] != b {
+                continue; // Invalidated by an earlier merge, this round or a previous one.
+            }
+
+            let left_neighbor = prev[left];
+            if left_neighbor != -1 && active[left_neighbor as usize] {
+                let ln = left_neighbor as usize;
+                decrement_pair(&mut pair_count, &mut heap, (symbol[ln], symbol[left]));
+            }
+            let right_neighbor = next[right];
+            if right_neighbor != -1 && active[right_neighbor as usize] {
+                let rn = right_neighbor as usize;
+                decrement_pair(&mut pair_count, &mut heap, (symbol[right], symbol[rn]));
+            }
+
+            // Merge into the left slot; splice the right slot out of the list.
+            symbol[left] = new_sym;
+            active[right] = false;
+            next[left] = right_neighbor;
+            if right_neighbor != -1 {
+                prev[right_neighbor as usize] = left as isize;
+            }
+
+            if left_neighbor != -1 && active[left_neighbor as usize] {
+                let ln = left_neighbor as usize;
+                increment_pair(&mut pair_count, &mut occurrences, &mut heap, (symbol[ln], new_sym), ln);
+            }
+            if right_neighbor != -1 && active[right_neighbor as usize] {
+                let rn = right_neighbor as usize;
+                increment_pair(&mut pair_count, &mut occurrences, &mut heap, (new_sym, symbol[rn]), left);
             }
         }
-        seq = new_seq;
     }
 
-    (seq, sym_pairs)
+    let mut result = Vec::with_capacity(n);
+    let mut i = 0isize;
+    while i != -1 {
+        result.push(symbol[i as usize]);
+        i = next[i as usize];
+    }
+
+    (result, sym_pairs)
 }
 
 #[derive(Clone, Copy)]
@@ -373,6 +965,77 @@ mod tests {
         assert_eq!(decompressed, data);
     }
 
+    #[test]
+    fn free_run_fill_prefers_the_more_frequent_neighbor_pair() {
+        // Plenty of (Win, Win) pairs elsewhere but no (Loss, Loss) pairs: the free run bounded
+        // by Win on the left and Loss on the right should fill with Win, extending a pair
+        // RE-PAIR will already exploit, rather than falling back to the longest-run heuristic.
+        let win = u8::from(Win);
+        let loss = u8::from(Loss);
+        let unknown = u8::from(Unknown);
+        let mut seq = vec![win, win, win, win, loss, win, win, unknown, unknown, loss];
+        rewrite_free_runs(&mut seq, |v| v == unknown);
+        assert_eq!(seq, vec![win, win, win, win, loss, win, win, win, win, loss]);
+    }
+
+    #[test]
+    fn masking_unknown_round_trips_every_non_unknown_position() {
+        let data = vec![
+            Win, Win, Unknown, Unknown, Unknown, Win, Win, Draw, Loss, Loss,
+        ];
+        let compressed = compress_wdl_with_options(&data, true);
+        assert!(matches!(
+            compressed.scheme,
+            CompressionScheme::PairSubstitution {
+                mask_unknown: true,
+                ..
+            }
+        ));
+
+        let decompressed = decompress_wdl(&compressed);
+        for (original, actual) in data.iter().zip(decompressed.iter()) {
+            if *original != Unknown {
+                assert_eq!(original, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_with_overlapping_runs() {
+        // Runs of odd and even length, back to back, exercise the "don't double-count or
+        // double-consume an overlapping occurrence" edge case pair_substitution has to handle
+        // when merging runs like `a a a`.
+        let data = vec![
+            Win, Win, Win, Draw, Win, Win, Win, Win, Loss, Loss, Loss, Win, Win,
+        ];
+        let compressed = compress_wdl(&data);
+        let decompressed = decompress_wdl(&compressed);
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn probe_matches_full_decompression() {
+        let data = vec![
+            Win, Win, Draw, Loss, WinOrDraw, DrawOrLoss, Draw, Win, Loss, Win,
+        ];
+        let compressed = compress_wdl(&data);
+        let decompressed = decompress_wdl(&compressed);
+
+        for (idx, &expected) in decompressed.iter().enumerate() {
+            assert_eq!(probe(&compressed, idx), expected);
+        }
+    }
+
+    #[test]
+    fn checksum_detects_a_single_flipped_bitstream_byte() {
+        let data = vec![Win, Draw, Loss, WinOrDraw, DrawOrLoss, Draw, Win, Loss];
+        let mut compressed = compress_wdl(&data);
+        assert_eq!(compressed.checksum, compressed.compute_checksum());
+
+        compressed.bitstream[0] ^= 0xFF;
+        assert_ne!(compressed.checksum, compressed.compute_checksum());
+    }
+
     #[test]
     fn compression_is_effective_for_repetition() {
         let data = vec![Win; 100];
@@ -382,4 +1045,36 @@ mod tests {
         let decompressed = decompress_wdl(&compressed);
         assert_eq!(decompressed, data);
     }
+
+    #[test]
+    fn lz77_round_trips_a_long_exact_repeat() {
+        let mut data = vec![Win, Draw, Loss, WinOrDraw, DrawOrLoss, Draw, Win, Loss];
+        data.extend(data.clone());
+        data.extend(data.clone());
+        let compressed = compress_wdl_lz77(&data, DeflateMode::Best);
+        assert!(matches!(compressed.scheme, CompressionScheme::Lz77 { .. }));
+        let decompressed = decompress_wdl(&compressed);
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn lz77_probe_falls_back_to_full_decompression() {
+        let data = vec![Win, Win, Draw, Loss, Win, Win, Draw, Loss];
+        let compressed = compress_wdl_lz77(&data, DeflateMode::Fast);
+        for (idx, &expected) in data.iter().enumerate() {
+            assert_eq!(probe(&compressed, idx), expected);
+        }
+    }
+
+    #[test]
+    fn compress_wdl_with_scheme_picks_the_smaller_encoding() {
+        let data = vec![Win; 200];
+        let chosen = compress_wdl_with_scheme(&data, DeflateMode::Best, false);
+        let lz77_only = compress_wdl_lz77(&data, DeflateMode::Best);
+        let pair_substitution_only = compress_wdl(&data);
+        let smaller = lz77_only.bitstream.len().min(pair_substitution_only.bitstream.len());
+        assert_eq!(chosen.bitstream.len(), smaller);
+        let decompressed = decompress_wdl(&chosen);
+        assert_eq!(decompressed, data);
+    }
 }
@@ -0,0 +1,84 @@
+//! CRC-32 (IEEE 802.3), used to detect a corrupted or truncated compressed WDL block before
+//! decoding ever touches it. Unlike [`zobrist::checksum`](crate::zobrist::checksum), which folds
+//! a whole table's already-decompressed positions, this runs over a block's serialized bytes, so
+//! corruption is caught before `decode_bitstream`/`expand_symbol` so much as looks at them.
+
+const POLY: u32 = 0xEDB8_8320;
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { POLY ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+const TABLE: [u32; 256] = build_table();
+
+/// Incremental CRC-32 accumulator, so a caller can fold in a block's fields one at a time
+/// instead of assembling one contiguous buffer first.
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { state: 0xFFFF_FFFF }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let idx = ((self.state ^ byte as u32) & 0xFF) as usize;
+            self.state = TABLE[idx] ^ (self.state >> 8);
+        }
+    }
+
+    pub fn finish(self) -> u32 {
+        self.state ^ 0xFFFF_FFFF
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute the CRC-32 of `bytes` in one call.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_known_vector() {
+        // CRC-32 of the ASCII string "123456789" is a standard test vector.
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn incremental_matches_one_shot() {
+        let mut incremental = Crc32::new();
+        incremental.update(b"hello, ");
+        incremental.update(b"world");
+        assert_eq!(incremental.finish(), crc32(b"hello, world"));
+    }
+
+    #[test]
+    fn is_sensitive_to_every_byte() {
+        assert_ne!(crc32(b"KQvK"), crc32(b"KRvK"));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::dtz_table::DtzTable;
+use crate::material_key::MaterialKey;
+use crate::score::DtzScoreRange;
+use crate::zobrist;
+
+const MAGIC: &[u8; 4] = b"HBDZ";
+const VERSION: u8 = 1;
+
+/// Fold a material key and its table's storage values into a checksum stored in the file
+/// header, mirroring [`crate::wdl_file`]'s integrity check.
+fn dtz_table_checksum(material: &MaterialKey, positions: &[DtzScoreRange]) -> u64 {
+    let mut bytes = material.to_string().into_bytes();
+    bytes.extend(positions.iter().map(|score| score.to_storage_value() as u8));
+    zobrist::checksum(&bytes)
+}
+
+/// ZigZag-encode a signed byte into an unsigned value, mapping small magnitudes (positive or
+/// negative alike) to small outputs so [`write_varint`] can spend just one byte on them.
+fn zigzag_encode(value: i8) -> u16 {
+    let value = value as i16;
+    ((value << 1) ^ (value >> 15)) as u16
+}
+
+/// Inverse of [`zigzag_encode`].
+fn zigzag_decode(value: u16) -> i8 {
+    (((value >> 1) as i16) ^ -((value & 1) as i16)) as i8
+}
+
+/// Append `value` to `buf` as a little-endian base-128 varint: most DTZ step counts are small,
+/// so most entries cost a single byte instead of the fixed-width 2 bytes a raw `i16` would.
+fn write_varint(buf: &mut Vec<u8>, mut value: u16) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+/// Read one varint written by [`write_varint`], advancing `pos` past it.
+fn read_varint(bytes: &[u8], pos: &mut usize) -> io::Result<u16> {
+    let mut result: u16 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos).ok_or_else(truncated_varint)?;
+        *pos += 1;
+        result |= ((byte & 0x7F) as u16) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+fn truncated_varint() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "truncated varint")
+}
+
+/// Write a DTZ table to a file.
+///
+/// Reuses the `.hbt` header layout (magic, version, material-key length+bytes, integrity
+/// checksum, position count), but stores each entry as a zigzag-encoded varint instead of
+/// block-compressing a small alphabet: DTZ step counts span a much wider, less repetitive
+/// range than [`crate::wdl_score_range::WdlScoreRange`], so the pairing/Huffman scheme in
+/// [`crate::compression`] isn't a good fit here.
+pub fn write_dtz_file<P: AsRef<Path>>(path: P, dtz_table: &DtzTable) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    file.write_all(MAGIC)?;
+    file.write_all(&[VERSION])?;
+
+    let mk_string = dtz_table.material.to_string();
+    file.write_all(&[mk_string.len() as u8])?;
+    file.write_all(mk_string.as_bytes())?;
+
+    let checksum = dtz_table_checksum(&dtz_table.material, &dtz_table.positions);
+    file.write_all(&checksum.to_le_bytes())?;
+
+    file.write_all(&(dtz_table.positions.len() as u64).to_le_bytes())?;
+
+    let mut encoded = Vec::new();
+    for score in &dtz_table.positions {
+        write_varint(&mut encoded, zigzag_encode(score.to_storage_value()));
+    }
+    file.write_all(&(encoded.len() as u64).to_le_bytes())?;
+    file.write_all(&encoded)?;
+
+    Ok(())
+}
+
+/// Read a DTZ table written by [`write_dtz_file`].
+pub fn read_dtz_file<P: AsRef<Path>>(path: P) -> io::Result<DtzTable> {
+    let mut file = File::open(path)?;
+
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
+    }
+
+    let mut version = [0u8; 1];
+    file.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported version",
+        ));
+    }
+
+    let mut mk_len = [0u8; 1];
+    file.read_exact(&mut mk_len)?;
+    let mk_len = mk_len[0] as usize;
+    let mut mk_bytes = vec![0u8; mk_len];
+    file.read_exact(&mut mk_bytes)?;
+    let mk_string = String::from_utf8(mk_bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+    let material = MaterialKey::from_string(&mk_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+
+    let mut buf8 = [0u8; 8];
+    file.read_exact(&mut buf8)?;
+    let checksum = u64::from_le_bytes(buf8);
+
+    file.read_exact(&mut buf8)?;
+    let total_positions = u64::from_le_bytes(buf8) as usize;
+
+    file.read_exact(&mut buf8)?;
+    let encoded_len = u64::from_le_bytes(buf8) as usize;
+    let mut encoded = vec![0u8; encoded_len];
+    file.read_exact(&mut encoded)?;
+
+    let mut positions = Vec::with_capacity(total_positions);
+    let mut pos = 0;
+    while positions.len() < total_positions {
+        let zigzag = read_varint(&encoded, &mut pos)?;
+        positions.push(DtzScoreRange::from_storage_value(zigzag_decode(zigzag)));
+    }
+
+    if dtz_table_checksum(&material, &positions) != checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch: file is corrupted or its header doesn't match its content",
+        ));
+    }
+
+    Ok(DtzTable {
+        material,
+        positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material_key::MaterialKey;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_path(prefix: &str) -> std::path::PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after UNIX_EPOCH")
+            .as_nanos();
+        std::env::temp_dir().join(format!("heisenbase_{prefix}_{unique}.hbz"))
+    }
+
+    fn sample_table() -> DtzTable {
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let scores = [
+            DtzScoreRange::illegal(),
+            DtzScoreRange::draw(),
+            DtzScoreRange::checkmate(),
+            DtzScoreRange::cursed_win(),
+            DtzScoreRange::blessed_loss(),
+        ];
+        let positions = (0..2000).map(|i| scores[i % scores.len()]).collect();
+        DtzTable {
+            material,
+            positions,
+        }
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        let path = temp_path("round_trip");
+        let table = sample_table();
+
+        write_dtz_file(&path, &table).unwrap();
+        let read_back = read_dtz_file(&path).unwrap();
+
+        assert_eq!(read_back.material, table.material);
+        assert_eq!(read_back.positions, table.positions);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_dtz_file_rejects_bad_magic() {
+        let path = temp_path("bad_magic");
+        std::fs::write(&path, b"BAD!").unwrap();
+
+        let result = read_dtz_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn read_dtz_file_rejects_checksum_mismatch() {
+        let path = temp_path("checksum_mismatch");
+        let table = sample_table();
+        write_dtz_file(&path, &table).unwrap();
+
+        let mk_len = table.material.to_string().len();
+        let checksum_offset = 4 + 1 + 1 + mk_len;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[checksum_offset] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = read_dtz_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn mostly_small_steps_compress_to_one_byte_each() {
+        let path = temp_path("small_steps");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let positions = vec![DtzScoreRange::draw(); 1000];
+        let table = DtzTable {
+            material,
+            positions,
+        };
+
+        write_dtz_file(&path, &table).unwrap();
+        let on_disk = std::fs::metadata(&path).unwrap().len() as usize;
+        std::fs::remove_file(&path).unwrap();
+
+        // A raw `i16`-per-position encoding would take 2000 bytes for the payload alone; an
+        // all-draws table should need only 1 varint byte per position.
+        assert!(
+            on_disk < 1100,
+            "expected draws to cost one byte each, got {on_disk} bytes for 1000 positions"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use crate::material_key::MaterialKey;
+use crate::score::DtzScoreRange;
+use crate::table_builder::TableBuilder;
+
+/// A solved table's distance-to-zeroing-move metric, one entry per [`crate::position_indexer::PositionIndexer`]
+/// index.
+///
+/// Parallels [`crate::wdl_table::WdlTable`], but keeps the finer-grained step count a solved
+/// [`TableBuilder`] already computes instead of collapsing it down to a three-valued outcome.
+pub struct DtzTable {
+    pub material: MaterialKey,
+    pub positions: Vec<DtzScoreRange>,
+}
+
+impl From<TableBuilder> for DtzTable {
+    fn from(tb: TableBuilder) -> Self {
+        Self {
+            material: tb.material,
+            positions: tb.positions,
+        }
+    }
+}
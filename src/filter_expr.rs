@@ -0,0 +1,293 @@
+//! Small boolean expression language for `GenerateMany --filter`, letting a caller target
+//! endgame families ("pawnless", "at most two minor pieces", "must contain a rook") instead of
+//! only a flat `--max-pieces` cap.
+//!
+//! Grammar (lowest to highest precedence):
+//! ```text
+//! expr       := or_expr
+//! or_expr    := and_expr ("OR" and_expr)*
+//! and_expr   := unary ("AND" unary)*
+//! unary      := "NOT" unary | atom
+//! atom       := "(" expr ")" | comparison
+//! comparison := field comparator integer
+//! field      := "pawns" | "pieces" | "games" | "king" | "queen" | "rook" | "bishop" | "knight"
+//! comparator := "=" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+
+use anyhow::{Result, anyhow};
+use heisenbase::material_key::MaterialKey;
+use shakmaty::Role;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Pawns,
+    Pieces,
+    Games,
+    Role(Role),
+}
+
+impl Field {
+    fn from_ident(ident: &str) -> Option<Self> {
+        match ident {
+            "pawns" => Some(Field::Pawns),
+            "pieces" => Some(Field::Pieces),
+            "games" => Some(Field::Games),
+            "king" => Some(Field::Role(Role::King)),
+            "queen" => Some(Field::Role(Role::Queen)),
+            "rook" => Some(Field::Role(Role::Rook)),
+            "bishop" => Some(Field::Role(Role::Bishop)),
+            "knight" => Some(Field::Role(Role::Knight)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparator {
+    fn apply(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            Comparator::Eq => lhs == rhs,
+            Comparator::Ne => lhs != rhs,
+            Comparator::Lt => lhs < rhs,
+            Comparator::Le => lhs <= rhs,
+            Comparator::Gt => lhs > rhs,
+            Comparator::Ge => lhs >= rhs,
+        }
+    }
+}
+
+/// Parsed `--filter` expression, evaluated per candidate via [`FilterExpr::matches`].
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    Compare(Field, Comparator, i64),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Evaluate this expression against one candidate's material key and its PGN index's game
+    /// count.
+    pub fn matches(&self, material: &MaterialKey, games: u64) -> bool {
+        match self {
+            FilterExpr::Compare(field, comparator, rhs) => {
+                let lhs = match field {
+                    Field::Pawns => material.pawns.pawn_count() as i64,
+                    Field::Pieces => material.total_piece_count() as i64,
+                    Field::Games => games as i64,
+                    Field::Role(role) => material
+                        .pieces()
+                        .filter(|piece| piece.role.role() == *role)
+                        .count() as i64,
+                };
+                comparator.apply(lhs, *rhs)
+            }
+            FilterExpr::And(lhs, rhs) => {
+                lhs.matches(material, games) && rhs.matches(material, games)
+            }
+            FilterExpr::Or(lhs, rhs) => {
+                lhs.matches(material, games) || rhs.matches(material, games)
+            }
+            FilterExpr::Not(inner) => !inner.matches(material, games),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Comparator(Comparator),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Comparator(Comparator::Eq));
+                i += 1;
+            }
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Comparator(Comparator::Ne));
+                i += 2;
+            }
+            '<' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Comparator(Comparator::Le));
+                i += 2;
+            }
+            '<' => {
+                tokens.push(Token::Comparator(Comparator::Lt));
+                i += 1;
+            }
+            '>' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Comparator(Comparator::Ge));
+                i += 2;
+            }
+            '>' => {
+                tokens.push(Token::Comparator(Comparator::Gt));
+                i += 1;
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().collect();
+                tokens.push(Token::Int(
+                    digits
+                        .parse()
+                        .map_err(|_| anyhow!("invalid integer literal: {digits}"))?,
+                ));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Ident(word.to_ascii_lowercase()),
+                });
+            }
+            other => return Err(anyhow!("unexpected character '{other}'")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.advance() {
+            Some(token) if &token == expected => Ok(()),
+            Some(token) => Err(anyhow!("expected {expected:?}, found {token:?}")),
+            None => Err(anyhow!("expected {expected:?}, found end of expression")),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<FilterExpr> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            let rhs = self.parse_and()?;
+            lhs = FilterExpr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr> {
+        let mut lhs = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            let rhs = self.parse_unary()?;
+            lhs = FilterExpr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpr> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            return Ok(FilterExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<FilterExpr> {
+        if self.peek() == Some(&Token::LParen) {
+            self.advance();
+            let expr = self.parse_expr()?;
+            self.expect(&Token::RParen)?;
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<FilterExpr> {
+        let field = match self.advance() {
+            Some(Token::Ident(ident)) => {
+                Field::from_ident(&ident).ok_or_else(|| anyhow!("unknown field '{ident}'"))?
+            }
+            Some(token) => return Err(anyhow!("expected a field name, found {token:?}")),
+            None => return Err(anyhow!("expected a field name, found end of expression")),
+        };
+
+        let comparator = match self.advance() {
+            Some(Token::Comparator(comparator)) => comparator,
+            Some(token) => return Err(anyhow!("expected a comparator, found {token:?}")),
+            None => return Err(anyhow!("expected a comparator, found end of expression")),
+        };
+
+        let value = match self.advance() {
+            Some(Token::Int(value)) => value,
+            Some(token) => return Err(anyhow!("expected an integer literal, found {token:?}")),
+            None => return Err(anyhow!("expected an integer literal, found end of expression")),
+        };
+
+        Ok(FilterExpr::Compare(field, comparator, value))
+    }
+}
+
+/// Parse a `--filter` expression into an AST ready for repeated [`FilterExpr::matches`] calls.
+pub fn parse(source: &str) -> Result<FilterExpr> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(anyhow!(
+            "unexpected trailing tokens starting at {:?}",
+            parser.tokens[parser.pos]
+        ));
+    }
+    Ok(expr)
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     fs,
     fs::File,
     io,
@@ -8,13 +8,16 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use bzip2::read::BzDecoder;
 use flate2::read::MultiGzDecoder;
 use heisenbase::material_key::MaterialKey;
-use pgn_reader::{RawTag, Reader, SanPlus, Skip, Visitor};
+use heisenbase::position_indexer::PositionIndexer;
+use pgn_reader::{RawComment, RawTag, Reader, SanPlus, Skip, Visitor};
 use polars::{
     error::PolarsError,
     prelude::{DataFrame, NamedFrom, ParquetWriter, Series},
 };
+use rayon::prelude::*;
 use shakmaty::{CastlingMode, Chess, Position, fen::Fen};
 
 const PGN_ROOT: &str = "./data/fishtest_pgns";
@@ -25,24 +28,114 @@ const INVALID_FEN_TAG_PREFIX: &str = "invalid FEN tag:";
 const INVALID_FEN_POSITION_PREFIX: &str = "invalid FEN position:";
 const CORRUPT_GZIP_PREFIX: &str = "corrupt gzip stream";
 const PARQUET_PATH: &str = "./data/pgn_index.parquet";
+const EVALS_PARQUET_PATH: &str = "./data/pgn_evals.parquet";
+
+/// One `[%eval ...]` annotation observed on a position within [`MAX_NON_PAWN`], so a later
+/// stage can cross-check a built WDL table against what engines actually thought of its
+/// positions during play.
+struct EvalRecord {
+    material_key: MaterialKey,
+    position_index: u64,
+    eval_cp: Option<i32>,
+    eval_mate: Option<i32>,
+}
+
+/// A game's `Result` tag, parsed from the PGN strings `1-0`/`0-1`/`1/2-1/2`/`*`. Unrecognized or
+/// missing tags fall back to `Unknown` rather than failing the game, matching the existing
+/// skip-classification behavior for other malformed tags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum GameResult {
+    WhiteWin,
+    BlackWin,
+    Draw,
+    #[default]
+    Unknown,
+}
+
+fn parse_game_result(value: &[u8]) -> GameResult {
+    match value {
+        b"1-0" => GameResult::WhiteWin,
+        b"0-1" => GameResult::BlackWin,
+        b"1/2-1/2" => GameResult::Draw,
+        _ => GameResult::Unknown,
+    }
+}
+
+/// Per-material-key outcome tally, from the perspective of the side listed first in the key
+/// (before `v`) rather than the real game's white/black, since [`MaterialKey::from_position`]
+/// is free to normalize a key onto the side-swapped representation.
+#[derive(Clone, Copy, Debug, Default)]
+struct ResultTally {
+    white_wins: u64,
+    black_wins: u64,
+    draws: u64,
+    unknown: u64,
+}
 
-pub fn run() -> io::Result<()> {
+impl ResultTally {
+    fn record(&mut self, result: GameResult, first_side_is_white: bool) {
+        match result {
+            GameResult::WhiteWin if first_side_is_white => self.white_wins += 1,
+            GameResult::WhiteWin => self.black_wins += 1,
+            GameResult::BlackWin if first_side_is_white => self.black_wins += 1,
+            GameResult::BlackWin => self.white_wins += 1,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Unknown => self.unknown += 1,
+        }
+    }
+
+    fn merge(&mut self, other: ResultTally) {
+        self.white_wins += other.white_wins;
+        self.black_wins += other.black_wins;
+        self.draws += other.draws;
+        self.unknown += other.unknown;
+    }
+}
+
+/// One file's contribution to the global index, kept separate so worker threads never share
+/// mutable state; [`run`] merges these back together once every file has been processed.
+#[derive(Default)]
+struct FileIndex {
+    counts: HashMap<MaterialKey, u64>,
+    outcomes: HashMap<MaterialKey, ResultTally>,
+    games: u64,
+    evals: Vec<EvalRecord>,
+}
+
+pub fn run(workers: Option<usize>) -> io::Result<()> {
     let mut files = Vec::new();
     collect_pgn_files(Path::new(PGN_ROOT), &mut files)?;
     files.sort();
 
+    let worker_count = workers.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .max(8)
+    });
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(worker_count)
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+
+    let file_indexes: Vec<io::Result<FileIndex>> =
+        pool.install(|| files.par_iter().map(|path| process_file(path)).collect());
+
     let mut counts: HashMap<MaterialKey, u64> = HashMap::new();
+    let mut outcomes: HashMap<MaterialKey, ResultTally> = HashMap::new();
     let mut total_games: u64 = 0;
+    let mut evals: Vec<EvalRecord> = Vec::new();
 
-    for path in files {
-        println!("Processing {}", path.display());
-        let file = File::open(&path)?;
-        let game_count = if is_gz(&path) {
-            process_reader(MultiGzDecoder::new(file), &mut counts, &path)?
-        } else {
-            process_reader(file, &mut counts, &path)?
-        };
-        total_games += game_count;
+    for file_index in file_indexes {
+        let file_index = file_index?;
+        total_games += file_index.games;
+        for (key, count) in file_index.counts {
+            *counts.entry(key).or_insert(0) += count;
+        }
+        for (key, tally) in file_index.outcomes {
+            outcomes.entry(key).or_default().merge(tally);
+        }
+        evals.extend(file_index.evals);
     }
 
     println!("Processed {total_games} games.");
@@ -65,18 +158,40 @@ pub fn run() -> io::Result<()> {
         );
     }
 
-    write_full_index(&entries)?;
+    write_full_index(&entries, &outcomes)?;
+    write_evals_index(&evals)?;
 
     Ok(())
 }
 
-fn process_reader<R: Read>(
-    reader: R,
-    counts: &mut HashMap<MaterialKey, u64>,
-    path: &Path,
-) -> io::Result<u64> {
+fn process_file(path: &Path) -> io::Result<FileIndex> {
+    println!("Processing {}", path.display());
+    process_reader(open_pgn_stream(path)?, path)
+}
+
+/// Open `path` through whichever decoder its extension calls for (`.gz`, `.zst`, `.bz2`, or
+/// none), so [`process_file`] doesn't need to know which codec it's reading.
+fn open_pgn_stream(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Ok(Box::new(MultiGzDecoder::new(file))),
+        Some("zst") => Ok(Box::new(zstd::stream::read::Decoder::new(file)?)),
+        Some("bz2") => Ok(Box::new(BzDecoder::new(file))),
+        _ => Ok(Box::new(file)),
+    }
+}
+
+fn process_reader<R: Read>(reader: R, path: &Path) -> io::Result<FileIndex> {
+    let mut counts = HashMap::new();
+    let mut outcomes = HashMap::new();
     let mut reader = Reader::new(reader);
-    let mut visitor = IndexVisitor { counts, games: 0 };
+    let mut visitor = IndexVisitor {
+        counts: &mut counts,
+        outcomes: &mut outcomes,
+        games: 0,
+        evals: Vec::new(),
+        malformed_evals: 0,
+    };
     let mut skipped = SkipStats::default();
     loop {
         match reader.read_game(&mut visitor) {
@@ -89,9 +204,9 @@ fn process_reader<R: Read>(
                 }
             },
             Ok(None) => break,
-            Err(err) if is_corrupt_gzip_error(&err) => {
+            Err(err) if is_truncated_stream_error(&err) => {
                 eprintln!(
-                    "Stopped early due to corrupt gzip data in {}: {err}",
+                    "Stopped early due to a truncated or corrupted compressed stream in {}: {err}",
                     path.display()
                 );
                 break;
@@ -99,16 +214,36 @@ fn process_reader<R: Read>(
             Err(err) => return Err(err),
         }
     }
+    skipped.malformed_evals += visitor.malformed_evals;
     skipped.report(path);
-    Ok(visitor.games)
+    let games = visitor.games;
+    let evals = visitor.evals;
+    Ok(FileIndex {
+        counts,
+        outcomes,
+        games,
+        evals,
+    })
 }
 
-fn write_full_index(entries: &[(MaterialKey, u64)]) -> io::Result<()> {
+fn write_full_index(
+    entries: &[(MaterialKey, u64)],
+    outcomes: &HashMap<MaterialKey, ResultTally>,
+) -> io::Result<()> {
     let mut material_keys = Vec::with_capacity(entries.len());
     let mut counts = Vec::with_capacity(entries.len());
+    let mut white_wins = Vec::with_capacity(entries.len());
+    let mut black_wins = Vec::with_capacity(entries.len());
+    let mut draws = Vec::with_capacity(entries.len());
+    let mut unknown = Vec::with_capacity(entries.len());
     for (key, count) in entries {
+        let tally = outcomes.get(key).copied().unwrap_or_default();
         material_keys.push(key.to_string());
         counts.push(*count);
+        white_wins.push(tally.white_wins);
+        black_wins.push(tally.black_wins);
+        draws.push(tally.draws);
+        unknown.push(tally.unknown);
     }
 
     if let Some(parent) = Path::new(PARQUET_PATH).parent() {
@@ -118,6 +253,10 @@ fn write_full_index(entries: &[(MaterialKey, u64)]) -> io::Result<()> {
     let mut df = DataFrame::new(vec![
         Series::new("material_key", material_keys),
         Series::new("num_games", counts),
+        Series::new("white_wins", white_wins),
+        Series::new("black_wins", black_wins),
+        Series::new("draws", draws),
+        Series::new("unknown", unknown),
     ])
     .map_err(polars_to_io_error)?;
 
@@ -129,27 +268,75 @@ fn write_full_index(entries: &[(MaterialKey, u64)]) -> io::Result<()> {
     Ok(())
 }
 
+/// Write the engine-eval annotations gathered across every file to [`EVALS_PARQUET_PATH`], one
+/// row per observed `[%eval ...]` comment. `eval_cp` and `eval_mate` are mutually exclusive
+/// nullable columns rather than a single mixed-unit one, so a later cross-check can filter on
+/// whichever kind it cares about without first parsing the other back out.
+fn write_evals_index(evals: &[EvalRecord]) -> io::Result<()> {
+    let mut material_keys = Vec::with_capacity(evals.len());
+    let mut position_indices = Vec::with_capacity(evals.len());
+    let mut eval_cps = Vec::with_capacity(evals.len());
+    let mut eval_mates = Vec::with_capacity(evals.len());
+    for record in evals {
+        material_keys.push(record.material_key.to_string());
+        position_indices.push(record.position_index);
+        eval_cps.push(record.eval_cp);
+        eval_mates.push(record.eval_mate);
+    }
+
+    if let Some(parent) = Path::new(EVALS_PARQUET_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut df = DataFrame::new(vec![
+        Series::new("material_key", material_keys),
+        Series::new("position_index", position_indices),
+        Series::new("eval_cp", eval_cps),
+        Series::new("eval_mate", eval_mates),
+    ])
+    .map_err(polars_to_io_error)?;
+
+    let file = File::create(EVALS_PARQUET_PATH)?;
+    ParquetWriter::new(file)
+        .finish(&mut df)
+        .map_err(polars_to_io_error)?;
+
+    Ok(())
+}
+
 fn polars_to_io_error(err: PolarsError) -> io::Error {
     io::Error::new(io::ErrorKind::Other, err.to_string())
 }
 
 struct IndexVisitor<'a> {
     counts: &'a mut HashMap<MaterialKey, u64>,
+    outcomes: &'a mut HashMap<MaterialKey, ResultTally>,
     games: u64,
+    evals: Vec<EvalRecord>,
+    malformed_evals: u64,
+}
+
+#[derive(Default)]
+struct TagState {
+    position: Option<Chess>,
+    result: GameResult,
 }
 
 struct GameState {
     position: Chess,
-    seen: HashSet<MaterialKey>,
+    result: GameResult,
+    /// Every material key reached this game, alongside whether that key's first-listed side
+    /// (before `v`) was this game's actual white; see [`MaterialKey::from_position_with_swap`].
+    seen: HashMap<MaterialKey, bool>,
 }
 
 impl<'a> Visitor for IndexVisitor<'a> {
-    type Tags = Option<Chess>;
+    type Tags = TagState;
     type Movetext = GameState;
     type Output = io::Result<()>;
 
     fn begin_tags(&mut self) -> ControlFlow<Self::Output, Self::Tags> {
-        ControlFlow::Continue(None)
+        ControlFlow::Continue(TagState::default())
     }
 
     fn tag(
@@ -177,20 +364,26 @@ impl<'a> Visitor for IndexVisitor<'a> {
                     )));
                 }
             };
-            tags.replace(position);
+            tags.position.replace(position);
+        } else if name == b"Result" {
+            tags.result = parse_game_result(value.as_bytes());
         }
         ControlFlow::Continue(())
     }
 
     fn begin_movetext(&mut self, tags: Self::Tags) -> ControlFlow<Self::Output, Self::Movetext> {
-        let position = tags.unwrap_or_default();
-        let mut seen = HashSet::new();
-        if let Some(key) = MaterialKey::from_position(&position) {
+        let position = tags.position.unwrap_or_default();
+        let mut seen = HashMap::new();
+        if let Some((key, swapped)) = MaterialKey::from_position_with_swap(&position) {
             if key.non_pawn_piece_count() <= MAX_NON_PAWN {
-                seen.insert(key);
+                seen.insert(key, !swapped);
             }
         }
-        ControlFlow::Continue(GameState { position, seen })
+        ControlFlow::Continue(GameState {
+            position,
+            result: tags.result,
+            seen,
+        })
     }
 
     fn begin_variation(
@@ -215,23 +408,98 @@ impl<'a> Visitor for IndexVisitor<'a> {
             }
         };
         movetext.position.play_unchecked(mv);
-        if let Some(key) = MaterialKey::from_position(&movetext.position) {
+        if let Some((key, swapped)) = MaterialKey::from_position_with_swap(&movetext.position) {
             if key.non_pawn_piece_count() <= MAX_NON_PAWN {
-                movetext.seen.insert(key);
+                movetext.seen.insert(key, !swapped);
             }
         }
         ControlFlow::Continue(())
     }
 
+    fn comment(
+        &mut self,
+        movetext: &mut Self::Movetext,
+        comment: RawComment<'_>,
+    ) -> ControlFlow<Self::Output> {
+        let Some(key) = MaterialKey::from_position(&movetext.position) else {
+            return ControlFlow::Continue(());
+        };
+        if key.non_pawn_piece_count() > MAX_NON_PAWN {
+            return ControlFlow::Continue(());
+        }
+        let Ok(text) = std::str::from_utf8(comment.as_bytes()) else {
+            self.malformed_evals += 1;
+            return ControlFlow::Continue(());
+        };
+        let (eval_cp, eval_mate) = match parse_eval_comment(text) {
+            EvalToken::NotPresent => return ControlFlow::Continue(()),
+            EvalToken::Malformed => {
+                self.malformed_evals += 1;
+                return ControlFlow::Continue(());
+            }
+            EvalToken::Parsed(eval_cp, eval_mate) => (eval_cp, eval_mate),
+        };
+        let Ok(position_index) =
+            PositionIndexer::new(key.clone()).position_to_index(&movetext.position)
+        else {
+            self.malformed_evals += 1;
+            return ControlFlow::Continue(());
+        };
+        self.evals.push(EvalRecord {
+            material_key: key,
+            position_index: position_index as u64,
+            eval_cp,
+            eval_mate,
+        });
+        ControlFlow::Continue(())
+    }
+
     fn end_game(&mut self, movetext: Self::Movetext) -> Self::Output {
         self.games += 1;
-        for key in movetext.seen {
-            *self.counts.entry(key).or_insert(0) += 1;
+        for (key, first_side_is_white) in movetext.seen {
+            *self.counts.entry(key.clone()).or_insert(0) += 1;
+            self.outcomes
+                .entry(key)
+                .or_default()
+                .record(movetext.result, first_side_is_white);
         }
         Ok(())
     }
 }
 
+/// Outcome of looking for a `[%eval ...]` token in a move comment.
+enum EvalToken {
+    /// The comment carries no `%eval` tag at all; nothing to record.
+    NotPresent,
+    /// A `%eval` tag is present but its value didn't parse; counted in [`SkipStats`].
+    Malformed,
+    /// `(eval_cp, eval_mate)`, with exactly one side set.
+    Parsed(Option<i32>, Option<i32>),
+}
+
+/// Parse a `[%eval <cp-or-mate>]` token out of a move comment. Mate scores are written as `#N`
+/// (or `#-N` for a losing mate); everything else is a pawn-unit score, scaled to centipawns.
+fn parse_eval_comment(text: &str) -> EvalToken {
+    let Some(start) = text.find("%eval") else {
+        return EvalToken::NotPresent;
+    };
+    let value = text[start + "%eval".len()..].trim_start();
+    let Some(value) = value.split(|c: char| c.is_whitespace() || c == ']').next() else {
+        return EvalToken::Malformed;
+    };
+    if let Some(mate) = value.strip_prefix('#') {
+        match mate.parse() {
+            Ok(mate) => EvalToken::Parsed(None, Some(mate)),
+            Err(_) => EvalToken::Malformed,
+        }
+    } else {
+        match value.parse::<f64>() {
+            Ok(cp) => EvalToken::Parsed(Some((cp * 100.0).round() as i32), None),
+            Err(_) => EvalToken::Malformed,
+        }
+    }
+}
+
 fn collect_pgn_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -248,7 +516,7 @@ fn collect_pgn_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
 fn is_pgn(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("pgn") => true,
-        Some("gz") => path
+        Some("gz") | Some("zst") | Some("bz2") => path
             .file_stem()
             .and_then(|stem| stem.to_str())
             .map(|stem| stem.ends_with(".pgn"))
@@ -257,19 +525,22 @@ fn is_pgn(path: &Path) -> bool {
     }
 }
 
-fn is_gz(path: &Path) -> bool {
-    path.extension().and_then(|ext| ext.to_str()) == Some("gz")
-}
-
 fn is_illegal_move_error(err: &io::Error) -> bool {
     err.kind() == io::ErrorKind::InvalidData && err.to_string().starts_with(ILLEGAL_MOVE_PREFIX)
 }
 
-fn is_corrupt_gzip_error(err: &io::Error) -> bool {
-    matches!(
+/// Whether `err` is a truncated or otherwise corrupted compressed stream for any of the codecs
+/// [`open_pgn_stream`] can return, so that file can be stopped early rather than aborting the
+/// whole run — mirrors the pre-existing gzip-specific check, generalized to also cover the
+/// zstd/bzip2 decoders. flate2 reports this as an `InvalidData`/`InvalidInput` error carrying
+/// [`CORRUPT_GZIP_PREFIX`]; the zstd and bzip2 readers instead surface a premature end of their
+/// compressed frame as a plain `UnexpectedEof`.
+fn is_truncated_stream_error(err: &io::Error) -> bool {
+    (matches!(
         err.kind(),
         io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput
-    ) && err.to_string().starts_with(CORRUPT_GZIP_PREFIX)
+    ) && err.to_string().starts_with(CORRUPT_GZIP_PREFIX))
+        || err.kind() == io::ErrorKind::UnexpectedEof
 }
 
 fn is_invalid_fen_tag_error(err: &io::Error) -> bool {
@@ -286,6 +557,7 @@ struct SkipStats {
     illegal_moves: u64,
     invalid_fen_tags: u64,
     invalid_fen_positions: u64,
+    malformed_evals: u64,
 }
 
 impl SkipStats {
@@ -311,6 +583,13 @@ impl SkipStats {
                 path.display()
             );
         }
+        if self.malformed_evals > 0 {
+            eprintln!(
+                "Skipped {} malformed [%eval ...] annotations in {}.",
+                self.malformed_evals,
+                path.display()
+            );
+        }
     }
 }
 
@@ -1,16 +1,31 @@
+mod filter_expr;
 mod index_pgn;
 
 use clap::{Parser, Subcommand};
 use polars::prelude::*;
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use shakmaty::{Chess, EnPassantMode, fen::Fen};
+use rayon::prelude::*;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, fen::Fen};
 use shakmaty_syzygy::{SyzygyError, Tablebase, Wdl};
-use std::{collections::HashSet, error::Error, fs, io, path::Path};
-
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use heisenbase::dtz_file::{read_dtz_file, write_dtz_file};
+use heisenbase::dtz_table::DtzTable;
 use heisenbase::material_key::MaterialKey;
 use heisenbase::position_indexer::PositionIndexer;
+use heisenbase::prober::Prober;
 use heisenbase::table_builder::TableBuilder;
-use heisenbase::wdl_file::{read_wdl_file, write_wdl_file};
+use heisenbase::verify::verify_sampled;
+use heisenbase::wdl_bitpack::write_packed_wdl_file;
+use heisenbase::wdl_file::{
+    WdlFileReader, inspect_wdl_file, read_wdl_file, verify_wdl_file, write_wdl_file_with_options,
+};
 use heisenbase::wdl_score_range::WdlScoreRange;
 use heisenbase::wdl_table::WdlTable;
 
@@ -23,7 +38,12 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Generate a table for a given material key.
+    /// Generate WDL and DTZ tables for a given material key.
+    ///
+    /// `TableBuilder::solve` already runs the retrograde analysis in terms of
+    /// [`heisenbase::score::DtzScoreRange`], so there is no separate DTZ pass to dispatch:
+    /// `WdlTable` is collapsed from the same solved [`heisenbase::dtz_table::DtzTable`] rather
+    /// than recomputed, and both `.hbt` and `.hbz` are written out together.
     Generate {
         /// Material key describing pieces, e.g. `KQvK`.
         material_key: String,
@@ -36,11 +56,76 @@ enum Commands {
         /// Maximum total number of pieces allowed.
         #[arg(long, required = true)]
         max_pieces: u32,
+        /// Boolean expression over `pawns`, `pieces`, `games` and piece-role fields (`king`,
+        /// `queen`, `rook`, `bishop`, `knight`), e.g. `pawns = 0 AND NOT (rook > 0)`.
+        #[arg(long)]
+        filter: Option<String>,
     },
     /// Index PGN files to find the most common material keys.
-    IndexPgn,
+    IndexPgn {
+        /// Number of worker threads to use; defaults to `max(8, available parallelism)`, since
+        /// indexing is I/O-plus-parse bound and benefits from oversubscription on small machines.
+        #[arg(long)]
+        workers: Option<usize>,
+    },
     /// Sample positions from heisenbase tables and compare against Syzygy WDL tables.
-    CheckAgainstSyzygy,
+    CheckAgainstSyzygy {
+        /// Check every valid position instead of sampling `SAMPLES_PER_TABLE` of them.
+        #[arg(long)]
+        exhaustive: bool,
+        /// Write one line per mismatch (material, index, both WDL values, FEN) to this file.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Keep cursed-win/blessed-loss distinct from true win/loss (probing the position
+        /// as-is via `probe_wdl` rather than after its next zeroing move), so a heisenbase
+        /// `Win`/`Loss` is only accepted against a true Syzygy win/loss, not a 50-move-rule
+        /// draw in disguise.
+        #[arg(long)]
+        fifty_move: bool,
+    },
+    /// Read FENs from stdin, one per line, and print the WDL (and DTZ, if generated) for each.
+    Probe {
+        /// Directories to search for `.hbt`/`.hbz` tables, in priority order, joined with the
+        /// platform path separator (the same convention as Syzygy's `SyzygyPath`). Defaults to
+        /// `./data/heisenbase`.
+        #[arg(long)]
+        tables: Option<String>,
+    },
+    /// Print structural statistics about a compressed `.hbt` file.
+    Inspect {
+        /// Path to the `.hbt` file to inspect.
+        path: PathBuf,
+    },
+    /// Decompress every block of a `.hbt` file and confirm it's internally consistent.
+    Verify {
+        /// Path to the `.hbt` file to verify.
+        path: PathBuf,
+        /// Cross-check a sample of positions against retrograde re-derivation instead of
+        /// decompressing the whole file (see `verify::verify_sampled`). Needs sibling child
+        /// `.hbt` files in the same directory as `path`.
+        #[arg(long)]
+        sampled: bool,
+        /// Number of positions to sample when `--sampled` is set.
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        /// Seed for the sampled positions' pseudo-random draw, so `--sampled` runs reproduce.
+        #[arg(long, default_value_t = 42)]
+        seed: u32,
+    },
+    /// Convert an `.hbt` file to the bit-packed `.hbk` format.
+    ExportPacked {
+        /// Path to the `.hbt` file to read.
+        input: PathBuf,
+        /// Path of the `.hbk` file to write.
+        output: PathBuf,
+    },
+    /// Look up a single position by raw index in a block-structured, memory-mapped `.hbm` file.
+    ProbeMmap {
+        /// Path to the `.hbm` file to probe.
+        path: PathBuf,
+        /// Position index to look up, per `PositionIndexer`.
+        index: usize,
+    },
 }
 
 fn main() {
@@ -57,24 +142,69 @@ fn main() {
         Commands::GenerateMany {
             min_games,
             max_pieces,
+            filter,
         } => {
-            if let Err(err) = run_generate_many(min_games, max_pieces) {
+            if let Err(err) = run_generate_many(min_games, max_pieces, filter) {
                 eprintln!("generate-many failed: {err}");
                 std::process::exit(1);
             }
         }
-        Commands::IndexPgn => {
-            if let Err(err) = index_pgn::run() {
+        Commands::IndexPgn { workers } => {
+            if let Err(err) = index_pgn::run(workers) {
                 eprintln!("index-pgn failed: {err}");
                 std::process::exit(1);
             }
         }
-        Commands::CheckAgainstSyzygy => {
-            if let Err(err) = run_check_against_syzygy() {
+        Commands::CheckAgainstSyzygy {
+            exhaustive,
+            output,
+            fifty_move,
+        } => {
+            if let Err(err) = run_check_against_syzygy(exhaustive, output, fifty_move) {
                 eprintln!("check-against-syzygy failed: {err}");
                 std::process::exit(1);
             }
         }
+        Commands::Probe { tables } => {
+            if let Err(err) = run_probe(tables) {
+                eprintln!("probe failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Inspect { path } => {
+            if let Err(err) = run_inspect(&path) {
+                eprintln!("inspect failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Verify {
+            path,
+            sampled,
+            samples,
+            seed,
+        } => {
+            let result = if sampled {
+                run_verify_sampled(&path, seed, samples)
+            } else {
+                run_verify(&path)
+            };
+            if let Err(err) = result {
+                eprintln!("verify failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::ExportPacked { input, output } => {
+            if let Err(err) = run_export_packed(&input, &output) {
+                eprintln!("export-packed failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::ProbeMmap { path, index } => {
+            if let Err(err) = run_probe_mmap(&path, index) {
+                eprintln!("probe-mmap failed: {err}");
+                std::process::exit(1);
+            }
+        }
     }
 }
 
@@ -106,10 +236,15 @@ fn run_generate(material: MaterialKey) -> io::Result<()> {
             missing.join(", ")
         }
     );
+    // With no missing child materials, retrograde analysis should resolve every legal position,
+    // so any `Unknown` left over carries no information worth preserving and is safe to rewrite
+    // for compression.
+    let mask_unknown = missing.is_empty();
     table_builder.solve();
-    let wdl_table: WdlTable = table_builder.into();
+    let dtz_table: DtzTable = table_builder.into();
+    let wdl_table = WdlTable::from(&dtz_table);
     let total = wdl_table.positions.len() as f64;
-    let mut counts = [0usize; 7];
+    let mut counts = [0usize; 9];
     for wdl in &wdl_table.positions {
         counts[*wdl as usize] += 1;
     }
@@ -122,6 +257,8 @@ fn run_generate(material: MaterialKey) -> io::Result<()> {
         WdlScoreRange::Draw,
         WdlScoreRange::Loss,
         WdlScoreRange::IllegalPosition,
+        WdlScoreRange::CursedWin,
+        WdlScoreRange::BlessedLoss,
     ] {
         let count = counts[variant as usize];
         let percentage = if total > 0.0 {
@@ -131,14 +268,38 @@ fn run_generate(material: MaterialKey) -> io::Result<()> {
         };
         println!("{variant:?}: {percentage:.2}%");
     }
+
+    let dtz_values: Vec<i64> = dtz_table
+        .positions
+        .iter()
+        .map(|pos| pos.to_storage_value())
+        .filter(|&value| value != i8::MIN)
+        .map(|value| value as i64)
+        .collect();
+    let dtz_min = dtz_values.iter().copied().min();
+    let dtz_max = dtz_values.iter().copied().max();
+    println!(
+        "DTZ statistics: min={}, max={}",
+        dtz_min.map_or("n/a".to_string(), |v| v.to_string()),
+        dtz_max.map_or("n/a".to_string(), |v| v.to_string())
+    );
+
     let filename = format!("./data/heisenbase/{}.hbt", wdl_table.material);
-    write_wdl_file(&filename, &wdl_table)?;
+    write_wdl_file_with_options(&filename, &wdl_table, mask_unknown)?;
     println!("Wrote table to {}", filename);
+
+    let dtz_filename = format!("./data/heisenbase/{}.hbz", dtz_table.material);
+    write_dtz_file(&dtz_filename, &dtz_table)?;
+    println!("Wrote table to {}", dtz_filename);
     println!();
     Ok(())
 }
 
-fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<(), Box<dyn Error>> {
+fn run_generate_many(
+    min_games: u64,
+    max_pieces: u32,
+    filter: Option<String>,
+) -> Result<(), Box<dyn Error>> {
     let df = LazyFrame::scan_parquet(index_pgn::PARQUET_PATH, Default::default())
         .unwrap()
         .filter(col("num_games").gt(1))
@@ -156,14 +317,31 @@ fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<(), Box<dyn Erro
         .unwrap();
 
     let keys = df.column("material_key").unwrap();
+    let games_counts = df.column("num_games").unwrap();
+
+    let predicate = match filter {
+        Some(expr) => Some(filter_expr::parse(&expr)?),
+        None => None,
+    };
 
     let mut candidates = Vec::new();
-    for key in keys.str().unwrap().into_iter() {
+    for (key, num_games) in keys
+        .str()
+        .unwrap()
+        .into_iter()
+        .zip(games_counts.u64().unwrap().into_iter())
+    {
         let material_key = MaterialKey::from_string(key.expect("material_key null"))
             .expect("invalid material key");
+        let num_games = num_games.expect("num_games null");
         if material_key.total_piece_count() > max_pieces {
             continue;
         }
+        if let Some(predicate) = &predicate {
+            if !predicate.matches(&material_key, num_games) {
+                continue;
+            }
+        }
         candidates.push(material_key);
     }
 
@@ -182,21 +360,52 @@ fn run_generate_many(min_games: u64, max_pieces: u32) -> Result<(), Box<dyn Erro
         max_pieces
     );
 
+    // `TableBuilder::new` loads every child material's `.hbt` from disk, and a child always
+    // has strictly fewer pieces than its parent, so generating in ascending piece-count waves
+    // guarantees a key's children are already on disk (or excluded entirely) by the time its
+    // own wave runs. Tables within a wave are mutually independent and run across a thread
+    // pool; the wave boundary is the only synchronization needed.
+    let mut waves: BTreeMap<u32, Vec<MaterialKey>> = BTreeMap::new();
     for material_key in candidates {
-        let material_str = material_key.to_string();
-        let filename = format!("./data/heisenbase/{}.hbt", material_str);
-        if Path::new(&filename).exists() {
-            println!("Skipping {} (already exists)", material_str);
+        waves
+            .entry(material_key.total_piece_count())
+            .or_default()
+            .push(material_key);
+    }
+
+    for (piece_count, wave) in waves {
+        let wave: Vec<MaterialKey> = wave
+            .into_iter()
+            .filter(|material_key| {
+                let filename = format!("./data/heisenbase/{}.hbt", material_key);
+                if Path::new(&filename).exists() {
+                    println!("Skipping {} (already exists)", material_key);
+                    false
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        if wave.is_empty() {
             continue;
         }
-        println!("Generating {}", material_str);
-        if let Err(err) = run_generate(material_key) {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("failed to generate {}: {}", material_str, err),
-            )
-            .into());
-        }
+
+        println!(
+            "Generating wave of {}-piece material keys ({} tables)...",
+            piece_count,
+            wave.len()
+        );
+        wave.into_par_iter().try_for_each(|material_key| {
+            let material_str = material_key.to_string();
+            println!("Generating {}", material_str);
+            run_generate(material_key).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("failed to generate {}: {}", material_str, err),
+                )
+            })
+        })?;
     }
 
     Ok(())
@@ -229,6 +438,30 @@ fn heisenbase_allows(wdl: WdlScoreRange, syzygy: SimpleWdl) -> bool {
         WdlScoreRange::DrawOrLoss => matches!(syzygy, SimpleWdl::Draw | SimpleWdl::Loss),
         WdlScoreRange::Unknown => true,
         WdlScoreRange::IllegalPosition => false,
+        WdlScoreRange::CursedWin => syzygy == SimpleWdl::Win,
+        WdlScoreRange::BlessedLoss => syzygy == SimpleWdl::Loss,
+    }
+}
+
+/// `--fifty-move` variant of [`heisenbase_allows`]: keeps `CursedWin`/`BlessedLoss` distinct
+/// from a true win/loss, rather than folding them together via [`simplify_wdl`], so a
+/// heisenbase `Win`/`Loss` is only accepted against a Syzygy value that is actually winning or
+/// losing, not one that is a draw in practice under the 50-move rule.
+fn heisenbase_allows_fifty_move(wdl: WdlScoreRange, syzygy: Wdl) -> bool {
+    match wdl {
+        WdlScoreRange::Win => syzygy == Wdl::Win,
+        WdlScoreRange::Draw => matches!(syzygy, Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss),
+        WdlScoreRange::Loss => syzygy == Wdl::Loss,
+        WdlScoreRange::WinOrDraw => {
+            matches!(syzygy, Wdl::Win | Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss)
+        }
+        WdlScoreRange::DrawOrLoss => {
+            matches!(syzygy, Wdl::Draw | Wdl::CursedWin | Wdl::BlessedLoss | Wdl::Loss)
+        }
+        WdlScoreRange::Unknown => true,
+        WdlScoreRange::IllegalPosition => false,
+        WdlScoreRange::CursedWin => syzygy == Wdl::Win,
+        WdlScoreRange::BlessedLoss => syzygy == Wdl::Loss,
     }
 }
 
@@ -269,7 +502,135 @@ fn collect_valid_indices(indexer: &PositionIndexer) -> Vec<usize> {
     valid
 }
 
-fn run_check_against_syzygy() -> Result<(), Box<dyn Error>> {
+/// Record a material key's Syzygy cross-validation result, alongside the WDL/DTZ stats
+/// `run_generate` prints for it.
+fn log_syzygy_check(material: &MaterialKey, checked: usize, mismatches: usize) {
+    println!("Syzygy check: {material} checked={checked} mismatches={mismatches}");
+}
+
+/// Outcome of cross-validating one material key's table against Syzygy.
+struct MaterialCheckOutcome {
+    material: MaterialKey,
+    positions_checked: usize,
+    mismatches: usize,
+    uncertain: usize,
+    missing_table: bool,
+    probe_failed: bool,
+    /// One line per mismatch (material, index, both WDL values, FEN), written out only when
+    /// `--output` is in use.
+    mismatch_lines: Vec<String>,
+}
+
+/// Cross-validate one material key's `.hbt` table against Syzygy, either over
+/// `SAMPLES_PER_TABLE` random indices or, in `exhaustive` mode, every valid index.
+///
+/// Independent of every other material key, so [`run_check_against_syzygy`] calls this once
+/// per table from a `rayon` parallel iterator rather than a sequential loop.
+fn check_material(
+    material: MaterialKey,
+    heisenbase_dir: &Path,
+    tablebase: &Tablebase<Chess>,
+    exhaustive: bool,
+    fifty_move: bool,
+) -> io::Result<MaterialCheckOutcome> {
+    let table_path = heisenbase_dir.join(format!("{}.hbt", material));
+    let mut table = WdlFileReader::open(&table_path)?;
+    let indexer = PositionIndexer::new(material.clone());
+    let valid_indices = collect_valid_indices(&indexer);
+    if valid_indices.is_empty() {
+        eprintln!("No valid positions for {}", material);
+        return Ok(MaterialCheckOutcome {
+            material,
+            positions_checked: 0,
+            mismatches: 0,
+            uncertain: 0,
+            missing_table: false,
+            probe_failed: false,
+            mismatch_lines: Vec::new(),
+        });
+    }
+
+    let indices: Vec<usize> = if exhaustive {
+        valid_indices
+    } else {
+        let mut rng = StdRng::from_entropy();
+        (0..SAMPLES_PER_TABLE)
+            .map(|_| valid_indices[rng.gen_range(0..valid_indices.len())])
+            .collect()
+    };
+
+    let mut positions_checked = 0usize;
+    let mut mismatches = 0usize;
+    let mut uncertain = 0usize;
+    let mut missing_table = false;
+    let mut probe_failed = false;
+    let mut mismatch_lines = Vec::new();
+
+    for idx in indices {
+        let pos = match indexer.index_to_position(idx) {
+            Ok(pos) => pos,
+            Err(_) => continue,
+        };
+
+        let hb_wdl = table.probe_index(idx)?;
+        if hb_wdl.is_uncertain() {
+            uncertain += 1;
+        }
+
+        let syzygy_wdl = if fifty_move {
+            tablebase.probe_wdl(&pos)
+        } else {
+            tablebase.probe_wdl_after_zeroing(&pos)
+        };
+        let syzygy_wdl = match syzygy_wdl {
+            Ok(wdl) => wdl,
+            Err(SyzygyError::MissingTable { .. }) => {
+                missing_table = true;
+                break;
+            }
+            Err(_) => {
+                probe_failed = true;
+                break;
+            }
+        };
+
+        positions_checked += 1;
+        let allowed = if fifty_move {
+            heisenbase_allows_fifty_move(hb_wdl, syzygy_wdl)
+        } else {
+            heisenbase_allows(hb_wdl, simplify_wdl(syzygy_wdl))
+        };
+        if !allowed {
+            mismatches += 1;
+            let fen = Fen::from_position(&pos, EnPassantMode::Legal).to_string();
+            if !exhaustive && mismatches <= MAX_MISMATCHES_PER_TABLE {
+                println!(
+                    "Mismatch {}: hb={:?}, syzygy={:?}, fen={}",
+                    material, hb_wdl, syzygy_wdl, fen
+                );
+            }
+            mismatch_lines.push(format!(
+                "{material}\t{idx}\thb={hb_wdl:?}\tsyzygy={syzygy_wdl:?}\tfen={fen}"
+            ));
+        }
+    }
+
+    Ok(MaterialCheckOutcome {
+        material,
+        positions_checked,
+        mismatches,
+        uncertain,
+        missing_table,
+        probe_failed,
+        mismatch_lines,
+    })
+}
+
+fn run_check_against_syzygy(
+    exhaustive: bool,
+    output: Option<PathBuf>,
+    fifty_move: bool,
+) -> Result<(), Box<dyn Error>> {
     let heisenbase_dir = Path::new("./data/heisenbase");
     let syzygy_dir = Path::new("./data/syzygy");
 
@@ -294,13 +655,20 @@ fn run_check_against_syzygy() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let mut rng = StdRng::from_entropy();
+    if exhaustive {
+        println!("Running exhaustive check over every valid position (parallel across tables)...");
+    }
+    if fifty_move {
+        println!("Running in --fifty-move mode: probing as-is and requiring true win/loss match.");
+    }
+
     let mut total_tables = 0usize;
     let mut total_positions = 0usize;
     let mut total_mismatches = 0usize;
     let mut total_uncertain = 0usize;
     let mut missing_tables = 0usize;
     let mut probe_errors = 0usize;
+    let mut all_mismatch_lines = Vec::new();
 
     for (label, keys) in [("3-man", three_man), ("4-man", four_man)] {
         println!(
@@ -308,81 +676,57 @@ fn run_check_against_syzygy() -> Result<(), Box<dyn Error>> {
             label,
             keys.len()
         );
-        for material in keys {
-            total_tables += 1;
-            let table_path = heisenbase_dir.join(format!("{}.hbt", material));
-            let table = read_wdl_file(&table_path)?;
-            let indexer = PositionIndexer::new(material.clone());
-            let valid_indices = collect_valid_indices(&indexer);
-            if valid_indices.is_empty() {
-                eprintln!("No valid positions for {}", material);
-                continue;
-            }
-
-            let mut mismatches = 0usize;
-            let mut uncertain = 0usize;
-            let mut missing_table = false;
-            let mut probe_failed = false;
-
-            for _ in 0..SAMPLES_PER_TABLE {
-                let idx = valid_indices[rng.gen_range(0..valid_indices.len())];
-                let pos = match indexer.index_to_position(idx) {
-                    Ok(pos) => pos,
-                    Err(_) => continue,
-                };
-
-                let hb_wdl = table.positions[idx];
-                if hb_wdl.is_uncertain() {
-                    uncertain += 1;
-                }
-
-                let syzygy_wdl = match tablebase.probe_wdl_after_zeroing(&pos) {
-                    Ok(wdl) => wdl,
-                    Err(SyzygyError::MissingTable { .. }) => {
-                        missing_table = true;
-                        break;
-                    }
-                    Err(_) => {
-                        probe_failed = true;
-                        break;
-                    }
-                };
-
-                let syzygy_simple = simplify_wdl(syzygy_wdl);
-                if !heisenbase_allows(hb_wdl, syzygy_simple) {
-                    mismatches += 1;
-                    if mismatches <= MAX_MISMATCHES_PER_TABLE {
-                        let fen = Fen::from_position(&pos, EnPassantMode::Legal).to_string();
-                        println!(
-                            "Mismatch {}: hb={:?}, syzygy={:?}, fen={}",
-                            material, hb_wdl, syzygy_wdl, fen
-                        );
-                    }
-                }
-            }
-
-            if missing_table {
+        total_tables += keys.len();
+
+        let outcomes: Vec<io::Result<MaterialCheckOutcome>> = keys
+            .into_par_iter()
+            .map(|material| {
+                check_material(material, heisenbase_dir, &tablebase, exhaustive, fifty_move)
+            })
+            .collect();
+
+        for outcome in outcomes {
+            let outcome = outcome?;
+            if outcome.missing_table {
                 missing_tables += 1;
-                eprintln!("Missing Syzygy tables for {}", material);
+                eprintln!("Missing Syzygy tables for {}", outcome.material);
                 continue;
             }
-            if probe_failed {
+            if outcome.probe_failed {
                 probe_errors += 1;
-                eprintln!("Syzygy probe failed for {}", material);
+                eprintln!("Syzygy probe failed for {}", outcome.material);
+                continue;
+            }
+            if outcome.positions_checked == 0 {
                 continue;
             }
 
-            total_positions += SAMPLES_PER_TABLE;
-            total_mismatches += mismatches;
-            total_uncertain += uncertain;
+            total_positions += outcome.positions_checked;
+            total_mismatches += outcome.mismatches;
+            total_uncertain += outcome.uncertain;
+            log_syzygy_check(&outcome.material, outcome.positions_checked, outcome.mismatches);
 
-            if mismatches > 0 {
+            if outcome.mismatches > 0 {
                 println!(
                     "Found {} mismatches in {} ({} uncertain samples).",
-                    mismatches, material, uncertain
+                    outcome.mismatches, outcome.material, outcome.uncertain
                 );
             }
+            all_mismatch_lines.extend(outcome.mismatch_lines);
+        }
+    }
+
+    if let Some(output_path) = &output {
+        let mut contents = all_mismatch_lines.join("\n");
+        if !all_mismatch_lines.is_empty() {
+            contents.push('\n');
         }
+        fs::write(output_path, contents)?;
+        println!(
+            "Wrote {} mismatch line(s) to {}",
+            all_mismatch_lines.len(),
+            output_path.display()
+        );
     }
 
     println!(
@@ -408,3 +752,218 @@ fn run_check_against_syzygy() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// Lazily opens and caches `.hbt`/`.hbz` tables across one or more directories, keyed by
+/// [`MaterialKey`], so [`run_probe`] only pays to load a table the first time a FEN needs it.
+struct ProbeTables {
+    dirs: Vec<PathBuf>,
+    wdl_readers: HashMap<MaterialKey, WdlFileReader>,
+    dtz_tables: HashMap<MaterialKey, Option<DtzTable>>,
+}
+
+impl ProbeTables {
+    fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            wdl_readers: HashMap::new(),
+            dtz_tables: HashMap::new(),
+        }
+    }
+
+    /// First existing `{material}.{extension}` across `dirs`, in priority order.
+    fn find_file(&self, material: &MaterialKey, extension: &str) -> Option<PathBuf> {
+        self.dirs.iter().find_map(|dir| {
+            let path = dir.join(format!("{material}.{extension}"));
+            path.exists().then_some(path)
+        })
+    }
+
+    fn wdl_reader(&mut self, material: &MaterialKey) -> io::Result<&mut WdlFileReader> {
+        if !self.wdl_readers.contains_key(material) {
+            let path = self.find_file(material, "hbt").ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no .hbt table found for {material}"),
+                )
+            })?;
+            self.wdl_readers
+                .insert(material.clone(), WdlFileReader::open(&path)?);
+        }
+        Ok(self.wdl_readers.get_mut(material).unwrap())
+    }
+
+    /// `None` if no `.hbz` file exists for `material`; DTZ tables are optional, unlike WDL.
+    fn dtz_table(&mut self, material: &MaterialKey) -> io::Result<Option<&DtzTable>> {
+        if !self.dtz_tables.contains_key(material) {
+            let table = match self.find_file(material, "hbz") {
+                Some(path) => Some(read_dtz_file(&path)?),
+                None => None,
+            };
+            self.dtz_tables.insert(material.clone(), table);
+        }
+        Ok(self.dtz_tables.get(material).unwrap().as_ref())
+    }
+
+    /// Probe a single position, returning its WDL value and, if a `.hbz` table is available,
+    /// its DTZ (half-moves to the next zeroing move, signed positive for a win and negative
+    /// for a loss).
+    fn probe(&mut self, position: &Chess) -> Result<(WdlScoreRange, Option<i64>), String> {
+        let material = MaterialKey::from_position(position)
+            .ok_or_else(|| "position has no valid material key".to_string())?;
+        let indexer = PositionIndexer::new(material.clone());
+        let index = indexer
+            .position_to_index(position)
+            .map_err(|err| format!("position not indexable for {material}: {err:?}"))?;
+
+        let wdl = self
+            .wdl_reader(&material)
+            .map_err(|err| err.to_string())?
+            .probe_index(index)
+            .map_err(|err| err.to_string())?;
+        let dtz = self
+            .dtz_table(&material)
+            .map_err(|err| err.to_string())?
+            .and_then(|table| {
+                let value = table.positions[index].to_storage_value();
+                (value != i8::MIN).then_some(value as i64)
+            });
+
+        Ok((wdl, dtz))
+    }
+}
+
+fn run_probe(table_dirs: Option<String>) -> io::Result<()> {
+    let dirs: Vec<PathBuf> = match table_dirs {
+        Some(paths) => std::env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("./data/heisenbase")],
+    };
+    let mut tables = ProbeTables::new(dirs);
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let fen = line.trim();
+        if fen.is_empty() {
+            continue;
+        }
+
+        let parsed = Fen::from_ascii(fen.as_bytes())
+            .map_err(|err| format!("invalid FEN: {err}"))
+            .and_then(|f| {
+                f.into_position::<Chess>(CastlingMode::Standard)
+                    .map_err(|err| format!("illegal position: {err}"))
+            });
+        let position = match parsed {
+            Ok(position) => position,
+            Err(err) => {
+                println!("error: {fen}: {err}");
+                continue;
+            }
+        };
+
+        match tables.probe(&position) {
+            Ok((wdl, Some(dtz))) => println!("{fen}: {wdl:?} dtz={dtz}"),
+            Ok((wdl, None)) => println!("{fen}: {wdl:?}"),
+            Err(err) => println!("error: {fen}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn run_inspect(path: &Path) -> io::Result<()> {
+    let (material, total_positions, blocks) = inspect_wdl_file(path)?;
+
+    println!("Material: {material}");
+    println!("Positions: {total_positions}");
+    println!("Blocks: {}", blocks.len());
+
+    let mut scheme_counts: HashMap<&str, usize> = HashMap::new();
+    let mut code_len_histogram: HashMap<u8, usize> = HashMap::new();
+    let mut total_bitstream_bytes = 0usize;
+    let mut total_generated_symbols = 0usize;
+    for block in &blocks {
+        *scheme_counts.entry(block.scheme_name).or_insert(0) += 1;
+        for (&len, &count) in &block.code_len_histogram {
+            *code_len_histogram.entry(len).or_insert(0) += count;
+        }
+        total_bitstream_bytes += block.bitstream_bytes;
+        total_generated_symbols += block.generated_symbols;
+    }
+
+    println!("Schemes: {scheme_counts:?}");
+    println!("Generated symbols (pair substitution blocks): {total_generated_symbols}");
+    println!("Bitstream bytes: {total_bitstream_bytes}");
+
+    let mut lens: Vec<u8> = code_len_histogram.keys().copied().collect();
+    lens.sort_unstable();
+    println!("Huffman code-length histogram:");
+    for len in lens {
+        println!("  {len} bits: {} symbols", code_len_histogram[&len]);
+    }
+
+    if total_positions > 0 {
+        let ratio = total_bitstream_bytes as f64 / total_positions as f64;
+        println!(
+            "Compression ratio vs 1 byte/position: {:.2}% ({total_bitstream_bytes} / {total_positions} bytes)",
+            ratio * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+fn run_verify(path: &Path) -> io::Result<()> {
+    let total_positions = verify_wdl_file(path)?;
+    println!(
+        "OK: {total_positions} positions decoded successfully from {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Cross-check `samples` pseudo-random positions of the `.hbt` file at `path` against
+/// retrograde re-derivation via [`verify_sampled`], instead of just decompressing every block.
+/// Needs `path`'s sibling child `.hbt` files in the same directory to re-derive scores from.
+fn run_verify_sampled(path: &Path, seed: u32, samples: usize) -> io::Result<()> {
+    let material = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(MaterialKey::from_string)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot derive a material key from {}", path.display()),
+            )
+        })?;
+    let data_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut prober = Prober::new(data_dir);
+    verify_sampled(&mut prober, &material, seed, samples).map_err(|failure| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{failure:?}"))
+    })?;
+
+    println!("OK: {samples} sampled positions verified for {material}");
+    Ok(())
+}
+
+/// Read an `.hbt` file and re-write it in the bit-packed `.hbk` format ([`write_packed_wdl_file`]),
+/// trading its adaptive compression for a fixed-width encoding with a simpler reader.
+fn run_export_packed(input: &Path, output: &Path) -> io::Result<()> {
+    let table = read_wdl_file(input)?;
+    write_packed_wdl_file(output, &table)?;
+    println!(
+        "Wrote {} positions to {} in bit-packed format",
+        table.positions.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Look up a single position by raw index in a block-structured, memory-mapped `.hbm` file
+/// written by [`heisenbase::wdl_mmap::write_mmap_wdl_file`], touching only the owning block.
+fn run_probe_mmap(path: &Path, index: usize) -> io::Result<()> {
+    let table = WdlTable::open(path)?;
+    let value = table.probe(index)?;
+    println!("{value:?}");
+    Ok(())
+}
@@ -3,6 +3,9 @@ mod pawn_structure;
 mod piece_counts;
 
 use crate::material_key::pawn_structure::PawnStructure;
+use crate::position_indexer::PositionIndexer;
+use crate::transform::Transform;
+use crate::zobrist::{MATERIAL_PRESENCE_KEYS, PIECE_SQUARE_KEYS, piece_key_index};
 use itertools::iproduct;
 use shakmaty::{Bitboard, ByColor, Chess, Color, Position, Role, Square};
 use std::{cmp::Ordering, collections::BTreeSet, fmt, iter::once};
@@ -14,6 +17,25 @@ use winnow::token::{literal, take};
 pub use hb_piece::{HbPiece, HbPieceRole};
 pub use piece_counts::PieceCounts;
 
+/// One slot per `(HbPieceRole, Color)` combination, for [`MaterialKey::zobrist`]'s squareless
+/// "this piece is present" keys. Distinct from [`crate::zobrist::piece_key_index`], which is
+/// keyed by `shakmaty::Role` and so can't tell a light-squared bishop from a dark-squared one.
+fn material_presence_kind(role: HbPieceRole, color: Color) -> usize {
+    let role_index = match role {
+        HbPieceRole::King => 0,
+        HbPieceRole::Queen => 1,
+        HbPieceRole::Rook => 2,
+        HbPieceRole::LightBishop => 3,
+        HbPieceRole::DarkBishop => 4,
+        HbPieceRole::Knight => 5,
+    };
+    role_index * 2
+        + match color {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+}
+
 /// Represents a material configuration, e.g. `KQvK`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct MaterialKey {
@@ -126,6 +148,18 @@ impl MaterialKey {
         self
     }
 
+    /// Pick the lexicographically smallest of the 4 material keys reachable by swapping
+    /// sides and/or mirroring left-to-right.
+    ///
+    /// A pawnless key is also invariant under the other 4 elements of the full 8-fold board
+    /// symmetry (vertical and both diagonal reflections), but a bare piece count carries no
+    /// square information for those extra reflections to act on beyond the same
+    /// color-parity-driven bishop swap the horizontal flip already triggers here — so they
+    /// would only ever revisit one of these same 4 keys, never shrink the orbit further.
+    /// [`crate::position_indexer::PositionIndexer`] is where that extra symmetry actually
+    /// pays off: it canonicalizes concrete king squares (which do carry geometry) against the
+    /// full 8-element [`crate::transform::ALL_TRANSFORMS`] group for pawnless, bishopless
+    /// material keys.
     fn into_normalized(self) -> Self {
         [
             self.clone(),
@@ -151,6 +185,15 @@ impl MaterialKey {
                 .map(|ps| MaterialKey::new(self.counts.clone(), ps)),
         );
 
+        // En-passant captures: a pawn count change that `child_pawn_structures_no_piece_change`
+        // can't represent, since the captured pawn isn't on the landing square.
+        children.extend(
+            self.pawns
+                .child_pawn_structures_en_passant()
+                .into_iter()
+                .map(|ps| MaterialKey::new(self.counts.clone(), ps)),
+        );
+
         // Captures: any move that removes an opponent piece (except the king).
         for color in Color::ALL {
             let opponent = color.other();
@@ -216,7 +259,13 @@ impl MaterialKey {
         children
     }
 
-    pub fn from_position(position: &Chess) -> Option<Self> {
+    /// Build the material key directly from `position`'s pieces, without picking the
+    /// lexicographically smallest of the 4 side/mirror-equivalent representations
+    /// [`into_normalized`](Self::into_normalized) does. Used by
+    /// [`from_position`](Self::from_position) and
+    /// [`from_position_with_swap`](Self::from_position_with_swap), which both need this
+    /// position-accurate (but un-normalized) key before deciding whether to normalize it.
+    fn raw_from_position(position: &Chess) -> Option<Self> {
         let mut counts = ByColor::new_with(|_| PieceCounts::empty());
         for square in Square::ALL {
             if let Some(piece) = position.board().piece_at(square) {
@@ -240,10 +289,28 @@ impl MaterialKey {
             }
         }
 
-        Some(MaterialKey::new(
+        Some(Self {
             counts,
-            PawnStructure::from_board(position.board()),
-        ))
+            pawns: PawnStructure::from_board(position.board()),
+        })
+    }
+
+    pub fn from_position(position: &Chess) -> Option<Self> {
+        Self::raw_from_position(position).map(Self::into_normalized)
+    }
+
+    /// Like [`from_position`](Self::from_position), but also reports whether normalizing swapped
+    /// white and black to land on the canonical key: `false` means the side listed first
+    /// (before `v`) is still `position`'s actual white, `true` means it's black's material that
+    /// ended up first. Callers that need to attribute a real game's outcome to "whichever side
+    /// is listed first in the key" (e.g. PGN result tallying) need this, since
+    /// [`into_normalized`](Self::into_normalized) is free to swap sides underneath them.
+    pub fn from_position_with_swap(position: &Chess) -> Option<(Self, bool)> {
+        let raw = Self::raw_from_position(position)?;
+        let normalized = raw.clone().into_normalized();
+        let swapped =
+            normalized != raw && normalized != raw.clone().into_mirrored_left_to_right();
+        Some((normalized, swapped))
     }
 
     pub fn pieces(&self) -> impl Iterator<Item = HbPiece> {
@@ -253,6 +320,223 @@ impl MaterialKey {
             })
         })
     }
+
+    /// How Syzygy tablebase names rank `color`'s side against the other when deciding which one
+    /// is listed first: `(total non-king pieces, count of "unique" i.e. singleton piece types)`,
+    /// compared lexicographically. Unlike [`Self::into_normalized`]'s own
+    /// queen-then-rook-then-bishop-then-knight tiebreak, this never looks at *which* roles a
+    /// side holds, only how many of each — so e.g. `KQvKR` and `KRvKQ` rank their sides
+    /// identically (one queen, one unique role, either way), while heisenbase's own ordering
+    /// would always prefer the queen-holding side. See [`Self::to_syzygy_name`].
+    fn syzygy_side_strength(&self, color: Color) -> (u8, u8) {
+        let counts = self.counts[color];
+        let bishops = counts[HbPieceRole::LightBishop] + counts[HbPieceRole::DarkBishop];
+        let role_counts = [
+            counts[HbPieceRole::Queen],
+            counts[HbPieceRole::Rook],
+            bishops,
+            counts[HbPieceRole::Knight],
+            self.pawns.0[color].count() as u8,
+        ];
+        let total = role_counts.iter().sum();
+        let unique = role_counts.iter().filter(|&&count| count == 1).count() as u8;
+        (total, unique)
+    }
+
+    /// Render this key the way Syzygy tablebase names do: light and dark bishops collapse into a
+    /// single `B`, pawns lose their squares and become bare `P` counts, and the two sides are
+    /// ordered by [`Self::syzygy_side_strength`] (stronger side first) rather than
+    /// [`Self::into_normalized`]'s tiebreak. Ties keep their current order.
+    ///
+    /// The result is lossy in both directions this crate's own notation isn't: besides the
+    /// bishop color and pawn squares, two sides that are equally strong by
+    /// [`Self::syzygy_side_strength`] but hold different pieces (e.g. `KQvKR`) have no canonical
+    /// order in Syzygy's scheme the way heisenbase's own `into_normalized` always provides one.
+    pub fn to_syzygy_name(&self) -> String {
+        fn side_tokens(counts: PieceCounts, pawn_count: u32) -> String {
+            let mut tokens = String::new();
+            tokens.push_str(&"Q".repeat(counts[HbPieceRole::Queen] as usize));
+            tokens.push_str(&"R".repeat(counts[HbPieceRole::Rook] as usize));
+            let bishops = counts[HbPieceRole::LightBishop] + counts[HbPieceRole::DarkBishop];
+            tokens.push_str(&"B".repeat(bishops as usize));
+            tokens.push_str(&"N".repeat(counts[HbPieceRole::Knight] as usize));
+            tokens.push_str(&"P".repeat(pawn_count as usize));
+            tokens
+        }
+
+        let (first, second) =
+            if self.syzygy_side_strength(Color::Black) > self.syzygy_side_strength(Color::White) {
+                (Color::Black, Color::White)
+            } else {
+                (Color::White, Color::Black)
+            };
+
+        format!(
+            "K{}vK{}",
+            side_tokens(self.counts[first], self.pawns.0[first].count() as u32),
+            side_tokens(self.counts[second], self.pawns.0[second].count() as u32)
+        )
+    }
+
+    /// Parse a Syzygy-style material name like `KQvKR` into a [`MaterialKey`].
+    ///
+    /// Returns `None` whenever the name can't be faithfully reconstructed:
+    /// - Any `P` makes pawn squares unrecoverable (a Syzygy name only ever records a pawn
+    ///   *count*, never positions), so names with pawns are rejected outright.
+    /// - Two or more `B`s, on one side or one on each side, are genuinely ambiguous: heisenbase
+    ///   distinguishes same-colored bishops from opposite-colored ones (see
+    ///   [`crate::transform::TransformSet::for_material`]), but Syzygy's `B` does not, so there's
+    ///   no single `MaterialKey` to return. A name with at most one `B` is unambiguous — the
+    ///   bishop's color is immaterial to [`Self::into_normalized`] when there's only one of them.
+    pub fn from_syzygy_name(s: &str) -> Option<Self> {
+        #[derive(Clone, Copy)]
+        enum SyzygyToken {
+            Piece(HbPieceRole),
+            Pawn,
+        }
+
+        fn token(input: &mut &[u8]) -> ModalResult<SyzygyToken> {
+            alt((
+                'Q'.value(SyzygyToken::Piece(HbPieceRole::Queen)),
+                'R'.value(SyzygyToken::Piece(HbPieceRole::Rook)),
+                'B'.value(SyzygyToken::Piece(HbPieceRole::DarkBishop)),
+                'N'.value(SyzygyToken::Piece(HbPieceRole::Knight)),
+                'P'.value(SyzygyToken::Pawn),
+            ))
+            .parse_next(input)
+        }
+
+        // Pawn count: rejected by the caller below, since a Syzygy name has no squares for us to
+        // place them on.
+        fn side(input: &mut &[u8]) -> ModalResult<(PieceCounts, u8)> {
+            literal("K").parse_next(input)?;
+            let tokens: Vec<SyzygyToken> = repeat(0.., token).parse_next(input)?;
+
+            let mut piece_counts = PieceCounts::empty();
+            let mut pawns = 0u8;
+            for token in tokens {
+                match token {
+                    SyzygyToken::Piece(role) => piece_counts[role] += 1,
+                    SyzygyToken::Pawn => pawns += 1,
+                }
+            }
+            Ok((piece_counts, pawns))
+        }
+
+        let mut input = s.as_bytes();
+        let ((white_counts, white_pawns), (black_counts, black_pawns)) =
+            terminated(separated_pair(side, 'v', side), eof)
+                .parse_next(&mut input)
+                .ok()?;
+
+        if white_pawns > 0 || black_pawns > 0 {
+            return None;
+        }
+
+        if white_counts[HbPieceRole::DarkBishop] + black_counts[HbPieceRole::DarkBishop] > 1 {
+            return None;
+        }
+
+        let mut counts = ByColor {
+            white: white_counts,
+            black: black_counts,
+        };
+        counts.white[HbPieceRole::King] += 1;
+        counts.black[HbPieceRole::King] += 1;
+
+        Some(Self::new(
+            counts,
+            PawnStructure::new(Bitboard::EMPTY, Bitboard::EMPTY),
+        ))
+    }
+
+    /// The number of distinct canonical indices for this material, i.e. one past the largest
+    /// value [`Self::index`] can return.
+    ///
+    /// Builds a [`PositionIndexer`] on every call, so a caller indexing many positions for the
+    /// same material (a generator's main loop, say) should build one directly with
+    /// [`PositionIndexer::new`] and keep it around instead — [`Prober`](crate::prober::Prober)
+    /// and [`TableBuilder`](crate::table_builder::TableBuilder) both do this to avoid rebuilding
+    /// the underlying binomial tables per lookup. This method is for one-off queries.
+    pub fn num_indices(&self) -> u64 {
+        PositionIndexer::new(self.clone()).total_positions() as u64
+    }
+
+    /// Map `pos` to its canonical index under this material's symmetries (see
+    /// [`PositionIndexer::position_to_index`]), or `None` if `pos` doesn't match this material
+    /// key or isn't a legal position under it.
+    ///
+    /// See [`Self::num_indices`]'s note on why a one-off call here rebuilds a
+    /// [`PositionIndexer`] from scratch.
+    pub fn index(&self, pos: &Chess) -> Option<u64> {
+        PositionIndexer::new(self.clone())
+            .position_to_index(pos)
+            .ok()
+            .map(|index| index as u64)
+    }
+
+    /// Invert [`Self::index`]: rebuild the canonical [`Chess`] position stored at `idx`, or
+    /// `None` if `idx` is out of range or decodes to an illegal position.
+    ///
+    /// See [`Self::num_indices`]'s note on why a one-off call here rebuilds a
+    /// [`PositionIndexer`] from scratch.
+    pub fn position_from_index(&self, idx: u64) -> Option<Chess> {
+        PositionIndexer::new(self.clone())
+            .index_to_position(idx as usize)
+            .ok()
+    }
+
+    /// Hash this material key to a 64-bit Zobrist-style key, stable across runs and machines and
+    /// identical for two keys that are the same material up to a horizontal mirror of the pawns
+    /// — e.g. `Kf2g2vK` and `Kb2c2vK` hash the same, since one is just the other's mirror image.
+    /// [`Self::new`] already normalizes such a pair to one key; for two un-normalized keys built
+    /// from the public fields, [`PartialEq`] tells them apart (their exact pawn squares differ),
+    /// while this is meant for callers that only care about the material up to board
+    /// orientation, e.g. a transposition-style cache keyed on "this shape of material", not on
+    /// which exact file the pawns happen to sit on.
+    ///
+    /// Non-pawn pieces have no squares to fold a transform over, so a transform can't change
+    /// their contribution: XORs one "occurrence" key per non-pawn piece (one sequence per
+    /// `(HbPieceRole, Color)`, so — unlike [`Self::syzygy_side_strength`] — a light-squared
+    /// bishop and a dark-squared one hash differently, since that distinction is a genuine,
+    /// outcome-relevant property of the material, not just a labeling choice), plus one
+    /// piece-square key per pawn taken from whichever of `{Identity, FlipHorizontal}` leaves the
+    /// pawn bitboards smallest.
+    pub fn zobrist(&self) -> u64 {
+        let mut key = 0u64;
+
+        for color in Color::ALL {
+            let counts = self.counts[color];
+            for role in HbPieceRole::ALL {
+                let kind = material_presence_kind(role, color);
+                for occurrence in 0..counts[role] as usize {
+                    key ^= MATERIAL_PRESENCE_KEYS[kind][occurrence];
+                }
+            }
+        }
+
+        let identity_pawns = (self.pawns.0.white, self.pawns.0.black);
+        let mirrored_pawns = (
+            Transform::FlipHorizontal.apply_bitboard(self.pawns.0.white),
+            Transform::FlipHorizontal.apply_bitboard(self.pawns.0.black),
+        );
+        let canonical_pawns = [identity_pawns, mirrored_pawns]
+            .into_iter()
+            .min_by_key(|(white, black)| (white.0, black.0))
+            .expect("always exactly 2 candidates");
+
+        for (color, pawns) in [
+            (Color::White, canonical_pawns.0),
+            (Color::Black, canonical_pawns.1),
+        ] {
+            let kind = piece_key_index(Role::Pawn, color);
+            for square in pawns {
+                key ^= PIECE_SQUARE_KEYS[square.to_usize()][kind];
+            }
+        }
+
+        key
+    }
 }
 
 impl fmt::Display for MaterialKey {
@@ -435,6 +719,17 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn child_material_keys_includes_en_passant_capture() {
+        let key = MaterialKey::from_string("Ke5vKd5").unwrap();
+        let expected_child = MaterialKey::new(
+            key.counts.clone(),
+            PawnStructure::new(Bitboard::from_square(Square::D6), Bitboard::EMPTY),
+        );
+
+        assert!(key.child_material_keys().contains(&expected_child));
+    }
+
     #[test]
     fn material_key_from_position() {
         let position = "8/4k3/8/8/8/8/3P4/4K3 w - - 0 1"
@@ -455,4 +750,128 @@ mod tests {
             3
         );
     }
+
+    #[test]
+    fn to_syzygy_name_collapses_bishop_colors() {
+        assert_eq!(
+            MaterialKey::from_string("KBdBlvKN").unwrap().to_syzygy_name(),
+            "KBBvKN"
+        );
+    }
+
+    #[test]
+    fn to_syzygy_name_drops_pawn_squares() {
+        assert_eq!(
+            MaterialKey::from_string("Ka2h2vK").unwrap().to_syzygy_name(),
+            "KPPvK"
+        );
+    }
+
+    #[test]
+    fn to_syzygy_name_puts_the_stronger_side_first() {
+        // 2 non-king pieces (both unique) beats 1 (also unique), regardless of which side of
+        // `v` either one started on.
+        assert_eq!(
+            MaterialKey::from_string("KRvKQR").unwrap().to_syzygy_name(),
+            "KQRvKR"
+        );
+    }
+
+    #[test]
+    fn to_syzygy_name_keeps_order_stable_when_sides_are_equally_strong() {
+        // One unique non-king piece each, so syzygy_side_strength ties; to_syzygy_name doesn't
+        // reorder on a tie.
+        assert_eq!(
+            MaterialKey::from_string("KQvKR").unwrap().to_syzygy_name(),
+            "KQvKR"
+        );
+    }
+
+    #[test]
+    fn syzygy_round_trips_standard_signatures() {
+        for name in ["KQvKR", "KRvKB", "KBNvKN", "KQRvKR", "KRRvKR"] {
+            let key = MaterialKey::from_syzygy_name(name)
+                .unwrap_or_else(|| panic!("{name} should parse"));
+            assert_eq!(key.to_syzygy_name(), name, "round-trip of {name}");
+        }
+    }
+
+    #[test]
+    fn from_syzygy_name_rejects_pawns() {
+        assert!(MaterialKey::from_syzygy_name("KPvK").is_none());
+    }
+
+    #[test]
+    fn from_syzygy_name_rejects_ambiguous_bishop_pairs() {
+        assert!(MaterialKey::from_syzygy_name("KBBvKN").is_none());
+    }
+
+    #[test]
+    fn from_syzygy_name_accepts_a_single_bishop_either_color() {
+        assert_eq!(
+            MaterialKey::from_syzygy_name("KBvKN"),
+            MaterialKey::from_string("KBlvKN")
+        );
+    }
+
+    #[test]
+    fn num_indices_matches_position_indexer() {
+        use crate::position_indexer::PositionIndexer;
+
+        let key = MaterialKey::from_string("KQvK").unwrap();
+        let indexer = PositionIndexer::new(key.clone());
+        assert_eq!(key.num_indices(), indexer.total_positions() as u64);
+    }
+
+    #[test]
+    fn index_round_trips_through_position_from_index() {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let key = MaterialKey::from_string("KQvK").unwrap();
+        let fen: Fen = "8/8/4k3/8/8/3Q4/8/4K3 w - - 0 1".parse().unwrap();
+        let position = fen.into_position(CastlingMode::Standard).unwrap();
+
+        let index = key.index(&position).unwrap();
+        let round_tripped = key.position_from_index(index).unwrap();
+
+        assert_eq!(key.index(&round_tripped), Some(index));
+    }
+
+    #[test]
+    fn index_rejects_mismatched_material() {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let key = MaterialKey::from_string("KQvK").unwrap();
+        let fen: Fen = "8/8/4k3/8/8/8/8/4K3 w - - 0 1".parse().unwrap();
+        let position = fen.into_position(CastlingMode::Standard).unwrap();
+
+        assert_eq!(key.index(&position), None);
+    }
+
+    #[test]
+    fn zobrist_is_invariant_under_horizontal_pawn_mirror() {
+        // `from_string` normalizes `Kf2g2vK` to this same key, so mirror it by hand to keep the
+        // f2/g2 pawn squares.
+        let key = MaterialKey::from_string("Kb2c2vK").unwrap();
+        let mirrored = key.clone().into_mirrored_left_to_right();
+
+        assert_ne!(key, mirrored, "the two keys' pawn squares are literally different");
+        assert_eq!(key.zobrist(), mirrored.zobrist());
+    }
+
+    #[test]
+    fn zobrist_differs_across_material() {
+        let krvk = MaterialKey::from_string("KRvK").unwrap();
+        let kqvk = MaterialKey::from_string("KQvK").unwrap();
+        assert_ne!(krvk.zobrist(), kqvk.zobrist());
+    }
+
+    #[test]
+    fn zobrist_differs_for_different_bishop_color() {
+        // `from_string` normalizes `KBlvK` and `KBdvK` to one key (the left-right mirror swaps
+        // a lone bishop's color), so build the other-colored key by mirroring by hand.
+        let key = MaterialKey::from_string("KBlvK").unwrap();
+        let other_color = key.clone().into_mirrored_left_to_right();
+        assert_ne!(key.zobrist(), other_color.zobrist());
+    }
 }
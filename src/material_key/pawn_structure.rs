@@ -138,6 +138,51 @@ impl PawnStructure {
             .collect()
     }
 
+    /// Returns the pawn structures reachable by an en-passant capture: a pawn standing on its own
+    /// 5th rank next to an enemy pawn that just advanced two squares lands on the skipped square
+    /// instead of the enemy pawn's square, which `child_pawn_structures_no_piece_change`'s normal
+    /// diagonal captures can't represent (they only ever remove whatever pawn sits on the landing
+    /// square). Gated purely on this rank-5/adjacent-file geometry, the same way the rest of this
+    /// module works from board shape alone rather than move history.
+    pub fn child_pawn_structures_en_passant(&self) -> Vec<PawnStructure> {
+        fn from_white_perspective(ps: &PawnStructure) -> Vec<PawnStructure> {
+            let capturing_rank = Bitboard::from_rank(Rank::Fifth);
+            let can_capture_right = capturing_rank.without(Bitboard::from_file(File::H))
+                & ps.0.white
+                & ps.0.black.shift(-1);
+            let can_capture_left = capturing_rank.without(Bitboard::from_file(File::A))
+                & ps.0.white
+                & ps.0.black.shift(1);
+
+            can_capture_right
+                .into_iter()
+                .map(|square| {
+                    let mut child = ps.clone();
+                    child.0.white.discard(square);
+                    child.0.white.add(square.offset(9).unwrap());
+                    child.0.black.discard(square.offset(1).unwrap());
+                    child
+                })
+                .chain(can_capture_left.into_iter().map(|square| {
+                    let mut child = ps.clone();
+                    child.0.white.discard(square);
+                    child.0.white.add(square.offset(7).unwrap());
+                    child.0.black.discard(square.offset(-1).unwrap());
+                    child
+                }))
+                .collect()
+        }
+
+        from_white_perspective(self)
+            .into_iter()
+            .chain(
+                from_white_perspective(&self.flip_sides())
+                    .into_iter()
+                    .map(|ps| ps.flip_sides()),
+            )
+            .collect()
+    }
+
     /// Returns the pawn structures that can be reached from this pawn structure when `color` makes a move
     /// by capturing a piece with a pawn without promoting a pawn.
     pub fn child_pawn_structures_with_piece_capture(&self, color: Color) -> Vec<PawnStructure> {
@@ -558,6 +603,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn child_pawn_structures_en_passant_relocates_capturing_pawn() {
+        let parent = PawnStructure::new(
+            Bitboard::from_square(Square::E5),
+            Bitboard::from_square(Square::D5),
+        );
+        assert_debug_snapshot!(parent.to_board(), @"
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . p P . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        ");
+        assert_debug_snapshot!(
+            parent
+                .child_pawn_structures_en_passant()
+                .into_iter()
+                .map(|ps| ps.to_board())
+                .collect::<Vec<Board>>(), @"
+        [
+            . . . . . . . .
+            . . . . . . . .
+            . . . P . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            ,
+        ]
+        "
+        );
+    }
+
+    #[test]
+    fn child_pawn_structures_en_passant_requires_adjacent_file() {
+        let parent = PawnStructure::new(
+            Bitboard::from_square(Square::E5),
+            Bitboard::from_square(Square::A5),
+        );
+        assert_debug_snapshot!(
+            parent
+                .child_pawn_structures_en_passant()
+                .into_iter()
+                .map(|ps| ps.to_board())
+                .collect::<Vec<Board>>(), @"[]"
+        );
+    }
+
     #[test]
     fn child_pawn_structures_with_piece_captures_generates_moves_for_white() {
         let parent = PawnStructure::new(
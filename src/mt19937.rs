@@ -0,0 +1,140 @@
+//! A self-contained, seedable Mersenne Twister (MT19937) generator.
+//!
+//! Used where a sequence needs to be bit-for-bit reproducible across machines given a seed
+//! (e.g. [`crate::verify::verify_sampled`]'s sample selection), rather than pulling in a
+//! nondeterministic system RNG or depending on an external crate's generator, which could
+//! change its output between versions out from under a fixed seed.
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+/// The classic 624-word-state MT19937, seeded and tempered per the reference algorithm.
+pub struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Self {
+        let mut state = [0u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            state[i] = 1_812_433_253u32
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        // Force a `generate()` on the first `next_u32` call rather than serving stale state.
+        Self { state, index: N }
+    }
+
+    fn generate(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mut next = self.state[(i + M) % N] ^ (y >> 1);
+            if y & 1 != 0 {
+                next ^= MATRIX_A;
+            }
+            self.state[i] = next;
+        }
+        self.index = 0;
+    }
+
+    /// Draw the next tempered 32-bit output.
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.generate();
+        }
+        let mut y = self.state[self.index];
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^= y >> 18;
+        self.index += 1;
+        y
+    }
+
+    /// Two draws from [`Self::next_u32`] packed into a 64-bit word, for ranges that don't fit
+    /// in 32 bits (a table's position count can run well past `u32::MAX`).
+    pub fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        let low = self.next_u32() as u64;
+        (high << 32) | low
+    }
+
+    /// Uniform in `0..bound`, via rejection sampling against the largest multiple of `bound`
+    /// that fits in a `u64` — unlike a plain `next_u64() % bound`, this doesn't bias the low
+    /// end of the range when `bound` doesn't evenly divide `u64::MAX + 1`.
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "bound must be positive");
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return value % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference output of the canonical `mt19937ar.c` implementation seeded with its own
+    // default seed (5489), from the algorithm's published `mt19937ar.out` test vector.
+    #[test]
+    fn matches_reference_implementation_for_the_canonical_seed() {
+        let mut rng = Mt19937::new(5489);
+        let expected = [
+            3499211612u32,
+            581869302,
+            3890346734,
+            3586334585,
+            545404204,
+            4161255391,
+            3922919429,
+            949333985,
+            2715962298,
+            1323567403,
+        ];
+        for value in expected {
+            assert_eq!(rng.next_u32(), value);
+        }
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_same_sequence() {
+        let mut a = Mt19937::new(12345);
+        let mut b = Mt19937::new(12345);
+        for _ in 0..1000 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        let mut a = Mt19937::new(1);
+        let mut b = Mt19937::new(2);
+        assert_ne!(a.next_u32(), b.next_u32());
+    }
+
+    #[test]
+    fn next_below_stays_within_bound() {
+        let mut rng = Mt19937::new(42);
+        for _ in 0..10_000 {
+            let value = rng.next_below(7);
+            assert!(value < 7);
+        }
+    }
+
+    #[test]
+    fn next_below_with_bound_one_is_always_zero() {
+        let mut rng = Mt19937::new(7);
+        for _ in 0..10 {
+            assert_eq!(rng.next_below(1), 0);
+        }
+    }
+}
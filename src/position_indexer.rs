@@ -1,7 +1,13 @@
-use crate::material_key::{HbPieceRole, MaterialKey};
+use crate::combinatorics::{n_choose_k, rank_combination, unrank_combination};
+use crate::material_key::{HbPiece, HbPieceRole, MaterialKey};
+use crate::transform::{IDENTITY_ONLY_TRANSFORMS, Transform, TransformSet};
+use crate::transpose::{TableLayout, transpose_blocked};
+use crate::zobrist::ZobristHasher;
 use shakmaty::{
-    Bitboard, CastlingMode, Chess, Color, FromSetup, Position, PositionErrorKinds, Setup, Square,
+    attacks, Bitboard, Board, CastlingMode, Chess, Color, EnPassantMode, FromSetup, Position,
+    PositionErrorKinds, Role, Setup, Square,
 };
+use std::collections::HashMap;
 
 fn nth_light_square(n: u32) -> Square {
     debug_assert!(n < 32);
@@ -27,29 +33,385 @@ fn nth_dark_square(n: u32) -> Square {
     Square::new(rank * 8 + file)
 }
 
+/// The number of squares a piece of `role` can occupy (32 for same-colored bishops, which
+/// are confined to one square color, 64 otherwise).
+fn domain_size(role: HbPieceRole) -> usize {
+    match role {
+        HbPieceRole::LightBishop | HbPieceRole::DarkBishop => 32,
+        _ => 64,
+    }
+}
+
+fn domain_index_to_square(role: HbPieceRole, index: usize) -> Square {
+    match role {
+        HbPieceRole::LightBishop => nth_light_square(index as u32),
+        HbPieceRole::DarkBishop => nth_dark_square(index as u32),
+        _ => Square::new(index as u32),
+    }
+}
+
+fn square_to_domain_index(role: HbPieceRole, square: Square) -> usize {
+    match role {
+        HbPieceRole::LightBishop | HbPieceRole::DarkBishop => square.to_usize() / 2,
+        _ => square.to_usize(),
+    }
+}
+
+fn role_mask(role: HbPieceRole) -> Bitboard {
+    match role {
+        HbPieceRole::LightBishop => Bitboard::LIGHT_SQUARES,
+        HbPieceRole::DarkBishop => Bitboard::DARK_SQUARES,
+        _ => Bitboard::FULL,
+    }
+}
+
+fn group_squares(board: &Board, role: HbPieceRole, color: Color) -> Vec<Square> {
+    let piece = HbPiece { role, color };
+    (role_mask(role) & board.by_piece(piece.into()))
+        .into_iter()
+        .collect()
+}
+
+/// Apply a board symmetry to a single square.
+fn apply_transform(square: Square, transform: Transform) -> Square {
+    transform.apply_square(square)
+}
+
+/// The symmetries that leave `material_key`'s positions unchanged as a set; see
+/// [`TransformSet::for_material`] for how pawns and bishops narrow this down. See
+/// `KRvKBd`/`KBdNvKQ` roundtrip tests below for the bishop-color-swap boundary this leaves in
+/// place.
+fn allowed_transforms(material_key: &MaterialKey) -> &'static [Transform] {
+    TransformSet::for_material(material_key).transforms()
+}
+
+/// One enumerable (side to move, en-passant target file, castling rights) combination.
+///
+/// Replaces a bare turn bit as the index's leading digit: for most material keys `ep_file`
+/// is always `None` and `castling_rights` is always `0`, making this equivalent to the old
+/// turn bit, but for pawn endgames where an en-passant capture is geometrically possible, or
+/// where a rook could still hold a castling right (see [`castling_available_mask`]), extra
+/// slots are reserved alongside the "nothing special" state for the turn on which they apply.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct EpState {
+    turn: Color,
+    /// File of the pawn that just double-pushed (and so the file of the capture-target
+    /// square), if an en-passant capture is available this turn.
+    ep_file: Option<u32>,
+    /// Subset of [`CASTLE_WK`]/[`CASTLE_WQ`]/[`CASTLE_BK`]/[`CASTLE_BQ`] this position still
+    /// holds. Always `0` unless [`castling_available_mask`] grants this material key any bits.
+    castling_rights: u8,
+}
+
+/// White's kingside/queenside and black's kingside/queenside castling-right bits.
+const CASTLE_WK: u8 = 1;
+const CASTLE_WQ: u8 = 2;
+const CASTLE_BK: u8 = 4;
+const CASTLE_BQ: u8 = 8;
+const ALL_CASTLING_BITS: [u8; 4] = [CASTLE_WK, CASTLE_WQ, CASTLE_BK, CASTLE_BQ];
+
+/// The rook's home square backing a single castling-right bit.
+fn castling_home_square(bit: u8) -> Square {
+    match bit {
+        CASTLE_WK => Square::H1,
+        CASTLE_WQ => Square::A1,
+        CASTLE_BK => Square::H8,
+        CASTLE_BQ => Square::A8,
+        _ => unreachable!("not a single castling-right bit: {bit}"),
+    }
+}
+
+/// The castling-rights bits this material key could ever hold: a color's kingside/queenside
+/// pair if it has a rook at all, since only the actual position (not the material key) fixes
+/// that rook to a particular square.
+///
+/// Gated to material keys [`allowed_transforms`] already restricts to the identity transform
+/// for unrelated (pawn-asymmetry) reasons. A transform other than the identity would move a
+/// claimed right off the home square it refers to, and most rook endgames this crate indexes
+/// are pawnless and would otherwise enjoy real symmetry compaction — paying for that with
+/// castling support here would cost every such table its 4- or 8-fold reduction for a right
+/// that, materially this deep into an endgame, is already lost in every practical game. So
+/// this only turns on for the pawnful, already-identity-only material keys where the
+/// compaction was forfeited anyway.
+fn castling_available_mask(material_key: &MaterialKey) -> u8 {
+    if std::ptr::eq(allowed_transforms(material_key), IDENTITY_ONLY_TRANSFORMS) {
+        let has_rook = |color: Color| {
+            material_key
+                .pieces()
+                .any(|piece| piece.role == HbPieceRole::Rook && piece.color == color)
+        };
+        let mut mask = 0;
+        if has_rook(Color::White) {
+            mask |= CASTLE_WK | CASTLE_WQ;
+        }
+        if has_rook(Color::Black) {
+            mask |= CASTLE_BK | CASTLE_BQ;
+        }
+        mask
+    } else {
+        0
+    }
+}
+
+/// Every subset of `available` bits, in ascending order starting with `0` ("no rights").
+fn castling_masks(available: u8) -> Vec<u8> {
+    (0u8..=15).filter(|mask| mask & !available == 0).collect()
+}
+
+/// The files this material key's fixed pawns would allow an en-passant capture on, if it
+/// were `capturer_turn`'s move: a pawn of the other color sits on the double-push landing
+/// rank, with a `capturer_turn`-colored pawn beside it on an adjacent file to capture it.
+///
+/// This only depends on the material key's frozen pawn squares, not on where any other
+/// piece sits, so it's computed once per [`PositionIndexer`].
+fn ep_candidate_files(material_key: &MaterialKey, capturer_turn: Color) -> Vec<u32> {
+    // The rank (0-indexed) a double-pushed pawn lands on, and the side that just moved it.
+    let (mover, rank) = match capturer_turn {
+        Color::Black => (Color::White, 3),
+        Color::White => (Color::Black, 4),
+    };
+
+    let mover_pawns = match mover {
+        Color::White => material_key.pawns.0.white,
+        Color::Black => material_key.pawns.0.black,
+    };
+    let capturer_pawns = match capturer_turn {
+        Color::White => material_key.pawns.0.white,
+        Color::Black => material_key.pawns.0.black,
+    };
+
+    (0..8u32)
+        .filter(|&file| {
+            mover_pawns.contains(Square::new(rank * 8 + file))
+                && [-1i32, 1].into_iter().any(|delta| {
+                    let adjacent = file as i32 + delta;
+                    (0..8).contains(&adjacent)
+                        && capturer_pawns.contains(Square::new(rank * 8 + adjacent as u32))
+                })
+        })
+        .collect()
+}
+
+/// Every `(turn, ep_file, castling_rights)` combination this material key's positions can
+/// take: one "no en passant" state per side to move, plus one extra state per legal
+/// en-passant target file available on that side's turn, each crossed with every castling
+/// subset [`castling_available_mask`] grants this material key (just `{0}` for most keys).
+fn compute_ep_states(material_key: &MaterialKey) -> Vec<EpState> {
+    let castling_masks = castling_masks(castling_available_mask(material_key));
+
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(|turn| {
+            std::iter::once(None)
+                .chain(ep_candidate_files(material_key, turn).into_iter().map(Some))
+                .flat_map(move |ep_file| {
+                    castling_masks
+                        .clone()
+                        .into_iter()
+                        .map(move |castling_rights| EpState {
+                            turn,
+                            ep_file,
+                            castling_rights,
+                        })
+                })
+        })
+        .collect()
+}
+
+/// The rank (0-indexed) of the en-passant target square itself (one square behind the
+/// double-pushed pawn), for the side whose turn it is to capture.
+fn ep_target_rank(capturer_turn: Color) -> u32 {
+    match capturer_turn {
+        Color::Black => 2,
+        Color::White => 5,
+    }
+}
+
+/// Largest group size and domain size the precomputed binomial table supports. No material
+/// key we index comes close to 8 identical pieces, so this is generous headroom rather than
+/// a tight bound.
+const MAX_N: usize = 64;
+const MAX_K: usize = 8;
+
+/// Precomputed binomial coefficients `C(n, k)` for `n <= MAX_N` and `k <= MAX_K`.
+///
+/// Used to encode/decode a group of identical pieces as an unordered k-subset of squares via
+/// the combinatorial number system, which keeps encode/decode to O(n) table lookups instead
+/// of repeatedly recomputing `C(n, k)` from scratch.
+#[derive(Clone, Copy)]
+struct BinomialTable {
+    values: [[u64; MAX_K + 1]; MAX_N + 1],
+}
+
+impl BinomialTable {
+    fn new() -> Self {
+        let mut values = [[0u64; MAX_K + 1]; MAX_N + 1];
+        for row in values.iter_mut() {
+            row[0] = 1;
+        }
+        for n in 1..=MAX_N {
+            for k in 1..=MAX_K.min(n) {
+                values[n][k] = values[n - 1][k - 1] + values[n - 1][k];
+            }
+        }
+        Self { values }
+    }
+
+    /// `C(n, k)`, or 0 if `k > n`.
+    fn get(&self, n: usize, k: usize) -> u64 {
+        if k > n { 0 } else { self.values[n][k] }
+    }
+}
+
+/// Decode a combinadic index into the `count` positions (descending) within a
+/// `C(n, count)`-sized group, where `n` is the number of candidate slots (not necessarily a
+/// full square domain — see [`PieceGroup::domain`]).
+///
+/// For position `i` from `count` down to `1`, greedily picks the largest `c` with
+/// `C(c, i) <= remaining` and subtracts it out. The picked values are guaranteed strictly
+/// decreasing, so the resulting slots are always distinct.
+fn decode_combination(
+    binomials: &BinomialTable,
+    n: usize,
+    count: usize,
+    mut remaining: u64,
+) -> Vec<usize> {
+    let mut positions = Vec::with_capacity(count);
+    let mut upper = n;
+
+    for i in (1..=count).rev() {
+        let mut c = upper - 1;
+        while binomials.get(c, i) > remaining {
+            c -= 1;
+        }
+        remaining -= binomials.get(c, i);
+        positions.push(c);
+        upper = c;
+    }
+
+    positions
+}
+
+/// Encode slots, sorted strictly descending, into their position in the combinatorial
+/// number system. Inverse of [`decode_combination`].
+fn encode_combination(binomials: &BinomialTable, positions_descending: &[usize]) -> u64 {
+    let count = positions_descending.len();
+    positions_descending
+        .iter()
+        .enumerate()
+        .map(|(pos, &c)| binomials.get(c, count - pos))
+        .sum()
+}
+
+/// A run of identical (role, color) pieces, indexed together as an unordered k-subset of
+/// `domain` rather than as `count` independent digits.
+#[derive(Clone)]
+struct PieceGroup {
+    role: HbPieceRole,
+    color: Color,
+    count: usize,
+    /// Ascending list of domain indices this group's pieces may occupy. Every group but the
+    /// first uses the unrestricted `[0, domain_size)` range; the first group (this material
+    /// key's white king) is pinned to one representative square per symmetry orbit, which is
+    /// what actually shrinks `total_positions` — canonicalizing every other piece falls out
+    /// of also minimizing their indices when picking a transform, with no extra domain
+    /// restriction needed on them.
+    domain: Vec<usize>,
+    /// `C(domain.len(), count)`: the number of distinct square assignments for this group,
+    /// and this group's radix in the mixed-radix index.
+    combinations: u64,
+}
+
+/// Group consecutive identical (role, color) pieces from `material_key.pieces()`.
+///
+/// `MaterialKey::pieces()` already yields pieces ordered by color then role, so pieces
+/// belonging to the same group are always adjacent.
+fn piece_groups(material_key: &MaterialKey, binomials: &BinomialTable) -> Vec<PieceGroup> {
+    let mut groups: Vec<PieceGroup> = Vec::new();
+
+    for piece in material_key.pieces() {
+        if let Some(last) = groups.last_mut() {
+            if last.role == piece.role && last.color == piece.color {
+                last.count += 1;
+                last.combinations = binomials.get(last.domain.len(), last.count);
+                continue;
+            }
+        }
+
+        let domain: Vec<usize> = (0..domain_size(piece.role)).collect();
+        groups.push(PieceGroup {
+            role: piece.role,
+            color: piece.color,
+            count: 1,
+            combinations: binomials.get(domain.len(), 1),
+            domain,
+        });
+    }
+
+    groups
+}
+
+/// Restrict `group`'s domain to one representative square per orbit of `transforms`: the
+/// squares whose domain index is already the smallest in their own orbit. Since `transforms`
+/// is closed under composition, every orbit contains exactly one such square, so this is a
+/// bijection from "one canonical position per orbit" to a contiguous `[0, n)` range.
+fn canonical_domain(role: HbPieceRole, transforms: &[Transform]) -> Vec<usize> {
+    (0..domain_size(role))
+        .filter(|&index| {
+            let square = domain_index_to_square(role, index);
+            transforms.iter().all(|&transform| {
+                square_to_domain_index(role, apply_transform(square, transform)) >= index
+            })
+        })
+        .collect()
+}
+
 /// This struct is used to create a Gödel number mapping for all positions of a material key.
+///
+/// Each piece from [`MaterialKey::pieces`] is assigned to a [`PieceGroup`], identical pieces
+/// sharing one group so they're indexed together as an unordered k-subset of their domain via
+/// the combinatorial number system ([`encode_combination`]/[`decode_combination`]) rather than
+/// as independent digits; the leading group (this key's white king) additionally has its domain
+/// restricted to one representative square per symmetry orbit via [`canonical_domain`]. The
+/// index is the mixed-radix composition of the en-passant/castling state digit (see
+/// [`EpState`]) followed by each group's combination number, in the same order as `groups`;
+/// `total_positions` is exactly the product of `ep_states.len()` and every group's
+/// `combinations`, by construction in [`Self::new`].
 #[derive(Clone)]
 pub struct PositionIndexer {
     material_key: MaterialKey,
+    transforms: &'static [Transform],
+    ep_states: Vec<EpState>,
+    groups: Vec<PieceGroup>,
+    binomials: BinomialTable,
     total_positions: usize,
 }
 
 impl PositionIndexer {
     pub fn new(material_key: MaterialKey) -> Self {
-        let mut total_positions = 2;
-        for piece in material_key.pieces() {
-            match piece.role {
-                HbPieceRole::LightBishop | HbPieceRole::DarkBishop => {
-                    total_positions *= 32;
-                }
-                _ => {
-                    total_positions *= 64;
-                }
-            }
+        let binomials = BinomialTable::new();
+        let transforms = allowed_transforms(&material_key);
+        let ep_states = compute_ep_states(&material_key);
+        let mut groups = piece_groups(&material_key, &binomials);
+
+        // Pin the leading piece (this material key's white king) to one representative
+        // square per symmetry orbit, which is what actually shrinks the index space.
+        if let Some(leading) = groups.first_mut() {
+            leading.domain = canonical_domain(leading.role, transforms);
+            leading.combinations = binomials.get(leading.domain.len(), leading.count);
         }
 
+        let total_positions = ep_states.len() * groups
+            .iter()
+            .map(|group| group.combinations as usize)
+            .product::<usize>();
+
         Self {
             material_key,
+            transforms,
+            ep_states,
+            groups,
+            binomials,
             total_positions,
         }
     }
@@ -58,15 +420,129 @@ impl PositionIndexer {
         self.total_positions
     }
 
+    /// Size of the combined (en-passant/castling digit, leading-piece-square) dimension: the
+    /// product of [`Self::index_to_position`]'s two fastest-growing digits, i.e. every index
+    /// less than this varies only the en-passant/castling state and the leading piece (this
+    /// key's white king), holding every other piece fixed.
+    ///
+    /// This is the split [`TableLayout::Transposed`] swaps to the slow-growing side: scanning
+    /// every other piece's arrangement for one fixed leading square currently strides by this
+    /// many elements per probe.
+    pub fn leading_dimension(&self) -> usize {
+        self.ep_states.len() * self.groups.first().map_or(1, |group| group.combinations as usize)
+    }
+
+    /// Reorder a table's positions from `from`'s physical layout to `to`'s.
+    ///
+    /// A no-op clone if the two layouts match; otherwise swaps the leading-piece-square axis
+    /// with the remaining-index axis via [`transpose_blocked`], tiled so the pass stays
+    /// cache-friendly even when one axis dwarfs the other. Works in either direction, since
+    /// transposing a `leading_dim x remaining_dim` matrix back with its dimensions swapped
+    /// recovers the original.
+    pub(crate) fn reorder_for_layout<T: Copy>(
+        &self,
+        positions: &[T],
+        from: TableLayout,
+        to: TableLayout,
+    ) -> Vec<T> {
+        if from == to {
+            return positions.to_vec();
+        }
+
+        let leading_dim = self.leading_dimension();
+        let remaining_dim = self.total_positions / leading_dim;
+        match to {
+            TableLayout::Transposed => transpose_blocked(positions, remaining_dim, leading_dim),
+            TableLayout::RowMajor => transpose_blocked(positions, leading_dim, remaining_dim),
+        }
+    }
+
+    /// Map a logical index (as returned by [`Self::position_to_index`]) to its physical offset
+    /// in a positions array stored in the given `layout`.
+    pub(crate) fn physical_index(&self, logical_index: usize, layout: TableLayout) -> usize {
+        match layout {
+            TableLayout::RowMajor => logical_index,
+            TableLayout::Transposed => {
+                let leading_dim = self.leading_dimension();
+                let remaining_dim = self.total_positions / leading_dim;
+                let leading_digit = logical_index % leading_dim;
+                let remaining_digit = logical_index / leading_dim;
+                leading_digit * remaining_dim + remaining_digit
+            }
+        }
+    }
+
+    /// Find the symmetry that canonicalizes `setup` for this material key: the one whose
+    /// per-group tuple of (descending-sorted) domain indices is lexicographically smallest,
+    /// groups compared in the same order as [`MaterialKey::pieces`] (white king first).
+    /// Ties — e.g. a king already on a symmetry axis — are broken by the next group, and so
+    /// on, so the choice is always well defined.
+    ///
+    /// Returns the winning transform along with `setup` transformed by it. Callers probing a
+    /// real game position can hang onto the transform to map a WDL/DTZ result, or a best
+    /// move, back to the original (non-canonical) position.
+    pub fn canonicalize(&self, setup: &Setup) -> (Transform, Setup) {
+        let (transform, _) = self
+            .transforms
+            .iter()
+            .map(|&transform| {
+                let tuple: Vec<Vec<usize>> = self
+                    .groups
+                    .iter()
+                    .map(|group| {
+                        let mut indices: Vec<usize> =
+                            group_squares(&setup.board, group.role, group.color)
+                                .into_iter()
+                                .map(|square| {
+                                    square_to_domain_index(
+                                        group.role,
+                                        apply_transform(square, transform),
+                                    )
+                                })
+                                .collect();
+                        indices.sort_unstable_by(|a, b| b.cmp(a));
+                        indices
+                    })
+                    .collect();
+                (transform, tuple)
+            })
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+            .expect("allowed_transforms is never empty");
+
+        let mut transformed = Setup::empty();
+        transformed.turn = setup.turn;
+        transformed.ep_square = setup.ep_square.map(|square| apply_transform(square, transform));
+        transformed.castling_rights = setup
+            .castling_rights
+            .into_iter()
+            .fold(Bitboard::EMPTY, |acc, square| {
+                acc | Bitboard::from_square(apply_transform(square, transform))
+            });
+        for square in Square::ALL {
+            if let Some(piece) = setup.board.piece_at(square) {
+                transformed
+                    .board
+                    .set_piece_at(apply_transform(square, transform), piece);
+            }
+        }
+
+        (transform, transformed)
+    }
+
     /// Convert an index into a [`Chess`] position.
     ///
-    /// Every index less than [`self.total_positions()`] corresponds to a unique arrangement
-    /// of the pieces described by the material key.  The mapping is purely
-    /// combinatorial and intentionally ignores the rules of play, so some indices
-    /// yield setups that are unreachable or illegal under normal chess rules — for
-    /// example, kings adjacent to one another or a side to move that can
-    /// immediately capture the opposing king.  When [`shakmaty`] rejects such a
-    /// placement, this function returns [`Err(MaterialError::InvalidPosition)`].
+    /// Every index less than [`self.total_positions()`] decodes to one arrangement of the
+    /// pieces described by the material key: groups of identical pieces are indexed as
+    /// unordered k-subsets of their shared squares, and the leading piece (this key's white
+    /// king) is restricted to one representative square per symmetry orbit. The other pieces
+    /// are not restricted, so when the leading piece sits on a symmetry axis two indices can
+    /// decode to symmetric copies of one position, of which [`Self::position_to_index`] returns
+    /// only the canonical one. The mapping
+    /// is otherwise purely combinatorial and intentionally ignores the rules of play, so some
+    /// indices yield setups that are unreachable or illegal under normal chess rules — for
+    /// example, kings adjacent to one another or a side to move that can immediately capture
+    /// the opposing king. When [`shakmaty`] rejects such a placement, this function returns
+    /// [`Err(PositionMappingError::InvalidPosition)`].
     ///
     /// Indices greater than or equal to `total_positions()` return
     /// [`Err(MaterialError::IndexOutOfBounds)`].
@@ -75,37 +551,40 @@ impl PositionIndexer {
             return Err(PositionMappingError::IndexOutOfBounds);
         }
 
-        let turn = match index % 2 {
-            0 => Color::White,
-            1 => Color::Black,
-            _ => unreachable!(),
-        };
-        let mut remaining = index / 2;
+        let ep_digit = index % self.ep_states.len();
+        let mut remaining = (index / self.ep_states.len()) as u64;
+        let ep_state = self.ep_states[ep_digit];
 
         let mut setup = Setup::empty();
-        setup.turn = turn;
+        setup.turn = ep_state.turn;
         setup.board = self.material_key.pawns.to_board();
+        if let Some(file) = ep_state.ep_file {
+            setup.ep_square = Some(Square::new(ep_target_rank(ep_state.turn) * 8 + file));
+        }
+        setup.castling_rights = ALL_CASTLING_BITS
+            .into_iter()
+            .filter(|&bit| ep_state.castling_rights & bit != 0)
+            .fold(Bitboard::EMPTY, |acc, bit| {
+                acc | Bitboard::from_square(castling_home_square(bit))
+            });
 
-        for piece in self.material_key.pieces() {
-            let radix = match piece.role {
-                HbPieceRole::LightBishop | HbPieceRole::DarkBishop => 32,
-                _ => 64,
-            };
-
-            let position = remaining % radix;
-            remaining /= radix;
+        for group in &self.groups {
+            let group_index = remaining % group.combinations;
+            remaining /= group.combinations;
 
-            let square = match piece.role {
-                HbPieceRole::LightBishop => nth_light_square(position as u32),
-                HbPieceRole::DarkBishop => nth_dark_square(position as u32),
-                _ => Square::new(position as u32),
+            let positions = decode_combination(&self.binomials, group.domain.len(), group.count, group_index);
+            let piece = HbPiece {
+                role: group.role,
+                color: group.color,
             };
 
-            if setup.board.piece_at(square).is_some() {
-                return Err(PositionMappingError::TwoPiecesOnSameSquare);
+            for position in positions {
+                let square = domain_index_to_square(group.role, group.domain[position]);
+                if setup.board.piece_at(square).is_some() {
+                    return Err(PositionMappingError::TwoPiecesOnSameSquare);
+                }
+                setup.board.set_piece_at(square, piece.into());
             }
-
-            setup.board.set_piece_at(square, piece.into());
         }
 
         debug_assert!(remaining == 0);
@@ -115,58 +594,96 @@ impl PositionIndexer {
     }
 
     pub fn position_to_index(&self, position: &Chess) -> Result<usize, PositionMappingError> {
-        let board = position.board();
-        let white_pawns = board.pawns() & board.white();
-        let black_pawns = board.pawns() & board.black();
+        let setup = position.clone().into_setup(EnPassantMode::Legal);
+
+        let white_pawns = setup.board.pawns() & setup.board.white();
+        let black_pawns = setup.board.pawns() & setup.board.black();
         if white_pawns != self.material_key.pawns.0.white
             || black_pawns != self.material_key.pawns.0.black
         {
             return Err(PositionMappingError::MismatchedMaterial);
         }
 
-        let mut index = 0;
-        let mut multiplier = 1;
+        let (_, canonical_setup) = self.canonicalize(&setup);
 
-        let turn_index = match position.turn() {
-            Color::White => 0,
-            Color::Black => 1,
+        #[cfg(debug_assertions)]
+        let canonical_position_for_check =
+            Chess::from_setup(canonical_setup.clone(), CastlingMode::Standard).ok();
+
+        let mut index: u64 = 0;
+        let mut multiplier: u64 = 1;
+
+        let castling_rights = ALL_CASTLING_BITS.into_iter().fold(0u8, |mask, bit| {
+            if canonical_setup
+                .castling_rights
+                .contains(castling_home_square(bit))
+            {
+                mask | bit
+            } else {
+                mask
+            }
+        });
+        let ep_state = EpState {
+            turn: canonical_setup.turn,
+            ep_file: canonical_setup.ep_square.map(|square| square.file() as u32),
+            castling_rights,
         };
-        index += multiplier * turn_index;
-        multiplier *= 2;
+        let ep_digit = self
+            .ep_states
+            .iter()
+            .position(|&state| state == ep_state)
+            .ok_or(PositionMappingError::MismatchedMaterial)?;
+        index += multiplier * ep_digit as u64;
+        multiplier *= self.ep_states.len() as u64;
 
-        let mut board = position.board().clone();
+        let mut board = canonical_setup.board;
 
-        for piece in self.material_key.pieces() {
-            let radix = match piece.role {
-                HbPieceRole::LightBishop | HbPieceRole::DarkBishop => 32,
-                _ => 64,
-            };
+        for group in &self.groups {
+            let squares = group_squares(&board, group.role, group.color);
+            if squares.len() != group.count {
+                return Err(PositionMappingError::MismatchedMaterial);
+            }
 
-            let mask = match piece.role {
-                HbPieceRole::LightBishop => Bitboard::LIGHT_SQUARES,
-                HbPieceRole::DarkBishop => Bitboard::DARK_SQUARES,
-                _ => Bitboard::FULL,
-            };
-            let bitboard = mask & board.by_piece(piece.into());
-            let square = bitboard
-                .first()
-                .ok_or(PositionMappingError::MismatchedMaterial)?;
-            board.discard_piece_at(square);
-            let square_index = square.to_usize();
-
-            let position = match piece.role {
-                HbPieceRole::LightBishop | HbPieceRole::DarkBishop => square_index / 2,
-                _ => square_index,
-            };
+            let mut positions: Vec<usize> = squares
+                .iter()
+                .map(|&square| {
+                    let domain_index = square_to_domain_index(group.role, square);
+                    group
+                        .domain
+                        .binary_search(&domain_index)
+                        .expect("canonicalize() must place this group within its own domain")
+                })
+                .collect();
+            positions.sort_unstable_by(|a, b| b.cmp(a));
 
-            index += multiplier * position;
-            multiplier *= radix;
+            for &square in &squares {
+                board.discard_piece_at(square);
+            }
+
+            let group_index = encode_combination(&self.binomials, &positions);
+            index += multiplier * group_index;
+            multiplier *= group.combinations;
         }
 
-        debug_assert!(index < self.total_positions);
-        debug_assert!(multiplier == self.total_positions);
+        debug_assert!(index < self.total_positions as u64);
+        debug_assert!(multiplier == self.total_positions as u64);
 
-        Ok(index)
+        // Zobrist-hash the position this index decodes back to and compare it against the
+        // canonicalized input: if they ever disagree, two distinct positions silently
+        // collided onto the same index.
+        #[cfg(debug_assertions)]
+        if let Some(canonical_position) = canonical_position_for_check {
+            let roundtripped = self
+                .index_to_position(index as usize)
+                .expect("index was just computed from a valid position, so it must decode");
+            debug_assert_eq!(
+                ZobristHasher::hash(&canonical_position),
+                ZobristHasher::hash(&roundtripped),
+                "position_to_index produced an index whose decoded position doesn't match the original"
+            );
+        }
+
+        Ok(index as usize)
     }
 }
 
@@ -182,6 +699,488 @@ pub enum PositionMappingError {
     InvalidPosition(PositionErrorKinds),
 }
 
+/// A run of identical (role, color) pieces for [`DensePositionIndexer`], indexed as an
+/// unordered k-subset of the squares still free once every earlier group has claimed its own,
+/// rather than of the full static domain [`PieceGroup`] uses.
+///
+/// `leading_domain` plays the same role it does for `PieceGroup`: only the first group (this
+/// material key's white king) gets one, restricting it to one representative square per
+/// symmetry orbit. Every other group's domain can't be precomputed, since it depends on which
+/// specific squares earlier groups ended up with, so it's rebuilt on demand by
+/// [`dense_group_domain`] from the running `occupied_*` trackers instead of being stored here.
+#[derive(Clone)]
+struct DenseGroup {
+    role: HbPieceRole,
+    color: Color,
+    count: usize,
+    leading_domain: Option<Vec<usize>>,
+    /// This group's radix in the mixed-radix index. Always `C(available, count)`, where
+    /// `available` is `leading_domain`'s size for the first group, and otherwise the number of
+    /// this role's domain squares not already claimed by earlier groups (see
+    /// [`dense_group_available`] for exactly which earlier groups count against it).
+    combinations: usize,
+}
+
+/// The number of `role`'s domain squares still free after the groups already placed, for a
+/// group that isn't the leading (symmetry-restricted) one.
+///
+/// A full-domain role (every role but a same-colored bishop) is compacted against *every*
+/// earlier group: full domain overlaps both square colors, so no matter which squares those
+/// groups actually took, exactly `full_used` of them came out of this role's domain. A
+/// same-colored bishop role is only compacted against earlier groups of that *same* bishop
+/// role, for the same reason one square color's 32-square domain is itself homogeneous under
+/// reordering. Mixing the two — compacting a bishop's domain against an earlier full-domain
+/// group's *specific* square — would make its size depend on that square's color, which varies
+/// by index and breaks the fixed-radix mixed number this index relies on; that one case is left
+/// for [`DensePositionIndexer::index_to_position`] to catch reactively, same as
+/// [`PositionIndexer`] does for every collision today.
+fn dense_group_available(
+    role: HbPieceRole,
+    full_used: usize,
+    light_used: usize,
+    dark_used: usize,
+) -> usize {
+    match role {
+        HbPieceRole::LightBishop => domain_size(role) - light_used,
+        HbPieceRole::DarkBishop => domain_size(role) - dark_used,
+        _ => domain_size(role) - full_used,
+    }
+}
+
+/// Group consecutive identical (role, color) pieces into [`DenseGroup`]s and compute each
+/// one's `combinations`, in the same order [`piece_groups`] does for [`PieceGroup`].
+fn dense_piece_groups(material_key: &MaterialKey, transforms: &[Transform]) -> Vec<DenseGroup> {
+    let mut groups: Vec<DenseGroup> = Vec::new();
+
+    for piece in material_key.pieces() {
+        if let Some(last) = groups.last_mut() {
+            if last.role == piece.role && last.color == piece.color {
+                last.count += 1;
+                continue;
+            }
+        }
+        groups.push(DenseGroup {
+            role: piece.role,
+            color: piece.color,
+            count: 1,
+            leading_domain: None,
+            combinations: 0,
+        });
+    }
+
+    if let Some(leading) = groups.first_mut() {
+        leading.leading_domain = Some(canonical_domain(leading.role, transforms));
+    }
+
+    let mut full_used = 0;
+    let mut light_used = 0;
+    let mut dark_used = 0;
+    for group in &mut groups {
+        let available = match &group.leading_domain {
+            Some(domain) => domain.len(),
+            None => dense_group_available(group.role, full_used, light_used, dark_used),
+        };
+        group.combinations = n_choose_k(available, group.count);
+
+        full_used += group.count;
+        match group.role {
+            HbPieceRole::LightBishop => light_used += group.count,
+            HbPieceRole::DarkBishop => dark_used += group.count,
+            _ => {}
+        }
+    }
+
+    groups
+}
+
+/// Rebuild `group`'s actual domain (an ascending list of domain indices) from the squares
+/// claimed by every group processed before it, recorded in `occupied_*`. See
+/// [`dense_group_available`] for which earlier groups count against which roles; this must
+/// filter the exact same way that function counted, or the resulting list's length would
+/// disagree with `group.combinations`.
+fn dense_group_domain(
+    group: &DenseGroup,
+    occupied_full: &[bool; 64],
+    occupied_light: &[bool; 32],
+    occupied_dark: &[bool; 32],
+) -> Vec<usize> {
+    if let Some(domain) = &group.leading_domain {
+        return domain.clone();
+    }
+
+    (0..domain_size(group.role))
+        .filter(|&index| match group.role {
+            HbPieceRole::LightBishop => !occupied_light[index],
+            HbPieceRole::DarkBishop => !occupied_dark[index],
+            _ => !occupied_full[index],
+        })
+        .collect()
+}
+
+/// A dense, bijective index for a material key: unlike [`PositionIndexer`], which gives every
+/// group a fixed radix of its full domain, this compacts each group's domain down to the
+/// squares still free once earlier groups (in the same order [`MaterialKey::pieces`] yields
+/// them) have claimed theirs, using [`rank_combination`]/[`unrank_combination`] against that
+/// compacted domain instead of [`encode_combination`]/[`decode_combination`] against a static
+/// one. `total_positions` shrinks accordingly, since almost every remaining index now
+/// corresponds to a reachable arrangement instead of wasting radix on squares that were always
+/// going to collide with an earlier piece. See [`dense_group_available`] for the one residual
+/// collision [`index_to_position`](Self::index_to_position) still has to catch reactively.
+#[derive(Clone)]
+pub struct DensePositionIndexer {
+    material_key: MaterialKey,
+    transforms: &'static [Transform],
+    ep_states: Vec<EpState>,
+    groups: Vec<DenseGroup>,
+    total_positions: usize,
+}
+
+impl DensePositionIndexer {
+    pub fn new(material_key: MaterialKey) -> Self {
+        let transforms = allowed_transforms(&material_key);
+        let ep_states = compute_ep_states(&material_key);
+        let groups = dense_piece_groups(&material_key, transforms);
+
+        let total_positions =
+            ep_states.len() * groups.iter().map(|group| group.combinations).product::<usize>();
+
+        Self {
+            material_key,
+            transforms,
+            ep_states,
+            groups,
+            total_positions,
+        }
+    }
+
+    pub fn total_positions(&self) -> usize {
+        self.total_positions
+    }
+
+    /// Same symmetry-minimizing search as [`PositionIndexer::canonicalize`], just driven by
+    /// this indexer's own group order.
+    pub fn canonicalize(&self, setup: &Setup) -> (Transform, Setup) {
+        let (transform, _) = self
+            .transforms
+            .iter()
+            .map(|&transform| {
+                let tuple: Vec<Vec<usize>> = self
+                    .groups
+                    .iter()
+                    .map(|group| {
+                        let mut indices: Vec<usize> =
+                            group_squares(&setup.board, group.role, group.color)
+                                .into_iter()
+                                .map(|square| {
+                                    square_to_domain_index(
+                                        group.role,
+                                        apply_transform(square, transform),
+                                    )
+                                })
+                                .collect();
+                        indices.sort_unstable_by(|a, b| b.cmp(a));
+                        indices
+                    })
+                    .collect();
+                (transform, tuple)
+            })
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+            .expect("allowed_transforms is never empty");
+
+        let mut transformed = Setup::empty();
+        transformed.turn = setup.turn;
+        transformed.ep_square = setup.ep_square.map(|square| apply_transform(square, transform));
+        transformed.castling_rights = setup
+            .castling_rights
+            .into_iter()
+            .fold(Bitboard::EMPTY, |acc, square| {
+                acc | Bitboard::from_square(apply_transform(square, transform))
+            });
+        for square in Square::ALL {
+            if let Some(piece) = setup.board.piece_at(square) {
+                transformed
+                    .board
+                    .set_piece_at(apply_transform(square, transform), piece);
+            }
+        }
+
+        (transform, transformed)
+    }
+
+    pub fn index_to_position(&self, index: usize) -> Result<Chess, PositionMappingError> {
+        if index >= self.total_positions {
+            return Err(PositionMappingError::IndexOutOfBounds);
+        }
+
+        let ep_digit = index % self.ep_states.len();
+        let mut remaining = index / self.ep_states.len();
+        let ep_state = self.ep_states[ep_digit];
+
+        let mut setup = Setup::empty();
+        setup.turn = ep_state.turn;
+        setup.board = self.material_key.pawns.to_board();
+        if let Some(file) = ep_state.ep_file {
+            setup.ep_square = Some(Square::new(ep_target_rank(ep_state.turn) * 8 + file));
+        }
+        setup.castling_rights = ALL_CASTLING_BITS
+            .into_iter()
+            .filter(|&bit| ep_state.castling_rights & bit != 0)
+            .fold(Bitboard::EMPTY, |acc, bit| {
+                acc | Bitboard::from_square(castling_home_square(bit))
+            });
+
+        let mut occupied_full = [false; 64];
+        let mut occupied_light = [false; 32];
+        let mut occupied_dark = [false; 32];
+
+        for group in &self.groups {
+            let group_index = remaining % group.combinations;
+            remaining /= group.combinations;
+
+            let domain = dense_group_domain(group, &occupied_full, &occupied_light, &occupied_dark);
+            let positions = unrank_combination(domain.len(), group.count, group_index);
+            let piece = HbPiece {
+                role: group.role,
+                color: group.color,
+            };
+
+            for position in positions {
+                let domain_index = domain[position];
+                let square = domain_index_to_square(group.role, domain_index);
+                if setup.board.piece_at(square).is_some() {
+                    return Err(PositionMappingError::TwoPiecesOnSameSquare);
+                }
+                setup.board.set_piece_at(square, piece.into());
+
+                occupied_full[square.to_usize()] = true;
+                match group.role {
+                    HbPieceRole::LightBishop => occupied_light[domain_index] = true,
+                    HbPieceRole::DarkBishop => occupied_dark[domain_index] = true,
+                    _ => {}
+                }
+            }
+        }
+
+        debug_assert!(remaining == 0);
+
+        Chess::from_setup(setup, CastlingMode::Standard)
+            .map_err(|e| PositionMappingError::InvalidPosition(e.kinds()))
+    }
+
+    pub fn position_to_index(&self, position: &Chess) -> Result<usize, PositionMappingError> {
+        let setup = position.clone().into_setup(EnPassantMode::Legal);
+
+        let white_pawns = setup.board.pawns() & setup.board.white();
+        let black_pawns = setup.board.pawns() & setup.board.black();
+        if white_pawns != self.material_key.pawns.0.white
+            || black_pawns != self.material_key.pawns.0.black
+        {
+            return Err(PositionMappingError::MismatchedMaterial);
+        }
+
+        let (_, canonical_setup) = self.canonicalize(&setup);
+
+        let mut index = 0;
+        let mut multiplier = 1;
+
+        let castling_rights = ALL_CASTLING_BITS.into_iter().fold(0u8, |mask, bit| {
+            if canonical_setup
+                .castling_rights
+                .contains(castling_home_square(bit))
+            {
+                mask | bit
+            } else {
+                mask
+            }
+        });
+        let ep_state = EpState {
+            turn: canonical_setup.turn,
+            ep_file: canonical_setup.ep_square.map(|square| square.file() as u32),
+            castling_rights,
+        };
+        let ep_digit = self
+            .ep_states
+            .iter()
+            .position(|&state| state == ep_state)
+            .ok_or(PositionMappingError::MismatchedMaterial)?;
+        index += multiplier * ep_digit;
+        multiplier *= self.ep_states.len();
+
+        let mut board = canonical_setup.board;
+        let mut occupied_full = [false; 64];
+        let mut occupied_light = [false; 32];
+        let mut occupied_dark = [false; 32];
+
+        for group in &self.groups {
+            let squares = group_squares(&board, group.role, group.color);
+            if squares.len() != group.count {
+                return Err(PositionMappingError::MismatchedMaterial);
+            }
+
+            let domain = dense_group_domain(group, &occupied_full, &occupied_light, &occupied_dark);
+            let mut positions: Vec<usize> = squares
+                .iter()
+                .map(|&square| {
+                    let domain_index = square_to_domain_index(group.role, square);
+                    domain
+                        .binary_search(&domain_index)
+                        .expect("canonicalize() must place this group within its own domain")
+                })
+                .collect();
+            positions.sort_unstable();
+
+            for &square in &squares {
+                board.discard_piece_at(square);
+                occupied_full[square.to_usize()] = true;
+            }
+            match group.role {
+                HbPieceRole::LightBishop => {
+                    for &square in &squares {
+                        occupied_light[square_to_domain_index(group.role, square)] = true;
+                    }
+                }
+                HbPieceRole::DarkBishop => {
+                    for &square in &squares {
+                        occupied_dark[square_to_domain_index(group.role, square)] = true;
+                    }
+                }
+                _ => {}
+            }
+
+            let group_index = rank_combination(domain.len(), &positions);
+            index += multiplier * group_index;
+            multiplier *= group.combinations;
+        }
+
+        debug_assert!(index < self.total_positions);
+        debug_assert!(multiplier == self.total_positions);
+
+        Ok(index)
+    }
+}
+
+/// The squares a piece of `role` could have slid or stepped in from to reach `square`, given
+/// `occupied`. Sliding attack tables are symmetric along their ray, so the squares a piece on
+/// `square` attacks are exactly the squares a piece on `square` could have arrived from.
+fn reverse_attacks(role: HbPieceRole, square: Square, occupied: Bitboard) -> Bitboard {
+    match role {
+        HbPieceRole::King => attacks::king_attacks(square),
+        HbPieceRole::Queen => attacks::queen_attacks(square, occupied),
+        HbPieceRole::Rook => attacks::rook_attacks(square, occupied),
+        HbPieceRole::LightBishop | HbPieceRole::DarkBishop => {
+            attacks::bishop_attacks(square, occupied)
+        }
+        HbPieceRole::Knight => attacks::knight_attacks(square),
+    }
+}
+
+/// Build `board` into a position with `turn` to move and index it against `indexer`, pushing
+/// `(material, index)` on success. Boards [`shakmaty`] rejects (most commonly: the side that
+/// just moved would be left in check) are silently dropped, same as every other caller of
+/// [`PositionIndexer::index_to_position`]/[`PositionIndexer::position_to_index`] already treats
+/// an `Err` as "not a reachable position" rather than a hard failure.
+fn push_unmove(
+    results: &mut Vec<(MaterialKey, usize)>,
+    material: &MaterialKey,
+    indexer: &PositionIndexer,
+    board: &Board,
+    turn: Color,
+) {
+    let mut setup = Setup::empty();
+    setup.turn = turn;
+    setup.board = board.clone();
+
+    let Ok(position) = Chess::from_setup(setup, CastlingMode::Standard) else {
+        return;
+    };
+    let Ok(index) = indexer.position_to_index(&position) else {
+        return;
+    };
+    results.push((material.clone(), index));
+}
+
+/// Every position that could reach `(material, index)` in one ply: decode the index, then for
+/// each piece belonging to the side that just moved (`position.turn().other()`), walk every
+/// reverse king/queen/rook/bishop/knight slide or step back to a currently-empty origin square.
+/// Pawn moves and en passant are not unwound, matching [`HbPieceRole`]'s own omission of pawns
+/// from the pieces a position can un-capture.
+///
+/// Each unmove is also tried with an un-capture: materializing one of
+/// [`HbPieceRole::CAPTURABLE`], owned by the side to move, on the square the mover just
+/// vacated. An un-capture adds a piece back to the board, so its index is looked up against
+/// that capturable piece's own parent [`MaterialKey`] rather than `material`.
+pub fn unmoves(material: &MaterialKey, index: usize) -> Vec<(MaterialKey, usize)> {
+    let indexer = PositionIndexer::new(material.clone());
+    let Ok(position) = indexer.index_to_position(index) else {
+        return Vec::new();
+    };
+
+    let mover = position.turn().other();
+    let captured_color = position.turn();
+    let board = position.board();
+    let occupied = board.occupied();
+
+    let mut results = Vec::new();
+    let mut capture_indexers: HashMap<HbPieceRole, (MaterialKey, PositionIndexer)> =
+        HashMap::new();
+
+    for square in Square::ALL {
+        let Some(piece) = board.piece_at(square) else {
+            continue;
+        };
+        if piece.color != mover {
+            continue;
+        }
+        let role = match piece.role {
+            Role::King => HbPieceRole::King,
+            Role::Queen => HbPieceRole::Queen,
+            Role::Rook => HbPieceRole::Rook,
+            Role::Bishop if square.is_light() => HbPieceRole::LightBishop,
+            Role::Bishop => HbPieceRole::DarkBishop,
+            Role::Knight => HbPieceRole::Knight,
+            Role::Pawn => continue,
+        };
+
+        for origin in reverse_attacks(role, square, occupied) & !occupied {
+            let mut quiet_board = board.clone();
+            quiet_board.discard_piece_at(square);
+            quiet_board.set_piece_at(origin, piece);
+            push_unmove(&mut results, material, &indexer, &quiet_board, mover);
+
+            for captured_role in HbPieceRole::CAPTURABLE {
+                let mut captured_board = quiet_board.clone();
+                captured_board.set_piece_at(
+                    square,
+                    HbPiece {
+                        role: captured_role,
+                        color: captured_color,
+                    }
+                    .into(),
+                );
+
+                let (parent_material, parent_indexer) =
+                    capture_indexers.entry(captured_role).or_insert_with(|| {
+                        let mut counts = material.counts.clone();
+                        counts[captured_color][captured_role] += 1;
+                        let parent_material = MaterialKey::new(counts, material.pawns.clone());
+                        let parent_indexer = PositionIndexer::new(parent_material.clone());
+                        (parent_material, parent_indexer)
+                    });
+
+                push_unmove(
+                    &mut results,
+                    parent_material,
+                    parent_indexer,
+                    &captured_board,
+                    mover,
+                );
+            }
+        }
+    }
+
+    results
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +1202,9 @@ mod tests {
     }
 
     proptest! {
+        // `material_key_strategy` samples pawn squares freely on both sides, so this already
+        // exercises en-passant-eligible material keys (e.g. a white pawn on e4 and a black
+        // pawn on d4) alongside everything else, without needing a dedicated ep strategy.
         #[test]
         fn roundtrip_indices((mk, index) in indexed_material_strategy()) {
             let indexer = PositionIndexer::new(mk);
@@ -313,4 +1315,322 @@ mod tests {
             Err(PositionMappingError::MismatchedMaterial)
         ));
     }
+
+    #[test]
+    fn identical_pieces_shrink_total_positions() {
+        // KNNvK has two identical white knights: the combinadic grouping should index them
+        // as an unordered pair from a shared domain of 64 squares (C(64, 2) = 2016), rather
+        // than as two independent radix-64 digits (64 * 64 = 4096). It's also pawnless and
+        // bishopless, so the white king (the leading group) is further pinned to the 10
+        // squares of the a1-d1-d4 triangle instead of the full board.
+        let mk = MaterialKey::from_string("KNNvK").unwrap();
+        let indexer = PositionIndexer::new(mk);
+        let white_king_triangle = 10;
+        let black_king = 64;
+        assert_eq!(
+            indexer.total_positions(),
+            2 * white_king_triangle * 2016 * black_king
+        );
+    }
+
+    #[test]
+    fn identical_pieces_roundtrip() {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let mk = MaterialKey::from_string("KNNvK").unwrap();
+        let indexer = PositionIndexer::new(mk);
+        let position = "k7/8/8/8/3N4/1N6/8/K7 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+
+        let index = indexer.position_to_index(&position).unwrap();
+        let reconstructed = indexer.index_to_position(index).unwrap();
+        assert_eq!(reconstructed.board().clone(), position.board().clone());
+    }
+
+    #[test]
+    fn mirrored_positions_share_an_index() {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let mk = MaterialKey::from_string("KQvK").unwrap();
+        let indexer = PositionIndexer::new(mk);
+
+        let position = "4k3/8/8/8/3Q4/8/8/4K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        // The file-mirror image of `position`: e <-> d.
+        let mirrored = "3k4/8/8/8/4Q3/8/8/3K4 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+
+        assert_eq!(
+            indexer.position_to_index(&position).unwrap(),
+            indexer.position_to_index(&mirrored).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_king_domain_is_the_a1_d1_d4_triangle() {
+        let mk = MaterialKey::from_string("KQvK").unwrap();
+        let indexer = PositionIndexer::new(mk);
+        assert_eq!(indexer.groups[0].domain.len(), 10);
+    }
+
+    #[test]
+    fn en_passant_position_gets_a_distinct_index_from_the_non_ep_position() {
+        use shakmaty::{CastlingMode, EnPassantMode, fen::Fen};
+
+        // White pawn e4 and black pawn d4 sit adjacent on the same rank, so black to move can
+        // (but doesn't have to) have an en-passant capture available on the e-file.
+        let mk = MaterialKey::from_string("Ke4vKd4").unwrap();
+        let indexer = PositionIndexer::new(mk);
+        let board = "4k3/8/8/8/3pP3/8/8/4K3";
+
+        let without_ep = format!("{board} b - - 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let with_ep = format!("{board} b - e3 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+
+        let index_without_ep = indexer.position_to_index(&without_ep).unwrap();
+        let index_with_ep = indexer.position_to_index(&with_ep).unwrap();
+        assert_ne!(index_without_ep, index_with_ep);
+
+        let reconstructed_without_ep = indexer.index_to_position(index_without_ep).unwrap();
+        assert_eq!(
+            reconstructed_without_ep.ep_square(EnPassantMode::Legal),
+            None
+        );
+
+        let reconstructed_with_ep = indexer.index_to_position(index_with_ep).unwrap();
+        assert_eq!(
+            reconstructed_with_ep.ep_square(EnPassantMode::Legal),
+            Some(Square::E3)
+        );
+    }
+
+    #[test]
+    fn total_positions_grows_only_when_en_passant_is_geometrically_possible() {
+        // Ka2vK has a lone white pawn that can never be the target of an en-passant capture
+        // (there's no opposing pawn to capture it with), so its index space shouldn't reserve
+        // any extra en-passant slots beyond the plain turn bit.
+        let no_ep = MaterialKey::from_string("Ka2vK").unwrap();
+        let with_ep = MaterialKey::from_string("Ke4vKd4").unwrap();
+        assert_eq!(PositionIndexer::new(no_ep).ep_states.len(), 2);
+        // One "no en passant" state per side, plus one extra slot for the single en-passant
+        // target file the Ke4vKd4 pawn structure can produce.
+        assert_eq!(PositionIndexer::new(with_ep).ep_states.len(), 3);
+    }
+
+    #[test]
+    fn castling_rights_round_trip_for_pawnful_rook_endgame() {
+        use shakmaty::{CastlingMode, EnPassantMode, fen::Fen};
+
+        // KRa2vK has a pawn, which already forces the identity transform, so
+        // `castling_available_mask` grants white's two castling-right bits (it has a rook) but
+        // none of black's (no black rook).
+        let mk = MaterialKey::from_string("KRa2vK").unwrap();
+        let indexer = PositionIndexer::new(mk);
+        let board = "4k3/8/8/8/8/8/P7/4K2R";
+
+        let with_right = format!("{board} w K - 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let without_right = format!("{board} w - - 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+
+        let index_with_right = indexer.position_to_index(&with_right).unwrap();
+        let index_without_right = indexer.position_to_index(&without_right).unwrap();
+        assert_ne!(index_with_right, index_without_right);
+
+        let reconstructed_with_right = indexer.index_to_position(index_with_right).unwrap();
+        assert_eq!(
+            reconstructed_with_right
+                .into_setup(EnPassantMode::Legal)
+                .castling_rights,
+            Bitboard::from_square(Square::H1)
+        );
+
+        let reconstructed_without_right = indexer.index_to_position(index_without_right).unwrap();
+        assert!(
+            reconstructed_without_right
+                .into_setup(EnPassantMode::Legal)
+                .castling_rights
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn castling_is_not_modeled_for_pawnless_rook_endgames() {
+        // KRvKR is pawnless, so it keeps the naive indexer's full dihedral compaction rather
+        // than forfeiting it for a castling right that, this deep into an endgame, is already
+        // gone from every practical game.
+        let mk = MaterialKey::from_string("KRvKR").unwrap();
+        assert_eq!(castling_available_mask(&mk), 0);
+    }
+
+    /// Every in-bounds index round-trips to itself with no collisions, checked exhaustively
+    /// rather than sampled: `PositionIndexer`'s own roundtrip test above notes that its naive
+    /// index can map two indices to the same position, which `DensePositionIndexer` is meant
+    /// to fix, so a handful of random samples passing wouldn't actually demonstrate that.
+    fn assert_dense_index_is_collision_free(material_key: &str) {
+        let mk = MaterialKey::from_string(material_key).unwrap();
+        let indexer = DensePositionIndexer::new(mk);
+        for index in 0..indexer.total_positions() {
+            let Ok(pos) = indexer.index_to_position(index) else {
+                continue;
+            };
+            let reencoded = indexer
+                .position_to_index(&pos)
+                .expect("a position just decoded from an in-bounds index must re-encode");
+            assert_eq!(reencoded, index, "index {index} didn't round-trip to itself");
+        }
+    }
+
+    #[test]
+    fn dense_index_is_collision_free_kvk() {
+        assert_dense_index_is_collision_free("KvK");
+    }
+
+    #[test]
+    fn dense_index_is_collision_free_knnvk() {
+        assert_dense_index_is_collision_free("KNNvK");
+    }
+
+    #[test]
+    fn dense_index_is_collision_free_krvkr() {
+        assert_dense_index_is_collision_free("KRvKR");
+    }
+
+    #[test]
+    fn dense_index_is_collision_free_with_same_colored_bishops() {
+        assert_dense_index_is_collision_free("KBlBlvKBl");
+    }
+
+    #[test]
+    fn dense_index_shrinks_total_positions_vs_the_naive_indexer() {
+        let mk = MaterialKey::from_string("KNNvK").unwrap();
+        let naive = PositionIndexer::new(mk.clone()).total_positions();
+        let dense = DensePositionIndexer::new(mk).total_positions();
+        assert!(
+            dense < naive,
+            "dense index ({dense}) should be smaller than the naive one ({naive})"
+        );
+    }
+
+    fn dense_indexed_material_strategy() -> impl Strategy<Value = (MaterialKey, usize)> {
+        material_key_strategy().prop_flat_map(|mk| {
+            let total = DensePositionIndexer::new(mk.clone()).total_positions();
+            (Just(mk), 0..total)
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn dense_roundtrip_indices((mk, index) in dense_indexed_material_strategy()) {
+            let indexer = DensePositionIndexer::new(mk);
+            let Ok(pos) = indexer.index_to_position(index) else {
+                return Ok(());
+            };
+
+            let index = indexer
+                .position_to_index(&pos)
+                .expect("This position came from a valid index, so it should never fail");
+            let pos2 = indexer
+                .index_to_position(index)
+                .expect("This index came from a valid index, so it should never fail");
+            prop_assert_eq!(pos, pos2);
+        }
+    }
+
+    /// `allowed_transforms` only grants bishop-containing material keys 4-fold symmetry, not
+    /// the full 8-fold a bishopless key of the same shape gets (see its doc comment for why).
+    /// This still has to roundtrip correctly under the color-preserving transforms it does
+    /// apply, for both the single-bishop (`KRvKBd`) and mixed dark-bishop-plus-knight
+    /// (`KBdNvKQ`) shapes.
+    fn assert_roundtrips_under_reduced_bishop_symmetry(material_key: &str, fen: &str) {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let mk = MaterialKey::from_string(material_key).unwrap();
+        let position: Chess = fen
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+
+        let naive = PositionIndexer::new(mk.clone());
+        let index = naive.position_to_index(&position).unwrap();
+        let reconstructed = naive.index_to_position(index).unwrap();
+        assert_eq!(naive.position_to_index(&reconstructed).unwrap(), index);
+
+        let dense = DensePositionIndexer::new(mk);
+        let index = dense.position_to_index(&position).unwrap();
+        let reconstructed = dense.index_to_position(index).unwrap();
+        assert_eq!(dense.position_to_index(&reconstructed).unwrap(), index);
+    }
+
+    #[test]
+    fn krvkbd_roundtrips_under_reduced_bishop_symmetry() {
+        assert_roundtrips_under_reduced_bishop_symmetry(
+            "KRvKBd",
+            "4k3/8/8/8/8/8/3b4/R3K3 w - - 0 1",
+        );
+    }
+
+    #[test]
+    fn kbdnvkq_roundtrips_under_reduced_bishop_symmetry() {
+        assert_roundtrips_under_reduced_bishop_symmetry(
+            "KBdNvKQ",
+            "3qk3/8/8/8/8/8/3B4/4K1N1 b - - 0 1",
+        );
+    }
+
+    #[test]
+    fn unmoves_from_krvk_finds_quiet_retreats_and_uncaptures() {
+        use shakmaty::{CastlingMode, fen::Fen};
+
+        let mk = MaterialKey::from_string("KRvK").unwrap();
+        let indexer = PositionIndexer::new(mk.clone());
+        let position: Chess = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        let index = indexer.position_to_index(&position).unwrap();
+
+        let results = unmoves(&mk, index);
+        assert!(!results.is_empty());
+        // The black king on e8 had several quiet retreats, all still "KRvK".
+        assert!(results.iter().any(|(material, _)| material == &mk));
+        // Undoing a capture restores a white piece, growing the material key.
+        assert!(results.iter().any(|(material, _)| material != &mk));
+
+        for (material, result_index) in &results {
+            let child_indexer = PositionIndexer::new(material.clone());
+            assert!(child_indexer.index_to_position(*result_index).is_ok());
+        }
+    }
+
+    #[test]
+    fn unmoves_out_of_bounds_index_returns_no_results() {
+        let mk = MaterialKey::from_string("KRvK").unwrap();
+        let index = PositionIndexer::new(mk.clone()).total_positions();
+        assert!(unmoves(&mk, index).is_empty());
+    }
 }
@@ -0,0 +1,312 @@
+use crate::dtz_file::read_dtz_file;
+use crate::material_key::MaterialKey;
+use crate::position_indexer::PositionIndexer;
+use crate::score::{DtzScore, DtzScoreRange};
+use crate::wdl_file::read_wdl_file;
+use crate::wdl_score_range::WdlScoreRange;
+use shakmaty::{Chess, Move, Position, Role};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Error returned when a position cannot be probed.
+///
+/// Mirrors `shakmaty_syzygy`'s `ProbeError`, but over heisenbase's own `.hbt` tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeError {
+    /// No `.hbt` file exists for this position's material key.
+    MaterialNotPresent(MaterialKey),
+    /// The position does not correspond to a valid index in its material's table
+    /// (e.g. kings adjacent to one another, or a side to move that could capture the
+    /// opposing king).
+    PositionIllegal,
+    /// The table for this material is loaded, but the stored `WdlScoreRange` is not
+    /// precise enough to answer the query (e.g. `probe_dtz` on an `Unknown` entry, or
+    /// a table that was never fully solved).
+    TableIncomplete,
+}
+
+type ProbeResult<T> = Result<T, ProbeError>;
+
+/// Loads `.hbt` files on demand and answers WDL/DTZ/best-move queries against them.
+///
+/// `probe_dtz` and `best_move` work from the `.hbt` WDL tables alone, so they only report a
+/// representative score (an immediate win or loss, a draw, a cursed win or a blessed loss)
+/// for positions whose `WdlScoreRange` is already certain; everything else surfaces as
+/// `ProbeError::TableIncomplete`. [`Self::probe_wdl_and_dtz`] reads the exact ply count from
+/// a sibling `.hbz` table instead.
+pub struct Prober {
+    data_dir: PathBuf,
+    tables: HashMap<MaterialKey, Vec<WdlScoreRange>>,
+    dtz_tables: HashMap<MaterialKey, Vec<DtzScoreRange>>,
+    indexers: HashMap<MaterialKey, PositionIndexer>,
+}
+
+impl Prober {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            data_dir: data_dir.as_ref().to_path_buf(),
+            tables: HashMap::new(),
+            dtz_tables: HashMap::new(),
+            indexers: HashMap::new(),
+        }
+    }
+
+    fn table(&mut self, material: &MaterialKey) -> ProbeResult<()> {
+        if self.tables.contains_key(material) {
+            return Ok(());
+        }
+
+        let path = self.data_dir.join(format!("{}.hbt", material));
+        let wdl_table = read_wdl_file(&path)
+            .map_err(|_| ProbeError::MaterialNotPresent(material.clone()))?;
+        self.indexers
+            .entry(material.clone())
+            .or_insert_with(|| PositionIndexer::new(material.clone()));
+        self.tables.insert(material.clone(), wdl_table.positions);
+        Ok(())
+    }
+
+    fn dtz_table(&mut self, material: &MaterialKey) -> ProbeResult<()> {
+        if self.dtz_tables.contains_key(material) {
+            return Ok(());
+        }
+
+        let path = self.data_dir.join(format!("{}.hbz", material));
+        let dtz_table = read_dtz_file(&path)
+            .map_err(|_| ProbeError::MaterialNotPresent(material.clone()))?;
+        self.indexers
+            .entry(material.clone())
+            .or_insert_with(|| PositionIndexer::new(material.clone()));
+        self.dtz_tables
+            .insert(material.clone(), dtz_table.positions);
+        Ok(())
+    }
+
+    /// Look up the WDL value of `position`.
+    pub fn probe_wdl(&mut self, position: &Chess) -> ProbeResult<WdlScoreRange> {
+        let material = MaterialKey::from_position(position).ok_or(ProbeError::PositionIllegal)?;
+        self.table(&material)?;
+        let indexer = &self.indexers[&material];
+        let idx = indexer
+            .position_to_index(position)
+            .map_err(|_| ProbeError::PositionIllegal)?;
+        Ok(self.tables[&material][idx])
+    }
+
+    /// Look up the DTZ value of `position`.
+    ///
+    /// Only available once the position's `WdlScoreRange` is a certain `Win`, `Draw`,
+    /// `Loss`, `CursedWin` or `BlessedLoss`; otherwise returns `ProbeError::TableIncomplete`.
+    pub fn probe_dtz(&mut self, position: &Chess) -> ProbeResult<DtzScore> {
+        dtz_from_wdl(self.probe_wdl(position)?)
+    }
+
+    /// Look up both the WDL and DTZ value of `position` from their respective `.hbt`/`.hbz`
+    /// tables, mirroring how a real tablebase prober pairs the two metrics: the outcome, and
+    /// the exact number of halfmoves to the next move that zeroes towards it.
+    ///
+    /// Unlike [`Self::probe_dtz`], which only estimates a ply count from the WDL outcome
+    /// alone, this requires a sibling `.hbz` file to exist for the position's material.
+    pub fn probe_wdl_and_dtz(&mut self, position: &Chess) -> ProbeResult<(WdlScoreRange, DtzScore)> {
+        let wdl = self.probe_wdl(position)?;
+
+        let material = MaterialKey::from_position(position).ok_or(ProbeError::PositionIllegal)?;
+        self.dtz_table(&material)?;
+        let indexer = &self.indexers[&material];
+        let idx = indexer
+            .position_to_index(position)
+            .map_err(|_| ProbeError::PositionIllegal)?;
+        let dtz = self.dtz_tables[&material][idx].certain();
+
+        Ok((wdl, dtz))
+    }
+
+    /// Find the move that maximizes the side-to-move's score (shortest win, or failing
+    /// that, longest loss), following captures, promotions and pawn moves into child
+    /// tables exactly as `TableBuilder::evaluate_move` does while building a table.
+    ///
+    /// Returns `Ok(None)` for checkmate and stalemate, which have no legal moves.
+    pub fn best_move(&mut self, position: &Chess) -> ProbeResult<Option<(Move, DtzScore)>> {
+        let own_material =
+            MaterialKey::from_position(position).ok_or(ProbeError::PositionIllegal)?;
+        self.table(&own_material)?;
+
+        let mut best: Option<(Move, DtzScoreRange)> = None;
+        let mut acc = DtzScoreRange::checkmate();
+
+        for mv in position.legal_moves() {
+            let score = self
+                .evaluate_move(position, &own_material, &mv)?
+                .flip();
+            let next_acc = acc.max(&score);
+            if best.is_none() || next_acc != acc {
+                best = Some((mv, score));
+            }
+            acc = next_acc;
+        }
+
+        best.map(|(mv, score)| Ok((mv, dtz_from_range(score)?)))
+            .transpose()
+    }
+
+    /// Score a single move from `position`'s perspective of the *child* position, i.e.
+    /// without flipping to the mover's perspective. Mirrors
+    /// `TableBuilder::evaluate_move`.
+    fn evaluate_move(
+        &mut self,
+        position: &Chess,
+        own_material: &MaterialKey,
+        mv: &Move,
+    ) -> ProbeResult<DtzScoreRange> {
+        let mut child = position.clone();
+        child.play_unchecked(mv.clone());
+
+        let is_promotion = mv.promotion().is_some();
+        let is_pawn_move = mv.role() == Role::Pawn;
+
+        if !mv.is_capture() && !is_promotion && !is_pawn_move {
+            // Quiet move: stays within `own_material`.
+            let indexer = &self.indexers[own_material];
+            let idx = indexer
+                .position_to_index(&child)
+                .map_err(|_| ProbeError::PositionIllegal)?;
+            return Ok(DtzScoreRange::from(self.tables[own_material][idx]).add_half_move());
+        }
+
+        if child.is_checkmate() {
+            return Ok(DtzScoreRange::checkmate());
+        }
+        if child.is_stalemate() || child.is_insufficient_material() {
+            return Ok(DtzScoreRange::draw());
+        }
+
+        let child_material = MaterialKey::from_position(&child).ok_or(ProbeError::PositionIllegal)?;
+        self.table(&child_material)?;
+        let indexer = &self.indexers[&child_material];
+        let idx = indexer
+            .position_to_index(&child)
+            .map_err(|_| ProbeError::PositionIllegal)?;
+        Ok(DtzScoreRange::from(self.tables[&child_material][idx]))
+    }
+}
+
+fn dtz_from_wdl(wdl: WdlScoreRange) -> ProbeResult<DtzScore> {
+    match wdl {
+        WdlScoreRange::Win => Ok(DtzScore::immediate_win()),
+        WdlScoreRange::Draw => Ok(DtzScore::draw()),
+        WdlScoreRange::Loss => Ok(DtzScore::immediate_loss()),
+        WdlScoreRange::CursedWin => Ok(DtzScore::cursed_win()),
+        WdlScoreRange::BlessedLoss => Ok(DtzScore::blessed_loss()),
+        _ => Err(ProbeError::TableIncomplete),
+    }
+}
+
+fn dtz_from_range(range: DtzScoreRange) -> ProbeResult<DtzScore> {
+    if range.is_illegal() {
+        return Err(ProbeError::PositionIllegal);
+    }
+    if range == DtzScoreRange::draw() {
+        return Ok(DtzScore::draw());
+    }
+    if range.is_cursed_win() {
+        return Ok(DtzScore::cursed_win());
+    }
+    if range.is_blessed_loss() {
+        return Ok(DtzScore::blessed_loss());
+    }
+    if range.is_win() {
+        return Ok(DtzScore::immediate_win());
+    }
+    if range.flip().is_win() {
+        return Ok(DtzScore::immediate_loss());
+    }
+    Err(ProbeError::TableIncomplete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table_builder::TableBuilder;
+    use crate::wdl_file::write_wdl_file;
+    use shakmaty::{CastlingMode, fen::Fen};
+    use std::fs;
+
+    fn temp_data_dir(prefix: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("heisenbase_prober_{prefix}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn probes_wdl_and_best_move() {
+        let data_dir = temp_data_dir("kqvk");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+
+        let mut tb = TableBuilder::new_with_data_dir(material.clone(), &data_dir);
+        tb.solve();
+        let wdl_table = crate::wdl_table::WdlTable::from(tb);
+        write_wdl_file(data_dir.join("KQvK.hbt"), &wdl_table).unwrap();
+
+        let mut prober = Prober::new(&data_dir);
+
+        let mate_in_one = "k7/8/1QK5/8/8/8/8/8 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+
+        assert_eq!(prober.probe_wdl(&mate_in_one).unwrap(), WdlScoreRange::Win);
+
+        let (_, dtz) = prober.best_move(&mate_in_one).unwrap().unwrap();
+        assert_eq!(dtz, DtzScore::immediate_win());
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn probes_combined_wdl_and_dtz() {
+        use crate::dtz_file::write_dtz_file;
+        use crate::dtz_table::DtzTable;
+
+        let data_dir = temp_data_dir("kqvk_dtz");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+
+        let mut wdl_tb = TableBuilder::new_with_data_dir(material.clone(), &data_dir);
+        wdl_tb.solve();
+        let wdl_table = crate::wdl_table::WdlTable::from(wdl_tb);
+        write_wdl_file(data_dir.join("KQvK.hbt"), &wdl_table).unwrap();
+
+        let mut dtz_tb = TableBuilder::new_with_data_dir(material, &data_dir);
+        dtz_tb.solve();
+        let dtz_table = DtzTable::from(dtz_tb);
+        write_dtz_file(data_dir.join("KQvK.hbz"), &dtz_table).unwrap();
+
+        let mut prober = Prober::new(&data_dir);
+
+        let mate_in_one = "k7/8/1QK5/8/8/8/8/8 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+
+        let (wdl, dtz) = prober.probe_wdl_and_dtz(&mate_in_one).unwrap();
+        assert_eq!(wdl, WdlScoreRange::Win);
+        assert_eq!(dtz, DtzScore::immediate_win());
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn probe_wdl_reports_missing_material() {
+        let data_dir = temp_data_dir("missing");
+        let mut prober = Prober::new(&data_dir);
+        let position = Chess::default();
+        assert!(matches!(
+            prober.probe_wdl(&position),
+            Err(ProbeError::MaterialNotPresent(_))
+        ));
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -1,7 +1,20 @@
-use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::ops::Neg;
 
 use crate::wdl_score_range::WdlScoreRange;
 
+/// Whether a [`DtzScore`] has run past the 50-move-rule horizon.
+///
+/// A win (loss) that cannot zero the halfmove clock within the remaining ply budget is still
+/// a win (loss) in theory, but is drawn in practice once a player claims the 50-move rule.
+/// Syzygy calls these "cursed" wins and "blessed" losses; we track them here instead of
+/// collapsing them into a plain draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Curse {
+    None,
+    CursedWin,
+    BlessedLoss,
+}
+
 /// A DTZ score.
 ///
 /// This score is from the perspective of the side to move.
@@ -12,80 +25,162 @@ use crate::wdl_score_range::WdlScoreRange;
 /// -1 means the side to move loses and has a zeroing move immediately available
 /// -99 means the side to move loses and has a zeroing move in 100 halfmoves
 /// -100 means the side to move is checkmated
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct DtzScore(i8);
+///
+/// A win whose halfmove budget would run out before a zeroing move is available is a
+/// "cursed win" (and, mirrored, a "blessed loss"): still a win/loss by value, but a draw
+/// under the 50-move rule. These carry a halfmove count of 0, same as a plain draw, since
+/// they're equally unable to force progress within the window; see [`Curse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtzScore {
+    halfmoves: i8,
+    curse: Curse,
+}
 
 impl DtzScore {
     pub fn immediate_win() -> Self {
-        Self(99)
+        Self {
+            halfmoves: 99,
+            curse: Curse::None,
+        }
     }
 
     pub fn immediate_loss() -> Self {
-        Self(-100)
+        Self {
+            halfmoves: -100,
+            curse: Curse::None,
+        }
     }
 
     pub fn draw() -> Self {
-        Self(0)
+        Self {
+            halfmoves: 0,
+            curse: Curse::None,
+        }
+    }
+
+    /// A theoretical win that cannot zero the halfmove clock within the remaining ply
+    /// budget, so it is drawn under the 50-move rule.
+    pub fn cursed_win() -> Self {
+        Self {
+            halfmoves: 0,
+            curse: Curse::CursedWin,
+        }
+    }
+
+    /// A theoretical loss that cannot be converted within the remaining ply budget, so it
+    /// is drawn under the 50-move rule.
+    pub fn blessed_loss() -> Self {
+        Self {
+            halfmoves: 0,
+            curse: Curse::BlessedLoss,
+        }
     }
 
     pub fn is_draw(&self) -> bool {
-        self.0 == 0
+        self.halfmoves == 0 && self.curse == Curse::None
     }
 
     pub fn is_win(&self) -> bool {
-        self.0 > 0
+        self.halfmoves > 0 || self.curse == Curse::CursedWin
     }
 
     pub fn is_loss(&self) -> bool {
-        self.0 < 0
+        self.halfmoves < 0 || self.curse == Curse::BlessedLoss
     }
-}
 
-impl DtzScore {
-    pub fn add_half_move(&self) -> Self {
-        if self.0 > 0 {
-            Self(self.0 - 1)
-        } else if self.0 < 0 {
-            Self(self.0 + 1)
+    pub fn is_cursed_win(&self) -> bool {
+        self.curse == Curse::CursedWin
+    }
+
+    pub fn is_blessed_loss(&self) -> bool {
+        self.curse == Curse::BlessedLoss
+    }
+
+    /// Picks the better score for the side to move.
+    ///
+    /// Only the halfmove count is compared: a cursed win, a blessed loss and a plain draw
+    /// all carry a halfmove count of 0, since none of them can force progress within the
+    /// 50-move window, and so they compare equal here even though `Curse` distinguishes
+    /// them for WDL reporting.
+    pub fn max(self, other: Self) -> Self {
+        if other.halfmoves > self.halfmoves {
+            other
         } else {
-            self.clone()
+            self
         }
     }
 }
 
-impl Neg for DtzScore {
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        Self(-self.0)
+impl DtzScore {
+    /// Advance this score by one halfmove without a zeroing move being played.
+    ///
+    /// Once a win's (loss's) halfmove count would cross the horizon, it is pinned at a
+    /// cursed win (blessed loss) instead of continuing past it, since the 50-move rule
+    /// makes every halfmove beyond that point equally moot.
+    pub fn add_half_move(&self) -> Self {
+        match self.curse {
+            Curse::CursedWin | Curse::BlessedLoss => *self,
+            Curse::None => {
+                if self.halfmoves > 1 {
+                    Self {
+                        halfmoves: self.halfmoves - 1,
+                        curse: Curse::None,
+                    }
+                } else if self.halfmoves == 1 {
+                    Self::cursed_win()
+                } else if self.halfmoves < -1 {
+                    Self {
+                        halfmoves: self.halfmoves + 1,
+                        curse: Curse::None,
+                    }
+                } else if self.halfmoves == -1 {
+                    Self::blessed_loss()
+                } else {
+                    *self
+                }
+            }
+        }
     }
 }
 
-impl Add<i8> for DtzScore {
-    type Output = Self;
-
-    fn add(self, other: i8) -> Self::Output {
-        Self(self.0 + other)
+impl DtzScore {
+    /// Encode this score as a single signed byte for on-disk storage: the `halfmoves` count
+    /// for an uncursed score, or one of two sentinels outside that count's `-100..=99` range
+    /// for a cursed win/blessed loss, which otherwise share a halfmove count of 0 with a plain
+    /// draw.
+    pub fn to_storage_value(self) -> i8 {
+        match self.curse {
+            Curse::None => self.halfmoves,
+            Curse::CursedWin => 100,
+            Curse::BlessedLoss => -101,
+        }
     }
-}
 
-impl AddAssign<i8> for DtzScore {
-    fn add_assign(&mut self, other: i8) {
-        self.0 += other;
+    /// Inverse of [`Self::to_storage_value`].
+    pub fn from_storage_value(value: i8) -> Self {
+        match value {
+            100 => Self::cursed_win(),
+            -101 => Self::blessed_loss(),
+            halfmoves => Self {
+                halfmoves,
+                curse: Curse::None,
+            },
+        }
     }
 }
 
-impl Sub<i8> for DtzScore {
+impl Neg for DtzScore {
     type Output = Self;
 
-    fn sub(self, other: i8) -> Self::Output {
-        Self(self.0 - other)
-    }
-}
-
-impl SubAssign<i8> for DtzScore {
-    fn sub_assign(&mut self, other: i8) {
-        self.0 -= other;
+    fn neg(self) -> Self::Output {
+        Self {
+            halfmoves: -self.halfmoves,
+            curse: match self.curse {
+                Curse::None => Curse::None,
+                Curse::CursedWin => Curse::BlessedLoss,
+                Curse::BlessedLoss => Curse::CursedWin,
+            },
+        }
     }
 }
 
@@ -126,6 +221,24 @@ impl DtzScoreRange {
         }
     }
 
+    /// A theoretical win that cannot zero the halfmove clock within the remaining ply budget,
+    /// so it is drawn under the 50-move rule.
+    pub fn cursed_win() -> Self {
+        Self {
+            min: DtzScore::cursed_win(),
+            max: DtzScore::cursed_win(),
+        }
+    }
+
+    /// A theoretical loss that cannot be converted within the remaining ply budget, so it is
+    /// drawn under the 50-move rule.
+    pub fn blessed_loss() -> Self {
+        Self {
+            min: DtzScore::blessed_loss(),
+            max: DtzScore::blessed_loss(),
+        }
+    }
+
     pub fn is_certain(&self) -> bool {
         self.min == self.max || self.is_illegal()
     }
@@ -138,6 +251,23 @@ impl DtzScoreRange {
         self.min.is_win() && self.max.is_loss()
     }
 
+    /// True if this range has collapsed to a single, certain win for the side to move.
+    pub fn is_win(&self) -> bool {
+        self.is_certain() && self.min.is_win()
+    }
+
+    /// True if this range has collapsed to a certain win that is drawn under the 50-move
+    /// rule.
+    pub fn is_cursed_win(&self) -> bool {
+        self.is_certain() && self.min.is_cursed_win()
+    }
+
+    /// True if this range has collapsed to a certain loss that is drawn under the 50-move
+    /// rule.
+    pub fn is_blessed_loss(&self) -> bool {
+        self.is_certain() && self.min.is_blessed_loss()
+    }
+
     /// Flips the score range.
     ///
     /// This is used to convert a score range from the perspective of the side to move to the
@@ -166,17 +296,67 @@ impl DtzScoreRange {
 
         Self { min, max }
     }
+
+    /// The single [`DtzScore`] this range has collapsed to.
+    ///
+    /// # Panics
+    /// Panics if this range is illegal or still uncertain; a [`DtzTable`](crate::dtz_table::DtzTable)
+    /// is only ever built from a fully solved [`TableBuilder`](crate::table_builder::TableBuilder),
+    /// whose every position has resolved to a certain score by the time it's persisted.
+    pub fn certain(&self) -> DtzScore {
+        assert!(!self.is_illegal(), "illegal positions have no DtzScore");
+        assert!(
+            self.is_certain(),
+            "DTZ storage requires a fully solved, certain score"
+        );
+        self.min
+    }
+
+    /// Encode this (already solved) range as a single signed byte for on-disk storage: see
+    /// [`DtzScore::to_storage_value`], or `i8::MIN` for an illegal position.
+    pub fn to_storage_value(&self) -> i8 {
+        if self.is_illegal() {
+            return i8::MIN;
+        }
+        self.certain().to_storage_value()
+    }
+
+    /// Inverse of [`Self::to_storage_value`].
+    pub fn from_storage_value(value: i8) -> Self {
+        if value == i8::MIN {
+            return Self::illegal();
+        }
+        let score = DtzScore::from_storage_value(value);
+        Self {
+            min: score,
+            max: score,
+        }
+    }
 }
 
 impl From<DtzScoreRange> for WdlScoreRange {
     fn from(score: DtzScoreRange) -> Self {
-        match (score.min.0.signum(), score.max.0.signum()) {
+        // A certain cursed win/blessed loss carries a halfmove count of 0, same as a plain
+        // draw, so it must be special-cased before falling through to the signum match below.
+        if score.is_cursed_win() {
+            return WdlScoreRange::CursedWin;
+        }
+        if score.is_blessed_loss() {
+            return WdlScoreRange::BlessedLoss;
+        }
+
+        match (
+            score.min.halfmoves.signum(),
+            score.max.halfmoves.signum(),
+        ) {
             (1, -1) => WdlScoreRange::IllegalPosition,
             (1, 1) => WdlScoreRange::Win,
-            (1, 0) => panic!("DtzScoreRange::into: min > 0 and max == 0"),
+            // Only reachable for an uncertain bound that straddles the 50-move horizon;
+            // conservatively report the curse rather than a bare win/draw.
+            (1, 0) => WdlScoreRange::CursedWin,
             (0, 1) => WdlScoreRange::WinOrDraw,
             (0, 0) => WdlScoreRange::Draw,
-            (0, -1) => panic!("DtzScoreRange::into: min == 0 and max < 0"),
+            (0, -1) => WdlScoreRange::BlessedLoss,
             (-1, 1) => WdlScoreRange::Unknown,
             (-1, 0) => WdlScoreRange::DrawOrLoss,
             (-1, -1) => WdlScoreRange::Loss,
@@ -210,6 +390,110 @@ impl From<WdlScoreRange> for DtzScoreRange {
                 max: DtzScore::immediate_loss(),
             },
             WdlScoreRange::IllegalPosition => DtzScoreRange::illegal(),
+            WdlScoreRange::CursedWin => Self {
+                min: DtzScore::cursed_win(),
+                max: DtzScore::cursed_win(),
+            },
+            WdlScoreRange::BlessedLoss => Self {
+                min: DtzScore::blessed_loss(),
+                max: DtzScore::blessed_loss(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn win_becomes_cursed_once_the_horizon_is_crossed() {
+        let mut score = DtzScore::immediate_win();
+        for _ in 0..98 {
+            score = score.add_half_move();
         }
+        assert!(score.is_win());
+        assert!(!score.is_cursed_win());
+
+        score = score.add_half_move();
+        assert!(score.is_win());
+        assert!(score.is_cursed_win());
+
+        // The curse sticks once crossed; further quiet halfmoves don't un-curse it.
+        let still_cursed = score.add_half_move();
+        assert_eq!(still_cursed, score);
+    }
+
+    #[test]
+    fn loss_becomes_blessed_once_the_horizon_is_crossed() {
+        let mut score = DtzScore::immediate_loss();
+        for _ in 0..100 {
+            score = score.add_half_move();
+        }
+        assert!(score.is_loss());
+        assert!(score.is_blessed_loss());
+    }
+
+    #[test]
+    fn cursed_win_converts_to_wdl_cursed_win() {
+        let range = DtzScoreRange {
+            min: DtzScore::cursed_win(),
+            max: DtzScore::cursed_win(),
+        };
+        assert_eq!(WdlScoreRange::from(range), WdlScoreRange::CursedWin);
+        assert_eq!(
+            DtzScoreRange::from(WdlScoreRange::CursedWin),
+            range
+        );
+    }
+
+    #[test]
+    fn storage_value_roundtrips_through_every_kind_of_score() {
+        for score in [
+            DtzScore::immediate_win(),
+            DtzScore::immediate_loss(),
+            DtzScore::draw(),
+            DtzScore::cursed_win(),
+            DtzScore::blessed_loss(),
+        ] {
+            assert_eq!(DtzScore::from_storage_value(score.to_storage_value()), score);
+        }
+    }
+
+    #[test]
+    fn cursed_win_and_blessed_loss_storage_values_dont_collide_with_plain_halfmove_counts() {
+        let cursed_win = DtzScore::cursed_win().to_storage_value();
+        let blessed_loss = DtzScore::blessed_loss().to_storage_value();
+        assert_ne!(cursed_win, DtzScore::immediate_win().to_storage_value());
+        assert_ne!(blessed_loss, DtzScore::immediate_loss().to_storage_value());
+        assert_ne!(cursed_win, blessed_loss);
+    }
+
+    #[test]
+    fn range_storage_value_roundtrips_including_illegal() {
+        let illegal = DtzScoreRange::illegal();
+        assert_eq!(
+            DtzScoreRange::from_storage_value(illegal.to_storage_value()),
+            illegal
+        );
+
+        let win = DtzScoreRange {
+            min: DtzScore::immediate_win(),
+            max: DtzScore::immediate_win(),
+        };
+        assert_eq!(DtzScoreRange::from_storage_value(win.to_storage_value()), win);
+    }
+
+    #[test]
+    fn blessed_loss_converts_to_wdl_blessed_loss() {
+        let range = DtzScoreRange {
+            min: DtzScore::blessed_loss(),
+            max: DtzScore::blessed_loss(),
+        };
+        assert_eq!(WdlScoreRange::from(range), WdlScoreRange::BlessedLoss);
+        assert_eq!(
+            DtzScoreRange::from(WdlScoreRange::BlessedLoss),
+            range
+        );
     }
 }
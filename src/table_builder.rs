@@ -1,35 +1,56 @@
 use crate::material_key::MaterialKey;
-use crate::position_indexer::{PositionIndexer, PositionMappingError};
+use crate::position_indexer::PositionIndexer;
 use crate::score::DtzScoreRange;
+use crate::transpose::TableLayout;
 use crate::wdl_file::read_wdl_file;
 use crate::wdl_score_range::WdlScoreRange;
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use rayon::prelude::*;
+use indicatif::{ProgressBar, ProgressStyle};
 use shakmaty::{Chess, Move, Position, Role};
-use std::collections::HashMap;
+use shakmaty_syzygy::{SyzygyError, Tablebase};
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 
+#[cfg(test)]
+use indicatif::ParallelProgressIterator;
+#[cfg(test)]
+use rayon::prelude::*;
+
 pub struct TableBuilder {
     pub(crate) material: MaterialKey,
     pub(crate) position_indexer: PositionIndexer,
     pub(crate) positions: Vec<DtzScoreRange>,
+    /// Stored in [`Self::child_table_layout`]'s physical order; only [`Self::evaluate_move`]
+    /// reads from it, so it's the only place that needs to know the layout.
     pub(crate) child_tables: HashMap<MaterialKey, Vec<WdlScoreRange>>,
     pub(crate) child_indexers: HashMap<MaterialKey, PositionIndexer>,
     pub(crate) loaded_child_tables: Vec<MaterialKey>,
     pub(crate) missing_child_tables: Vec<MaterialKey>,
+    /// Physical storage order for [`Self::child_tables`]. Batched sibling probing (scoring
+    /// every legal move from one node in [`Self::evaluate_move`]) mostly varies a non-leading
+    /// piece while the rest of the position stays fixed, so [`TableLayout::Transposed`] keeps
+    /// that scan contiguous; see [`crate::transpose`].
+    pub(crate) child_table_layout: TableLayout,
+    /// Standard Syzygy WDL tables, used as a fallback for child materials that have no
+    /// `.hbt` file. Positions served from here never reach `Unknown`/`WinOrDraw`-style
+    /// uncertainty, since Syzygy tables are exact.
+    pub(crate) syzygy_tables: Tablebase<Chess>,
 }
 
 impl TableBuilder {
     pub fn new(material: MaterialKey) -> Self {
-        Self::with_data_dir(material, Path::new("./data/heisenbase"))
+        Self::with_data_dirs(
+            material,
+            Path::new("./data/heisenbase"),
+            Path::new("./data/syzygy"),
+        )
     }
 
     #[cfg(test)]
     pub(crate) fn new_with_data_dir<P: AsRef<Path>>(material: MaterialKey, data_dir: P) -> Self {
-        Self::with_data_dir(material, data_dir.as_ref())
+        Self::with_data_dirs(material, data_dir.as_ref(), Path::new("./data/syzygy"))
     }
 
-    fn with_data_dir(material: MaterialKey, data_dir: &Path) -> Self {
+    fn with_data_dirs(material: MaterialKey, data_dir: &Path, syzygy_dir: &Path) -> Self {
         let position_indexer = PositionIndexer::new(material.clone());
         let positions_len = position_indexer.total_positions();
         let mut child_tables = HashMap::new();
@@ -53,7 +74,11 @@ impl TableBuilder {
             }
         }
 
-        Self {
+        let mut syzygy_tables = Tablebase::new();
+        // Best-effort: a missing or empty directory just means no Syzygy fallback.
+        let _ = syzygy_tables.add_directory(syzygy_dir);
+
+        let mut table_builder = Self {
             material,
             position_indexer,
             positions: vec![DtzScoreRange::unknown(); positions_len],
@@ -61,10 +86,164 @@ impl TableBuilder {
             child_indexers,
             loaded_child_tables,
             missing_child_tables,
+            child_table_layout: TableLayout::RowMajor,
+            syzygy_tables,
+        };
+        // Child tables are read off disk in `TableLayout::RowMajor` order; switch them to the
+        // cache-friendly transposed layout once up front so `solve`'s batched sibling probing in
+        // `evaluate_move` benefits from it without every caller having to remember to ask.
+        table_builder.set_child_table_layout(TableLayout::Transposed);
+        table_builder
+    }
+
+    /// Reorder [`Self::child_tables`] into `layout`'s physical order, so [`Self::evaluate_move`]
+    /// stays cache-friendly while scoring many sibling moves from one node.
+    ///
+    /// `.hbt` files are read in [`TableLayout::RowMajor`] order, so this is a one-time pass
+    /// over every loaded child table rather than something the file format itself needs to
+    /// know about.
+    pub fn set_child_table_layout(&mut self, layout: TableLayout) {
+        if layout == self.child_table_layout {
+            return;
+        }
+
+        for (child_key, table) in self.child_tables.iter_mut() {
+            let indexer = &self.child_indexers[child_key];
+            *table = indexer.reorder_for_layout(table, self.child_table_layout, layout);
         }
+        self.child_table_layout = layout;
     }
 
+    /// Solve every position in this material class via retrograde (backward-induction)
+    /// analysis.
+    ///
+    /// The solver first resolves every position decidable from purely static information:
+    /// checkmates, stalemates/draws by insufficient material, and zeroing moves (captures,
+    /// promotions and pawn pushes), whose scores come straight from already-solved child
+    /// material tables and never depend on another position in *this* table. Every other
+    /// legal move is a quiet move that stays within the material class, so its reverse edge
+    /// is recorded as a predecessor link while scanning. Resolved positions are then pushed
+    /// onto a work queue and propagated to their quiet-move predecessors: a predecessor is an
+    /// immediate win as soon as one of its successors resolves to a loss for the mover, and it
+    /// only becomes a loss once every successor has resolved without yielding a win. Each
+    /// position and each quiet-move edge is visited exactly once, so this runs in time
+    /// proportional to the move graph rather than `iterations × positions × moves` like a
+    /// forward fixpoint.
     pub fn solve(&mut self) {
+        let total = self.positions.len();
+        let progress = self.create_solve_progress_bar(total as u64);
+
+        // `predecessors[child_index]` lists every position reaching `child_index` via a quiet
+        // move. Zeroing moves leave this material class, so they are resolved eagerly below
+        // instead of being un-made.
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); total];
+        let mut pending_quiet_successors = vec![0u32; total];
+        let mut resolved = vec![false; total];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+
+        for pos_index in 0..total {
+            progress.inc(1);
+
+            let position = match self.position_indexer.index_to_position(pos_index) {
+                Ok(p) => p,
+                Err(_) => {
+                    self.positions[pos_index] = DtzScoreRange::illegal();
+                    resolved[pos_index] = true;
+                    continue;
+                }
+            };
+
+            if position.is_checkmate() {
+                self.positions[pos_index] = DtzScoreRange::checkmate();
+                resolved[pos_index] = true;
+                queue.push_back(pos_index);
+                continue;
+            }
+            if position.is_stalemate() || position.is_insufficient_material() {
+                self.positions[pos_index] = DtzScoreRange::draw();
+                resolved[pos_index] = true;
+                queue.push_back(pos_index);
+                continue;
+            }
+
+            // `checkmate()` is the identity element of `DtzScoreRange::max`, so folding it in
+            // is equivalent to starting from "no move considered yet".
+            let mut best = DtzScoreRange::checkmate();
+            let mut quiet_successors = 0u32;
+            for mv in position.legal_moves() {
+                if Self::is_zeroing_move(&mv) {
+                    best = best.max(&self.evaluate_move(&self.positions, &position, mv).flip());
+                } else {
+                    let mut child = position.clone();
+                    child.play_unchecked(mv);
+                    let child_index = self
+                        .position_indexer
+                        .position_to_index(&child)
+                        .expect("a quiet move stays within the same material class");
+                    predecessors[child_index].push(pos_index);
+                    quiet_successors += 1;
+                }
+            }
+
+            self.positions[pos_index] = best;
+            if quiet_successors == 0 || best.is_win() {
+                resolved[pos_index] = true;
+                queue.push_back(pos_index);
+            } else {
+                pending_quiet_successors[pos_index] = quiet_successors;
+            }
+        }
+
+        progress.finish_and_clear();
+
+        while let Some(child_index) = queue.pop_front() {
+            let child_score = self.positions[child_index];
+            for &pos_index in &predecessors[child_index] {
+                if resolved[pos_index] {
+                    continue;
+                }
+
+                let contribution = child_score.flip().add_half_move();
+                self.positions[pos_index] = self.positions[pos_index].max(&contribution);
+                pending_quiet_successors[pos_index] -= 1;
+
+                if pending_quiet_successors[pos_index] == 0 || self.positions[pos_index].is_win()
+                {
+                    resolved[pos_index] = true;
+                    queue.push_back(pos_index);
+                }
+            }
+        }
+
+        // A position still unresolved here has no winning move and can reach another
+        // unresolved position, so neither side can force a result out of that cycle: fold in
+        // a draw.
+        for pos_index in 0..total {
+            if !resolved[pos_index] {
+                self.positions[pos_index] = DtzScoreRange::draw().max(&self.positions[pos_index]);
+            }
+        }
+    }
+
+    fn is_zeroing_move(mv: &Move) -> bool {
+        mv.is_capture() || mv.promotion().is_some() || mv.role() == Role::Pawn
+    }
+
+    fn create_solve_progress_bar(&self, total_positions: u64) -> ProgressBar {
+        let progress = ProgressBar::new(total_positions);
+        let style = ProgressStyle::with_template(
+            "{msg} {bar:40.cyan/blue} {pos}/{len} [{elapsed_precise}<{eta_precise}]",
+        )
+        .unwrap();
+        progress.set_style(style);
+        progress.set_message("Solving (retrograde)");
+        progress
+    }
+
+    /// Forward fixpoint solver kept only as a correctness oracle for [`Self::solve`]'s
+    /// retrograde analysis; see the `retrograde_matches_fixpoint` test.
+    #[cfg(test)]
+    fn solve_fixpoint(&mut self) {
         const MAX_STEPS: usize = 101;
         let mut positions_next = vec![DtzScoreRange::unknown(); self.positions.len()];
 
@@ -72,7 +251,6 @@ impl TableBuilder {
             let progress_bar = self.create_iteration_progress_bar(it + 1);
             let updates;
             (updates, positions_next) = self.step(positions_next, progress_bar);
-            println!("Iteration {:>3}: {} updates", it + 1, updates);
 
             if updates == 0 {
                 break;
@@ -83,6 +261,7 @@ impl TableBuilder {
         }
     }
 
+    #[cfg(test)]
     fn create_iteration_progress_bar(&self, iteration: usize) -> ProgressBar {
         let total_positions = self.positions.len() as u64;
         let progress = ProgressBar::new(total_positions);
@@ -95,6 +274,7 @@ impl TableBuilder {
         progress
     }
 
+    #[cfg(test)]
     fn step(
         &mut self,
         mut positions_next: Vec<DtzScoreRange>,
@@ -117,7 +297,10 @@ impl TableBuilder {
         (updates, positions_next)
     }
 
+    #[cfg(test)]
     fn score_position(&self, prev_positions: &[DtzScoreRange], pos_index: usize) -> DtzScoreRange {
+        use crate::position_indexer::PositionMappingError;
+
         let old_score = prev_positions[pos_index];
         if old_score.is_illegal() || old_score.is_certain() {
             return old_score;
@@ -194,15 +377,38 @@ impl TableBuilder {
                 self.child_indexers.get(&child_key),
             ) {
                 match child_indexer.position_to_index(&child_position) {
-                    Ok(idx) => DtzScoreRange::from(table[idx]),
+                    Ok(idx) => {
+                        let physical_idx =
+                            child_indexer.physical_index(idx, self.child_table_layout);
+                        DtzScoreRange::from(table[physical_idx])
+                    }
                     Err(_) => DtzScoreRange::unknown(),
                 }
+            } else if let Some(wdl) = self.probe_syzygy_wdl(&child_position) {
+                DtzScoreRange::from(wdl)
             } else {
                 DtzScoreRange::unknown()
             }
         }
     }
 
+    /// Probe a standard Syzygy WDL table for `position`, if one is loaded that covers
+    /// its material. Cursed wins and blessed losses map to `WdlScoreRange::CursedWin` and
+    /// `WdlScoreRange::BlessedLoss`.
+    fn probe_syzygy_wdl(&self, position: &Chess) -> Option<WdlScoreRange> {
+        use shakmaty_syzygy::Wdl;
+
+        match self.syzygy_tables.probe_wdl_after_zeroing(position) {
+            Ok(Wdl::Win) => Some(WdlScoreRange::Win),
+            Ok(Wdl::CursedWin) => Some(WdlScoreRange::CursedWin),
+            Ok(Wdl::Draw) => Some(WdlScoreRange::Draw),
+            Ok(Wdl::BlessedLoss) => Some(WdlScoreRange::BlessedLoss),
+            Ok(Wdl::Loss) => Some(WdlScoreRange::Loss),
+            Err(SyzygyError::MissingTable { .. }) => None,
+            Err(_) => None,
+        }
+    }
+
     pub fn loaded_child_materials(&self) -> &[MaterialKey] {
         &self.loaded_child_tables
     }
@@ -235,6 +441,8 @@ mod tests {
             child_indexers: HashMap::new(),
             loaded_child_tables: Vec::new(),
             missing_child_tables: Vec::new(),
+            child_table_layout: TableLayout::RowMajor,
+            syzygy_tables: Tablebase::new(),
         };
 
         let position = "7k/8/8/8/8/8/8/KQ6 w - - 0 1"
@@ -327,6 +535,24 @@ mod tests {
         assert_eq!(wdl, WdlScoreRange::Win);
     }
 
+    #[test]
+    fn retrograde_matches_fixpoint() {
+        let material = MaterialKey::from_string("KQvK").unwrap();
+
+        let mut retrograde = TableBuilder::new(material.clone());
+        retrograde.solve();
+
+        let mut fixpoint = TableBuilder::new(material);
+        fixpoint.solve_fixpoint();
+
+        let retrograde_wdl: Vec<WdlScoreRange> =
+            retrograde.positions.iter().copied().map(Into::into).collect();
+        let fixpoint_wdl: Vec<WdlScoreRange> =
+            fixpoint.positions.iter().copied().map(Into::into).collect();
+
+        assert_eq!(retrograde_wdl, fixpoint_wdl);
+    }
+
     fn temp_data_dir(prefix: &str) -> PathBuf {
         let mut dir = std::env::temp_dir();
         dir.push(format!("heisenbase_{prefix}_{}", std::process::id()));
@@ -379,6 +605,52 @@ mod tests {
         fs::remove_dir_all(data_dir).unwrap();
     }
 
+    #[test]
+    fn child_table_layout_round_trips_through_physical_index() {
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let child_key = MaterialKey::from_string("KvK").unwrap();
+        let child_indexer = PositionIndexer::new(child_key.clone());
+        let total = child_indexer.total_positions();
+
+        let values: Vec<WdlScoreRange> = (0..total)
+            .map(|i| match i % 3 {
+                0 => WdlScoreRange::Win,
+                1 => WdlScoreRange::Draw,
+                _ => WdlScoreRange::Loss,
+            })
+            .collect();
+
+        let mut child_tables = HashMap::new();
+        child_tables.insert(child_key.clone(), values.clone());
+        let mut child_indexers = HashMap::new();
+        child_indexers.insert(child_key.clone(), child_indexer);
+
+        let mut tb = TableBuilder {
+            material: material.clone(),
+            position_indexer: PositionIndexer::new(material),
+            positions: Vec::new(),
+            child_tables,
+            child_indexers,
+            loaded_child_tables: vec![child_key.clone()],
+            missing_child_tables: Vec::new(),
+            child_table_layout: TableLayout::RowMajor,
+            syzygy_tables: Tablebase::new(),
+        };
+
+        tb.set_child_table_layout(TableLayout::Transposed);
+
+        let indexer = &tb.child_indexers[&child_key];
+        let transposed_table = &tb.child_tables[&child_key];
+        for logical_idx in 0..total {
+            let physical_idx = indexer.physical_index(logical_idx, TableLayout::Transposed);
+            assert_eq!(transposed_table[physical_idx], values[logical_idx]);
+        }
+
+        // Toggling back to row-major recovers the original physical order.
+        tb.set_child_table_layout(TableLayout::RowMajor);
+        assert_eq!(tb.child_tables[&child_key], values);
+    }
+
     #[test]
     fn pawn_move_uses_child_table() {
         let material = MaterialKey::from_string("Ka2vK").unwrap();
@@ -412,4 +684,54 @@ mod tests {
 
         fs::remove_dir_all(data_dir).unwrap();
     }
+
+    #[test]
+    fn evaluate_move_is_consistent_across_child_table_layouts() {
+        let material = MaterialKey::from_string("Ka2vK").unwrap();
+        let data_dir = temp_data_dir("layout_consistency");
+
+        let child_key = MaterialKey::from_string("Ka3vK").unwrap();
+        let child_indexer = PositionIndexer::new(child_key.clone());
+        // A mix of outcomes, not a uniform value, so a layout bug that reads the wrong
+        // physical slot would actually be observable.
+        let positions: Vec<WdlScoreRange> = (0..child_indexer.total_positions())
+            .map(|i| match i % 3 {
+                0 => WdlScoreRange::Win,
+                1 => WdlScoreRange::Draw,
+                _ => WdlScoreRange::Loss,
+            })
+            .collect();
+        let child_table = WdlTable {
+            material: child_key,
+            positions,
+        };
+        let child_path = data_dir.join("Ka3vK.hbt");
+        write_wdl_file(&child_path, &child_table).unwrap();
+
+        let mut tb = TableBuilder::new_with_data_dir(material, &data_dir);
+        // `new_with_data_dir` already switches to `Transposed`; start from `RowMajor` explicitly
+        // so this test actually exercises both layouts rather than comparing `Transposed`
+        // against itself.
+        tb.set_child_table_layout(TableLayout::RowMajor);
+
+        let position: Chess = "8/8/8/8/8/8/P7/K6k w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        let pawn_move = position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.role() == Role::Pawn && mv.to() == Square::A3)
+            .expect("expected a2a3 pawn move");
+
+        let row_major_result = tb.evaluate_move(&tb.positions, &position, pawn_move.clone());
+
+        tb.set_child_table_layout(TableLayout::Transposed);
+        let transposed_result = tb.evaluate_move(&tb.positions, &position, pawn_move);
+
+        assert_eq!(row_major_result, transposed_result);
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
 }
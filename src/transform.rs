@@ -1,3 +1,13 @@
+use crate::material_key::MaterialKey;
+use shakmaty::{
+    Bitboard, CastlingMode, Color, EnPassantMode, FromSetup, Piece, Position, Role, Setup, Square,
+};
+
+/// The full 8-element dihedral symmetry group of the board (D4): `MirrorMain`/`MirrorAnti` are
+/// the main/anti-diagonal flips (what's elsewhere called `FlipDiagonal`/`FlipAntiDiagonal`),
+/// and combined with `FlipHorizontal`/`FlipVertical`/the three rotations they already give
+/// [`TransformSet::for_material`] all 8 elements for pawnless, bishopless material — no
+/// additional variants are needed here.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Transform {
     Identity,
@@ -10,6 +20,92 @@ pub enum Transform {
     MirrorAnti,
 }
 
+impl Transform {
+    /// Map a `(file, rank)` coordinate pair through this symmetry, on a `board_size`-wide
+    /// board (0-indexed, so the last file/rank is `board_size - 1`).
+    pub fn apply(self, file: u8, rank: u8, board_size: u8) -> (u8, u8) {
+        let n = board_size - 1;
+        match self {
+            Transform::Identity => (file, rank),
+            Transform::FlipHorizontal => (n - file, rank),
+            Transform::FlipVertical => (file, n - rank),
+            Transform::Rotate90 => (rank, n - file),
+            Transform::Rotate180 => (n - file, n - rank),
+            Transform::Rotate270 => (n - rank, file),
+            Transform::MirrorMain => (rank, file),
+            Transform::MirrorAnti => (n - rank, n - file),
+        }
+    }
+
+    /// Map a single chessboard square through this symmetry.
+    pub fn apply_square(self, square: Square) -> Square {
+        let (file, rank) = self.apply(square.file() as u8, square.rank() as u8, 8);
+        Square::new(rank as u32 * 8 + file as u32)
+    }
+
+    /// Map every square in `squares` through this symmetry, in the same order.
+    pub fn apply_squares(self, squares: impl IntoIterator<Item = Square>) -> Vec<Square> {
+        squares
+            .into_iter()
+            .map(|square| self.apply_square(square))
+            .collect()
+    }
+
+    /// Apply this symmetry to a whole `Bitboard` in constant time via word-level bit-twiddling,
+    /// instead of looping over every square with [`Self::apply_square`]. Agrees with the
+    /// per-square path for every transform (see `apply_bitboard_agrees_with_apply_squares`
+    /// below) — this is the fast path for canonicalizing pawn structures, which runs once per
+    /// allowed transform of every material key during generation.
+    pub fn apply_bitboard(self, bitboard: Bitboard) -> Bitboard {
+        Bitboard(transform_word(self, bitboard.0))
+    }
+
+    /// The transform equivalent to applying `other`, then `self`.
+    ///
+    /// Derived straight from the coordinate action in [`Self::apply`] rather than a literal
+    /// Cayley table: the composition's image of a handful of probe points (on an arbitrary
+    /// 8-wide board; the group itself doesn't care about board size) uniquely identifies which
+    /// single `Transform` produces the same images.
+    pub fn compose(self, other: Transform) -> Transform {
+        const PROBES: [(u8, u8); 3] = [(1, 0), (0, 2), (3, 5)];
+        let image =
+            |transform: Transform| PROBES.map(|(file, rank)| transform.apply(file, rank, 8));
+        let combined = PROBES.map(|(file, rank)| {
+            let (file, rank) = other.apply(file, rank, 8);
+            self.apply(file, rank, 8)
+        });
+
+        ALL_TRANSFORMS
+            .iter()
+            .copied()
+            .find(|&candidate| image(candidate) == combined)
+            .expect("D4 is closed under composition")
+    }
+
+    /// The transform that undoes this one: `self.compose(self.inverse())` is always `Identity`.
+    pub fn inverse(self) -> Transform {
+        ALL_TRANSFORMS
+            .iter()
+            .copied()
+            .find(|&candidate| self.compose(candidate) == Transform::Identity)
+            .expect("D4 is closed under composition, so every transform has an inverse")
+    }
+
+    /// Apply this single symmetry to `pos`, unlike [`TransformSet::canonicalize`] which tries
+    /// every transform in a set and keeps the smallest: useful when the caller wants one
+    /// specific transformed copy, e.g. checking that a table's value is unchanged under a
+    /// particular symmetry rather than picking a canonical representative.
+    pub fn apply_to_position<P>(self, pos: &P) -> P
+    where
+        P: Position + FromSetup + Clone,
+    {
+        let setup = pos.clone().into_setup(EnPassantMode::Legal);
+        let transformed = transform_setup(&setup, self);
+        P::from_setup(transformed, CastlingMode::Standard)
+            .expect("applying a board symmetry to a legal position stays legal")
+    }
+}
+
 pub const ALL_TRANSFORMS: &[Transform] = &[
     Transform::Identity,
     Transform::FlipHorizontal,
@@ -37,12 +133,34 @@ pub const AXIS_FLIPS: &[Transform] = &[
 
 pub const HALF_TURN_ONLY: &[Transform] = &[Transform::Identity, Transform::Rotate180];
 
+/// The four transforms that never swap a square's color (`FlipHorizontal`, `FlipVertical`,
+/// `Rotate90` and `Rotate270` all do, since the board has an even side length). Safe to use on
+/// material keys with a same-colored bishop, which otherwise would hop between the
+/// `LightBishop`/`DarkBishop` domains a transform can't represent.
+const COLOR_PRESERVING_TRANSFORMS: &[Transform] = &[
+    Transform::Identity,
+    Transform::Rotate180,
+    Transform::MirrorMain,
+    Transform::MirrorAnti,
+];
+
+/// The only two transforms that leave a piece's rank untouched, so they're the only ones that
+/// could ever apply to a material key with pawns (which care about rank/direction).
+const HORIZONTAL_TRANSFORMS: &[Transform] = &[Transform::Identity, Transform::FlipHorizontal];
+
+/// No nontrivial symmetry survives for this material key.
+pub(crate) const IDENTITY_ONLY_TRANSFORMS: &[Transform] = &[Transform::Identity];
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TransformSet {
     Full,
     Rotations,
     AxisFlips,
     HalfTurn,
+    /// An explicit set of transforms, for configurations ([`Self::for_material`]) that don't fit
+    /// one of the four fixed groups above, or for a subgroup built from scratch with
+    /// [`Self::from_generators`].
+    Custom(&'static [Transform]),
 }
 
 impl TransformSet {
@@ -52,6 +170,442 @@ impl TransformSet {
             TransformSet::Rotations => ROTATION_ONLY,
             TransformSet::AxisFlips => AXIS_FLIPS,
             TransformSet::HalfTurn => HALF_TURN_ONLY,
+            TransformSet::Custom(transforms) => transforms,
+        }
+    }
+
+    /// The largest symmetry group that leaves `material_key`'s positions unchanged as a set.
+    ///
+    /// - Pawnless, no same-colored bishop: the full dihedral group of 8 applies.
+    /// - Pawnless, with a same-colored bishop: only the 4 color-preserving transforms are safe,
+    ///   since `LightBishop`/`DarkBishop` are fixed by the material key and a color-swapping
+    ///   transform would move the piece into the other domain.
+    /// - Pawns present: only `FlipHorizontal` preserves the pawns' direction of travel, and then
+    ///   only if this material key's frozen pawn structure is itself invariant under it (and
+    ///   there's no same-colored bishop to worry about); otherwise no nontrivial symmetry exists.
+    ///
+    /// The bishop restriction costs real density: a bishop endgame only gets 4-fold reduction
+    /// where a bishopless one of the same shape would get 8-fold. Lifting it would mean, for
+    /// whichever transform canonicalization picks, relabeling a `LightBishop` group as
+    /// `DarkBishop` (or back) whenever that transform swaps square color — but
+    /// `MaterialKey::from_position` bakes a bishop's light/dark identity into the material key
+    /// itself, from the real position's square color, before any indexer exists to relabel it.
+    /// So for now the 4-fold reduction stands; see `KRvKBd`/`KBdNvKQ` roundtrip tests in
+    /// [`crate::position_indexer`] for the boundary this leaves in place.
+    pub fn for_material(material_key: &MaterialKey) -> TransformSet {
+        let has_bishop = material_key.pieces().any(|piece| piece.role.is_bishop());
+
+        if !material_key.pawns.occupied().is_empty() {
+            if !has_bishop && material_key.pawns.is_symmetric_horizontal() {
+                TransformSet::Custom(HORIZONTAL_TRANSFORMS)
+            } else {
+                TransformSet::Custom(IDENTITY_ONLY_TRANSFORMS)
+            }
+        } else if has_bishop {
+            TransformSet::Custom(COLOR_PRESERVING_TRANSFORMS)
+        } else {
+            TransformSet::Full
+        }
+    }
+
+    /// Canonicalize `pos` against this symmetry group: apply every transform in
+    /// [`Self::transforms`], and return whichever result packs to the lexicographically
+    /// smallest byte encoding, together with the `Transform` that produced it.
+    ///
+    /// `Transform::Identity` is always among the candidates, so a position that's already
+    /// canonical is returned unchanged. This is the core dedup step for tablebase index
+    /// generation: positions related by board symmetry share one table entry, so the generator
+    /// stores only the canonical form and the lookup path transforms the query in and the
+    /// result (a WDL/DTZ value, or a best move) back out via the returned `Transform`.
+    pub fn canonicalize<P>(self, pos: &P) -> (P, Transform)
+    where
+        P: Position + FromSetup + Clone,
+    {
+        self.transforms()
+            .iter()
+            .map(|&transform| (transform.apply_to_position(pos), transform))
+            .min_by_key(|(candidate, _)| {
+                packed_encoding(&candidate.clone().into_setup(EnPassantMode::Legal))
+            })
+            .expect("every TransformSet includes at least Identity")
+    }
+
+    /// Build the closed subgroup of D4 generated by `generators`: starting from `Identity`,
+    /// repeatedly compose every transform already in the set with each generator until no new
+    /// ones appear.
+    ///
+    /// D4 has only 8 elements, so this always terminates in a handful of passes. The result is
+    /// leaked to `'static` once, the same way the fixed [`Self::Custom`] sets built by
+    /// [`Self::for_material`] are `'static` consts — a subgroup of an 8-element group can never
+    /// hold more than 8 transforms, so the one-time leak is bounded and cheap.
+    pub fn from_generators(generators: &[Transform]) -> TransformSet {
+        let mut elements = vec![Transform::Identity];
+        loop {
+            let mut grew = false;
+            for a in elements.clone() {
+                for &b in generators {
+                    let composed = a.compose(b);
+                    if !elements.contains(&composed) {
+                        elements.push(composed);
+                        grew = true;
+                    }
+                }
+            }
+            if !grew {
+                break;
+            }
+        }
+        TransformSet::Custom(elements.leak())
+    }
+
+    /// Whether this set is closed under composition, i.e. a genuine subgroup of D4.
+    ///
+    /// `debug_assert!`s on failure, so a build run with debug assertions on (including
+    /// `cargo test`) immediately flags an incomplete hand-written [`Self::Custom`] set instead of
+    /// letting it silently mis-canonicalize positions; the bool is still returned so release
+    /// builds and callers can use it directly.
+    pub fn is_group(&self) -> bool {
+        let transforms = self.transforms();
+        let closed = transforms
+            .iter()
+            .all(|&a| transforms.iter().all(|&b| transforms.contains(&a.compose(b))));
+        debug_assert!(closed, "TransformSet is not closed under composition: {transforms:?}");
+        closed
+    }
+}
+
+/// The word-level counterpart of [`Transform::apply`]: a square at bit index `rank * 8 + file`
+/// (shakmaty's convention, also used by [`Transform::apply_square`]) moves to the same index
+/// [`Transform::apply`] would compute. `FlipVertical`/`Rotate180` are already constant-time in
+/// `shakmaty::Bitboard` (`swap_bytes`/`reverse_bits`); the rest build on a per-byte bit reversal
+/// (file mirror) and the standard chess-programming `flipDiagA1H8` delta-swap (main-diagonal
+/// mirror), composing them for the remaining rotations and the anti-diagonal mirror.
+fn transform_word(transform: Transform, x: u64) -> u64 {
+    match transform {
+        Transform::Identity => x,
+        Transform::FlipVertical => x.swap_bytes(),
+        Transform::FlipHorizontal => mirror_bits_per_byte(x),
+        Transform::Rotate180 => x.reverse_bits(),
+        Transform::MirrorMain => flip_main_diagonal(x),
+        Transform::MirrorAnti => flip_main_diagonal(x.reverse_bits()),
+        Transform::Rotate90 => flip_main_diagonal(x).swap_bytes(),
+        Transform::Rotate270 => mirror_bits_per_byte(flip_main_diagonal(x)),
+    }
+}
+
+/// Reverse the bit order within each byte (the file axis), leaving byte order (the rank axis)
+/// untouched — the standard 3-step `0x55`/`0x33`/`0x0f` per-byte bit-reversal.
+fn mirror_bits_per_byte(x: u64) -> u64 {
+    let x = ((x & 0x5555555555555555) << 1) | ((x >> 1) & 0x5555555555555555);
+    let x = ((x & 0x3333333333333333) << 2) | ((x >> 2) & 0x3333333333333333);
+    ((x & 0x0f0f0f0f0f0f0f0f) << 4) | ((x >> 4) & 0x0f0f0f0f0f0f0f0f)
+}
+
+/// Flip along the a1-h8 diagonal (swap file and rank): the standard chess-programming
+/// `flipDiagA1H8` delta-swap.
+fn flip_main_diagonal(mut x: u64) -> u64 {
+    let mut t = (x ^ (x >> 7)) & 0x00AA00AA00AA00AA;
+    x ^= t ^ (t << 7);
+    t = (x ^ (x >> 14)) & 0x0000CCCC0000CCCC;
+    x ^= t ^ (t << 14);
+    t = (x ^ (x >> 28)) & 0x00000000F0F0F0F0;
+    x ^= t ^ (t << 28);
+    x
+}
+
+/// Apply a symmetry to every square-dependent field of a [`Setup`]. None of [`Transform`]'s
+/// eight symmetries swap color, so the side to move is left untouched.
+fn transform_setup(setup: &Setup, transform: Transform) -> Setup {
+    let mut transformed = Setup::empty();
+    transformed.turn = setup.turn;
+    transformed.ep_square = setup.ep_square.map(|square| transform.apply_square(square));
+    transformed.castling_rights = setup
+        .castling_rights
+        .into_iter()
+        .fold(Bitboard::EMPTY, |acc, square| {
+            acc | Bitboard::from_square(transform.apply_square(square))
+        });
+    for square in Square::ALL {
+        if let Some(piece) = setup.board.piece_at(square) {
+            transformed
+                .board
+                .set_piece_at(transform.apply_square(square), piece);
+        }
+    }
+    transformed
+}
+
+/// Pack a `Setup`'s board, side to move, en-passant target and castling rights into bytes, used
+/// only to break ties deterministically between transforms in [`TransformSet::canonicalize`].
+fn packed_encoding(setup: &Setup) -> [u8; 74] {
+    let mut bytes = [0u8; 74];
+    for square in Square::ALL {
+        bytes[square.to_usize()] = setup.board.piece_at(square).map_or(0, piece_byte);
+    }
+    bytes[64] = match setup.turn {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    bytes[65] = setup.ep_square.map_or(0xff, |square| square.file() as u8);
+    let castling_bits = setup
+        .castling_rights
+        .into_iter()
+        .fold(0u64, |acc, square| acc | (1u64 << square.to_usize()));
+    bytes[66..74].copy_from_slice(&castling_bits.to_be_bytes());
+    bytes
+}
+
+fn piece_byte(piece: Piece) -> u8 {
+    let role = match piece.role {
+        Role::Pawn => 0,
+        Role::Knight => 1,
+        Role::Bishop => 2,
+        Role::Rook => 3,
+        Role::Queen => 4,
+        Role::King => 5,
+    };
+    let color = match piece.color {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    1 + role * 2 + color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_leaves_coordinates_unchanged() {
+        assert_eq!(Transform::Identity.apply(3, 5, 8), (3, 5));
+    }
+
+    #[test]
+    fn flip_horizontal_mirrors_the_file() {
+        assert_eq!(Transform::FlipHorizontal.apply(0, 5, 8), (7, 5));
+    }
+
+    #[test]
+    fn rotate90_matches_the_documented_coordinate_math() {
+        assert_eq!(Transform::Rotate90.apply(2, 3, 8), (3, 5));
+    }
+
+    #[test]
+    fn mirror_main_swaps_file_and_rank() {
+        assert_eq!(Transform::MirrorMain.apply(2, 6, 8), (6, 2));
+    }
+
+    #[test]
+    fn apply_square_agrees_with_apply() {
+        assert_eq!(Transform::Rotate180.apply_square(Square::A1), Square::H8);
+    }
+
+    #[test]
+    fn apply_squares_maps_every_square_in_order() {
+        let squares = [Square::A1, Square::H1, Square::A8];
+        let mapped = Transform::FlipHorizontal.apply_squares(squares);
+        assert_eq!(mapped, vec![Square::H1, Square::A1, Square::H8]);
+    }
+
+    #[test]
+    fn compose_matches_known_d4_identities() {
+        assert_eq!(
+            Transform::Rotate90.compose(Transform::Rotate90),
+            Transform::Rotate180
+        );
+        assert_eq!(
+            Transform::FlipHorizontal.compose(Transform::FlipHorizontal),
+            Transform::Identity
+        );
+        assert_eq!(
+            Transform::Rotate90.compose(Transform::FlipHorizontal),
+            Transform::MirrorMain
+        );
+        assert_eq!(
+            Transform::Identity.compose(Transform::MirrorMain),
+            Transform::MirrorMain
+        );
+    }
+
+    #[test]
+    fn compose_agrees_with_applying_both_transforms_in_order() {
+        for &outer in ALL_TRANSFORMS {
+            for &inner in ALL_TRANSFORMS {
+                let composed = outer.compose(inner);
+                for square in Square::ALL {
+                    let direct = outer.apply_square(inner.apply_square(square));
+                    assert_eq!(composed.apply_square(square), direct);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_transform_has_an_inverse_that_composes_to_identity() {
+        for &transform in ALL_TRANSFORMS {
+            let inverse = transform.inverse();
+            assert_eq!(transform.compose(inverse), Transform::Identity);
+            assert_eq!(inverse.compose(transform), Transform::Identity);
+        }
+    }
+
+    #[test]
+    fn canonicalize_finds_the_packed_minimum_among_all_transforms() {
+        use shakmaty::{CastlingMode, Chess, EnPassantMode, fen::Fen};
+
+        let position = "8/8/8/8/3p4/8/4k3/4K2R w K - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let setup = position.clone().into_setup(EnPassantMode::Legal);
+
+        let expected_min = ALL_TRANSFORMS
+            .iter()
+            .map(|&transform| packed_encoding(&transform_setup(&setup, transform)))
+            .min()
+            .unwrap();
+
+        let (canonical, _) = TransformSet::Full.canonicalize(&position);
+        assert_eq!(
+            packed_encoding(&canonical.into_setup(EnPassantMode::Legal)),
+            expected_min
+        );
+    }
+
+    #[test]
+    fn canonicalize_is_invariant_under_the_chosen_symmetry_group() {
+        use shakmaty::{CastlingMode, Chess, EnPassantMode, fen::Fen};
+
+        let position = "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let mirrored = "3k4/8/8/8/8/8/8/3K3R w K - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+
+        let (canonical, _) = TransformSet::Full.canonicalize(&position);
+        let (canonical_mirrored, _) = TransformSet::Full.canonicalize(&mirrored);
+
+        assert_eq!(
+            packed_encoding(&canonical.into_setup(EnPassantMode::Legal)),
+            packed_encoding(&canonical_mirrored.into_setup(EnPassantMode::Legal))
+        );
+    }
+
+    #[test]
+    fn for_material_grants_full_symmetry_to_pawnless_bishopless_keys() {
+        let key = MaterialKey::from_string("KRvKR").unwrap();
+        assert_eq!(TransformSet::for_material(&key), TransformSet::Full);
+    }
+
+    #[test]
+    fn for_material_restricts_pawnless_bishop_keys_to_color_preserving_transforms() {
+        let key = MaterialKey::from_string("KBlvK").unwrap();
+        assert_eq!(
+            TransformSet::for_material(&key).transforms(),
+            &[
+                Transform::Identity,
+                Transform::Rotate180,
+                Transform::MirrorMain,
+                Transform::MirrorAnti
+            ]
+        );
+    }
+
+    #[test]
+    fn for_material_restricts_symmetric_pawn_structures_to_horizontal_flip() {
+        let key = MaterialKey::from_string("Ka2h2vK").unwrap();
+        assert_eq!(
+            TransformSet::for_material(&key).transforms(),
+            &[Transform::Identity, Transform::FlipHorizontal]
+        );
+    }
+
+    #[test]
+    fn for_material_forbids_every_transform_for_asymmetric_pawn_structures() {
+        let key = MaterialKey::from_string("Ka2vK").unwrap();
+        assert_eq!(
+            TransformSet::for_material(&key).transforms(),
+            &[Transform::Identity]
+        );
+    }
+
+    #[test]
+    fn canonicalize_always_offers_identity_as_a_candidate() {
+        use shakmaty::{Chess, EnPassantMode};
+
+        // A half turn keeps piece colors, so the rotated starting position puts a black rook on
+        // a1 where the original has a white one and packs larger; `Identity` is a candidate, so
+        // the canonical form must pack identically to the original.
+        let position = Chess::default();
+        let identity_encoding = packed_encoding(&position.clone().into_setup(EnPassantMode::Legal));
+
+        let (canonical, _) = TransformSet::HalfTurn.canonicalize(&position);
+        let canonical_encoding = packed_encoding(&canonical.into_setup(EnPassantMode::Legal));
+
+        assert_eq!(canonical_encoding, identity_encoding);
+    }
+
+    #[test]
+    fn from_generators_of_a_reflection_and_a_rotation_closes_the_full_group() {
+        let set = TransformSet::from_generators(&[Transform::FlipHorizontal, Transform::Rotate90]);
+        let transforms = set.transforms();
+
+        assert_eq!(transforms.len(), ALL_TRANSFORMS.len());
+        assert!(ALL_TRANSFORMS.iter().all(|t| transforms.contains(t)));
+    }
+
+    #[test]
+    fn from_generators_of_a_single_involution_yields_an_order_two_subgroup() {
+        let set = TransformSet::from_generators(&[Transform::FlipVertical]);
+        assert_eq!(
+            set.transforms(),
+            &[Transform::Identity, Transform::FlipVertical]
+        );
+    }
+
+    #[test]
+    fn from_generators_with_no_generators_yields_the_trivial_group() {
+        let set = TransformSet::from_generators(&[]);
+        assert_eq!(set.transforms(), &[Transform::Identity]);
+    }
+
+    #[test]
+    fn is_group_accepts_every_built_in_set() {
+        assert!(TransformSet::Full.is_group());
+        assert!(TransformSet::Rotations.is_group());
+        assert!(TransformSet::AxisFlips.is_group());
+        assert!(TransformSet::HalfTurn.is_group());
+        assert!(TransformSet::Custom(COLOR_PRESERVING_TRANSFORMS).is_group());
+        assert!(TransformSet::Custom(HORIZONTAL_TRANSFORMS).is_group());
+        assert!(TransformSet::Custom(IDENTITY_ONLY_TRANSFORMS).is_group());
+    }
+
+    #[test]
+    #[should_panic(expected = "not closed under composition")]
+    fn is_group_panics_in_debug_builds_on_a_set_that_is_not_closed() {
+        let broken = TransformSet::Custom(&[Transform::Identity, Transform::Rotate90]);
+        broken.is_group();
+    }
+
+    #[test]
+    fn apply_bitboard_agrees_with_apply_squares() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let bitboard = Bitboard(rng.gen_range(0..=u64::MAX));
+            for &transform in ALL_TRANSFORMS {
+                let via_word = transform.apply_bitboard(bitboard);
+                let via_squares: Bitboard =
+                    transform.apply_squares(bitboard).into_iter().collect();
+                assert_eq!(via_word, via_squares, "{transform:?} on {bitboard:?}");
+            }
         }
     }
 }
@@ -0,0 +1,127 @@
+//! A blocked/tiled matrix transpose for reordering a table's position array in memory.
+//!
+//! [`crate::position_indexer::PositionIndexer`] lays a table out with the leading piece's
+//! square (this material key's white king) as the fast-growing digit and every other piece's
+//! arrangement as the slow-growing one (see its doc comment). Batched sibling probing — e.g.
+//! `TableBuilder::evaluate_move` scoring every legal move from one node — mostly varies a
+//! *non*-leading piece's square while the rest of the position stays fixed, which strides
+//! through a row-major table by [`TableLayout::Transposed`]'s whole leading dimension per probe
+//! instead of walking contiguous memory. [`TableLayout::Transposed`] swaps the two axes so that
+//! dimension is contiguous instead.
+
+/// Number of elements per tile side. Chosen so a `TILE x TILE` block of `T` comfortably fits in
+/// a typical L1 data cache alongside the destination tile being written, so neither tile is
+/// evicted mid-copy the way a naive element-by-element transpose would thrash on large,
+/// non-square dimensions.
+const TILE: usize = 32;
+
+/// Transpose a `rows x cols` row-major matrix into a `cols x rows` row-major matrix.
+///
+/// Processes the matrix in `TILE x TILE` blocks, swapping block `(i, j)` with block `(j, i)`,
+/// rather than copying element-by-element: every source and destination tile stays cache-
+/// resident for the whole block instead of being re-fetched on every single element like a
+/// naive transpose would for large, non-square `rows`/`cols`.
+pub(crate) fn transpose_blocked<T: Copy>(data: &[T], rows: usize, cols: usize) -> Vec<T> {
+    assert_eq!(data.len(), rows * cols, "data doesn't match rows * cols");
+
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let mut out = vec![data[0]; data.len()];
+
+    let mut row_block = 0;
+    while row_block < rows {
+        let row_end = (row_block + TILE).min(rows);
+        let mut col_block = 0;
+        while col_block < cols {
+            let col_end = (col_block + TILE).min(cols);
+            for row in row_block..row_end {
+                for col in col_block..col_end {
+                    out[col * rows + row] = data[row * cols + col];
+                }
+            }
+            col_block += TILE;
+        }
+        row_block += TILE;
+    }
+
+    out
+}
+
+/// Physical storage order for a table's position array, relative to
+/// [`crate::position_indexer::PositionIndexer`]'s logical index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableLayout {
+    /// One entry per logical index, in `PositionIndexer`'s own digit order — the layout every
+    /// table has always used.
+    #[default]
+    RowMajor,
+    /// The same positions, with the (leading-piece-square, remaining-index) axes swapped, so
+    /// that holding the leading square fixed and scanning every other piece's arrangement —
+    /// what batched sibling probing from one node does — reads contiguous memory.
+    Transposed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpose_matches_naive_reference_for_non_square_dims() {
+        let rows = 5;
+        let cols = 3;
+        let data: Vec<u32> = (0..(rows * cols) as u32).collect();
+
+        let transposed = transpose_blocked(&data, rows, cols);
+
+        let mut expected = vec![0u32; rows * cols];
+        for row in 0..rows {
+            for col in 0..cols {
+                expected[col * rows + row] = data[row * cols + col];
+            }
+        }
+        assert_eq!(transposed, expected);
+    }
+
+    #[test]
+    fn transpose_is_its_own_inverse_up_to_swapped_dims() {
+        let rows = 7;
+        let cols = 11;
+        let data: Vec<u32> = (0..(rows * cols) as u32).collect();
+
+        let transposed = transpose_blocked(&data, rows, cols);
+        let round_tripped = transpose_blocked(&transposed, cols, rows);
+
+        assert_eq!(round_tripped, data);
+    }
+
+    #[test]
+    fn transpose_handles_dimensions_smaller_than_a_tile() {
+        let data = [1u8, 2, 3, 4, 5, 6];
+        assert_eq!(transpose_blocked(&data, 2, 3), vec![1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn transpose_handles_dimensions_larger_than_a_tile() {
+        let rows = TILE * 2 + 3;
+        let cols = TILE + 5;
+        let data: Vec<u32> = (0..(rows * cols) as u32).collect();
+
+        let transposed = transpose_blocked(&data, rows, cols);
+
+        let mut expected = vec![0u32; rows * cols];
+        for row in 0..rows {
+            for col in 0..cols {
+                expected[col * rows + row] = data[row * cols + col];
+            }
+        }
+        assert_eq!(transposed, expected);
+    }
+
+    #[test]
+    fn transpose_handles_empty_input() {
+        let data: [u8; 0] = [];
+        assert_eq!(transpose_blocked(&data, 0, 0), Vec::<u8>::new());
+    }
+}
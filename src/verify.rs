@@ -0,0 +1,234 @@
+use crate::material_key::MaterialKey;
+use crate::mt19937::Mt19937;
+use crate::position_indexer::PositionIndexer;
+use crate::prober::{ProbeError, Prober};
+use crate::score::DtzScore;
+use crate::transform::{Transform, TransformSet};
+use shakmaty::{Chess, EnPassantMode, Position, fen::Fen};
+
+/// Why [`verify_sampled`] rejected a position in a freshly built table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// Probing `fen` itself failed, e.g. a sibling table the check needed isn't present.
+    Probe { fen: String, error: ProbeError },
+    /// `fen`'s own probed score doesn't match the negamax of its best successor's score.
+    NegamaxMismatch {
+        fen: String,
+        expected: DtzScore,
+        actual: DtzScore,
+    },
+    /// `fen`'s score changed under `transform`, which [`TransformSet::for_material`] says
+    /// should leave every position of this material invariant.
+    TransformMismatch {
+        fen: String,
+        transform: Transform,
+        expected: DtzScore,
+        actual: DtzScore,
+    },
+}
+
+/// Cross-check a freshly built table without re-running retrograde analysis: draw `n`
+/// pseudo-random legal positions of `key`'s material from a seeded [`Mt19937`] (so a run is
+/// reproducible across machines given the same `seed`), and for each confirm
+///
+/// - its probed score equals the negamax of its best successor's score ([`Prober::best_move`]
+///   already computes that negamax while picking a move, so this just compares the two), and
+/// - its probed score is unchanged under every transform [`TransformSet::for_material`] allows
+///   for this material.
+///
+/// Stops at the first [`VerificationFailure`] found, carrying the offending position's FEN, so
+/// a mismatch is debuggable without re-deriving which of the `n` samples failed.
+pub fn verify_sampled(
+    prober: &mut Prober,
+    key: &MaterialKey,
+    seed: u32,
+    n: usize,
+) -> Result<(), VerificationFailure> {
+    let indexer = PositionIndexer::new(key.clone());
+    // Not every index decodes to a legal position (e.g. adjacent kings), so sample from the
+    // legal ones directly rather than rejection-sampling raw indices, matching
+    // `collect_valid_indices` in `cli::run_check_against_syzygy`.
+    let valid_indices: Vec<usize> = (0..indexer.total_positions())
+        .filter(|&index| indexer.index_to_position(index).is_ok())
+        .collect();
+    if valid_indices.is_empty() {
+        return Ok(());
+    }
+
+    let transforms = TransformSet::for_material(key).transforms();
+    let mut rng = Mt19937::new(seed);
+
+    for _ in 0..n {
+        let index = valid_indices[rng.next_below(valid_indices.len() as u64) as usize];
+        let position = indexer
+            .index_to_position(index)
+            .expect("index was filtered to decode successfully above");
+
+        verify_negamax(prober, &position)?;
+        for &transform in transforms {
+            verify_transform_invariance(prober, &position, transform)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn fen_of(position: &Chess) -> String {
+    Fen::from_position(position, EnPassantMode::Legal).to_string()
+}
+
+fn verify_negamax(prober: &mut Prober, position: &Chess) -> Result<(), VerificationFailure> {
+    let actual = prober
+        .probe_dtz(position)
+        .map_err(|error| VerificationFailure::Probe {
+            fen: fen_of(position),
+            error,
+        })?;
+
+    let expected = match prober
+        .best_move(position)
+        .map_err(|error| VerificationFailure::Probe {
+            fen: fen_of(position),
+            error,
+        })? {
+        Some((_, dtz)) => dtz,
+        None if position.is_checkmate() => DtzScore::immediate_loss(),
+        None => DtzScore::draw(),
+    };
+
+    if actual != expected {
+        return Err(VerificationFailure::NegamaxMismatch {
+            fen: fen_of(position),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn verify_transform_invariance(
+    prober: &mut Prober,
+    position: &Chess,
+    transform: Transform,
+) -> Result<(), VerificationFailure> {
+    let expected = prober
+        .probe_dtz(position)
+        .map_err(|error| VerificationFailure::Probe {
+            fen: fen_of(position),
+            error,
+        })?;
+
+    let transformed = transform.apply_to_position(position);
+    let actual = prober
+        .probe_dtz(&transformed)
+        .map_err(|error| VerificationFailure::Probe {
+            fen: fen_of(&transformed),
+            error,
+        })?;
+
+    if actual != expected {
+        return Err(VerificationFailure::TransformMismatch {
+            fen: fen_of(position),
+            transform,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table_builder::TableBuilder;
+    use crate::wdl_file::write_wdl_file;
+    use crate::wdl_score_range::WdlScoreRange;
+    use crate::wdl_table::WdlTable;
+    use shakmaty::{CastlingMode, fen::Fen};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_data_dir(prefix: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("heisenbase_verify_{prefix}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn verify_sampled_accepts_a_correctly_solved_table() {
+        let data_dir = temp_data_dir("kqvk_ok");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+
+        let mut tb = TableBuilder::new_with_data_dir(material.clone(), &data_dir);
+        tb.solve();
+        let wdl_table = WdlTable::from(tb);
+        write_wdl_file(data_dir.join("KQvK.hbt"), &wdl_table).unwrap();
+
+        let mut prober = Prober::new(&data_dir);
+        assert_eq!(verify_sampled(&mut prober, &material, 42, 100), Ok(()));
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn verify_sampled_is_deterministic_across_runs_with_the_same_seed() {
+        let data_dir = temp_data_dir("kqvk_repeatable");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+
+        let mut tb = TableBuilder::new_with_data_dir(material.clone(), &data_dir);
+        tb.solve();
+        let wdl_table = WdlTable::from(tb);
+        write_wdl_file(data_dir.join("KQvK.hbt"), &wdl_table).unwrap();
+
+        let mut first = Prober::new(&data_dir);
+        let mut second = Prober::new(&data_dir);
+        assert_eq!(
+            verify_sampled(&mut first, &material, 7, 20),
+            verify_sampled(&mut second, &material, 7, 20)
+        );
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn verify_negamax_detects_a_corrupted_child_entry() {
+        let data_dir = temp_data_dir("negamax_mismatch");
+        let material = MaterialKey::from_string("KRvK").unwrap();
+
+        let mut tb = TableBuilder::new_with_data_dir(material.clone(), &data_dir);
+        tb.solve();
+        let mut wdl_table = WdlTable::from(tb);
+
+        let position: Chess = "7k/8/5K2/8/8/8/8/7R b - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        // Black is in check from the rook along the h-file. Every escape square but g8 is
+        // either still attacked along that file or adjacent to the white king, so there's
+        // exactly one legal reply — the negamax recomputation below is driven entirely by
+        // that one child, with no other move able to mask the corruption.
+        let legal_moves = position.legal_moves();
+        assert_eq!(legal_moves.len(), 1);
+
+        let mut child = position.clone();
+        child.play_unchecked(legal_moves[0].clone());
+
+        let indexer = PositionIndexer::new(material.clone());
+        let child_idx = indexer.position_to_index(&child).unwrap();
+        wdl_table.positions[child_idx] = match wdl_table.positions[child_idx] {
+            WdlScoreRange::Draw => WdlScoreRange::Win,
+            _ => WdlScoreRange::Draw,
+        };
+
+        write_wdl_file(data_dir.join("KRvK.hbt"), &wdl_table).unwrap();
+
+        let mut prober = Prober::new(&data_dir);
+        let failure = verify_negamax(&mut prober, &position).unwrap_err();
+        assert!(matches!(failure, VerificationFailure::NegamaxMismatch { .. }));
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+//! Bit-packed on-disk format for [`WdlTable`].
+//!
+//! [`WdlTable::positions`] stores one [`WdlScoreRange`] per indexed position, which costs a full
+//! byte (or more, depending on the in-memory enum layout) per entry even though the type only
+//! has nine distinct states. [`WdlScoreRange`]'s own discriminants already enumerate those
+//! states densely as `0..=8` (see the comment on that enum), so packing just means writing each
+//! position's discriminant with the minimum fixed bit width instead of a whole byte, rather than
+//! inventing a second parallel code assignment.
+//!
+//! This is a plain fixed-width pack, not the adaptive pair-substitution/Huffman scheme in
+//! [`crate::compression`] used by `.hbt`: no per-table code tables to build, at the cost of a
+//! worse compression ratio than that scheme typically achieves.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::material_key::MaterialKey;
+use crate::wdl_score_range::WdlScoreRange;
+use crate::wdl_table::WdlTable;
+use crate::zobrist;
+
+const MAGIC: &[u8; 4] = b"HBPK";
+const VERSION: u8 = 1;
+
+/// Number of distinct [`WdlScoreRange`] states; keep in sync with that enum's variant count.
+const NUM_STATES: u32 = 9;
+
+/// Bits needed to give `states` distinct values a dense fixed-width code, i.e. `ceil(log2(states))`.
+const fn bits_needed(states: u32) -> u8 {
+    let mut bits = 0u8;
+    while (1u32 << bits) < states {
+        bits += 1;
+    }
+    bits
+}
+
+/// Fixed width of a packed [`WdlScoreRange`] code, computed once from [`NUM_STATES`].
+///
+/// `pub(crate)` so [`crate::wdl_mmap`]'s block-structured format can pack each of its blocks at
+/// the same width without duplicating this computation.
+pub(crate) const BIT_WIDTH: u8 = bits_needed(NUM_STATES);
+
+/// Big-endian bit accumulator: appends bits MSB-first into a byte, flushing it into `bytes`
+/// once it fills up. `pub(crate)` so [`crate::wdl_mmap`] can pack each of its blocks
+/// independently with the same accumulator.
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    next: u8,
+    nextbits: u8,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            next: 0,
+            nextbits: 0,
+        }
+    }
+
+    /// Append the low `n` bits of `value`, most-significant bit first.
+    pub(crate) fn write_bits(&mut self, value: u32, n: u8) {
+        for i in (0..n).rev() {
+            let bit = ((value >> i) & 1) as u8;
+            self.next = (self.next << 1) | bit;
+            self.nextbits += 1;
+            if self.nextbits == 8 {
+                self.bytes.push(self.next);
+                self.next = 0;
+                self.nextbits = 0;
+            }
+        }
+    }
+
+    /// Pad any partial final byte with zeros and return the accumulated bytes.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.nextbits > 0 {
+            self.next <<= 8 - self.nextbits;
+            self.bytes.push(self.next);
+        }
+        self.bytes
+    }
+}
+
+/// Reads bits written by [`BitWriter`], MSB-first, from a byte slice. `pub(crate)` so
+/// [`crate::wdl_mmap`] can decode a single mmapped block without pulling in a whole file's
+/// worth of bytes first.
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    next: u8,
+    nextbits: u8,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            next: 0,
+            nextbits: 0,
+        }
+    }
+
+    /// Read the next `n` bits into the low bits of the result, most-significant bit first.
+    pub(crate) fn read_bits(&mut self, n: u8) -> io::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            if self.nextbits == 0 {
+                let byte = *self
+                    .bytes
+                    .get(self.pos)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated bitstream"))?;
+                self.pos += 1;
+                self.next = byte;
+                self.nextbits = 8;
+            }
+            let bit = (self.next >> 7) & 1;
+            self.next <<= 1;
+            self.nextbits -= 1;
+            value = (value << 1) | bit as u32;
+        }
+        Ok(value)
+    }
+}
+
+/// Fold a material key and its table's storage values into a checksum stored in the file
+/// header, mirroring [`crate::dtz_file`]'s integrity check.
+fn packed_checksum(material: &MaterialKey, positions: &[WdlScoreRange]) -> u64 {
+    let mut bytes = material.to_string().into_bytes();
+    bytes.extend(positions.iter().map(|&score| u8::from(score)));
+    zobrist::checksum(&bytes)
+}
+
+/// Write `table` in the bit-packed format: magic, version, material key, integrity checksum,
+/// position count, bit width, then every position's [`WdlScoreRange`] discriminant packed at
+/// that width, byte-aligned and zero-padded at the end.
+pub fn write_packed_wdl_file<P: AsRef<Path>>(path: P, table: &WdlTable) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    file.write_all(MAGIC)?;
+    file.write_all(&[VERSION])?;
+
+    let mk_string = table.material.to_string();
+    file.write_all(&[mk_string.len() as u8])?;
+    file.write_all(mk_string.as_bytes())?;
+
+    let checksum = packed_checksum(&table.material, &table.positions);
+    file.write_all(&checksum.to_le_bytes())?;
+
+    file.write_all(&(table.positions.len() as u64).to_le_bytes())?;
+    file.write_all(&[BIT_WIDTH])?;
+
+    let mut writer = BitWriter::new();
+    for &score in &table.positions {
+        writer.write_bits(u8::from(score) as u32, BIT_WIDTH);
+    }
+    let payload = writer.finish();
+
+    file.write_all(&(payload.len() as u64).to_le_bytes())?;
+    file.write_all(&payload)?;
+
+    Ok(())
+}
+
+/// Read a table written by [`write_packed_wdl_file`].
+pub fn read_packed_wdl_file<P: AsRef<Path>>(path: P) -> io::Result<WdlTable> {
+    let mut file = File::open(path)?;
+
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
+    }
+
+    let mut version = [0u8; 1];
+    file.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported version",
+        ));
+    }
+
+    let mut mk_len = [0u8; 1];
+    file.read_exact(&mut mk_len)?;
+    let mk_len = mk_len[0] as usize;
+    let mut mk_bytes = vec![0u8; mk_len];
+    file.read_exact(&mut mk_bytes)?;
+    let mk_string = String::from_utf8(mk_bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+    let material = MaterialKey::from_string(&mk_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+
+    let mut buf8 = [0u8; 8];
+    file.read_exact(&mut buf8)?;
+    let checksum = u64::from_le_bytes(buf8);
+
+    file.read_exact(&mut buf8)?;
+    let total_positions = u64::from_le_bytes(buf8) as usize;
+
+    let mut bit_width = [0u8; 1];
+    file.read_exact(&mut bit_width)?;
+    let bit_width = bit_width[0];
+
+    file.read_exact(&mut buf8)?;
+    let payload_len = u64::from_le_bytes(buf8) as usize;
+    let mut payload = vec![0u8; payload_len];
+    file.read_exact(&mut payload)?;
+
+    let mut reader = BitReader::new(&payload);
+    let mut positions = Vec::with_capacity(total_positions);
+    for _ in 0..total_positions {
+        let code = reader.read_bits(bit_width)?;
+        let score = WdlScoreRange::try_from(code as u8)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid packed WDL code"))?;
+        positions.push(score);
+    }
+
+    if packed_checksum(&material, &positions) != checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch: file is corrupted or its header doesn't match its content",
+        ));
+    }
+
+    Ok(WdlTable {
+        material,
+        positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_path(prefix: &str) -> std::path::PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after UNIX_EPOCH")
+            .as_nanos();
+        std::env::temp_dir().join(format!("heisenbase_{prefix}_{unique}.hbk"))
+    }
+
+    fn sample_table(len: usize) -> WdlTable {
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let states = [
+            WdlScoreRange::Unknown,
+            WdlScoreRange::WinOrDraw,
+            WdlScoreRange::DrawOrLoss,
+            WdlScoreRange::Win,
+            WdlScoreRange::Draw,
+            WdlScoreRange::Loss,
+            WdlScoreRange::IllegalPosition,
+            WdlScoreRange::CursedWin,
+            WdlScoreRange::BlessedLoss,
+        ];
+        let positions = (0..len).map(|i| states[i % states.len()]).collect();
+        WdlTable {
+            material,
+            positions,
+        }
+    }
+
+    #[test]
+    fn bit_width_is_four_for_nine_states() {
+        assert_eq!(BIT_WIDTH, 4);
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        let path = temp_path("round_trip");
+        let table = sample_table(2000);
+
+        write_packed_wdl_file(&path, &table).unwrap();
+        let read_back = read_packed_wdl_file(&path).unwrap();
+
+        assert_eq!(read_back.material, table.material);
+        assert_eq!(read_back.positions, table.positions);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn round_trip_survives_a_count_not_a_multiple_of_two() {
+        // 3 positions at 4 bits each is 12 bits, spilling into a padded final byte; make sure
+        // the reader stops after exactly 3 codes instead of reading the padding as a 4th.
+        let path = temp_path("odd_count");
+        let table = sample_table(3);
+
+        write_packed_wdl_file(&path, &table).unwrap();
+        let read_back = read_packed_wdl_file(&path).unwrap();
+
+        assert_eq!(read_back.positions, table.positions);
+        assert_eq!(read_back.positions.len(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_packed_wdl_file_rejects_bad_magic() {
+        let path = temp_path("bad_magic");
+        std::fs::write(&path, b"BAD!").unwrap();
+
+        let result = read_packed_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn read_packed_wdl_file_rejects_checksum_mismatch() {
+        let path = temp_path("checksum_mismatch");
+        let table = sample_table(500);
+        write_packed_wdl_file(&path, &table).unwrap();
+
+        let mk_len = table.material.to_string().len();
+        let checksum_offset = 4 + 1 + 1 + mk_len;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[checksum_offset] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = read_packed_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn packs_to_four_bits_per_position() {
+        let path = temp_path("four_bits");
+        let table = sample_table(1000);
+
+        write_packed_wdl_file(&path, &table).unwrap();
+        let on_disk = std::fs::metadata(&path).unwrap().len() as usize;
+        std::fs::remove_file(&path).unwrap();
+
+        // Header overhead is small and fixed; the 1000-position payload should need ~500 bytes
+        // (4 bits each) rather than 1000+ for a byte-per-position encoding.
+        assert!(
+            on_disk < 600,
+            "expected roughly 4 bits/position, got {on_disk} bytes for 1000 positions"
+        );
+    }
+}
@@ -1,15 +1,75 @@
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use crate::compression::{
+    CompressedWdl, CompressionScheme, DeflateMode, compress_wdl_with_scheme, decompress_wdl, probe,
+};
 use crate::material_key::MaterialKey;
+use crate::transform::{ALL_TRANSFORMS, Transform, TransformSet};
+use crate::wdl_score_range::WdlScoreRange;
 use crate::wdl_table::WdlTable;
+use crate::zobrist;
 
 const MAGIC: &[u8; 4] = b"HBWD";
-const VERSION: u8 = 1;
+// Version 2 was the first on-disk format this crate ever shipped (block-compressed via
+// `compress_wdl`), so there was never a legacy one-byte-per-position v1 to dispatch on.
+// Version 3 added the integrity checksum above. Version 4 adds the `mask_unknown` flag to
+// each serialized compressed block. Version 5 replaces the flat pair-substitution fields with
+// a tagged `CompressionScheme`, so a block can also be stored as an `Lz77` back-reference
+// encoding. Version 6 adds a CRC-32 to each serialized block, checked in `deserialize_compressed`
+// before any decoding runs, so corruption is caught without relying on the whole-table checksum
+// above (which only ever sees already-decompressed positions). Version 7 records the list of
+// `Transform`s actually applied when canonicalizing this material (see `allowed_transforms`
+// below), so a reader can confirm it agrees with what `TransformSet::for_material` computes
+// today instead of silently trusting a stale assumption baked in at write time.
+const VERSION: u8 = 7;
 
-/// Write a compressed WDL table to a file.
+/// Number of positions compressed together as one unit.
+///
+/// Each block is compressed independently, so [`WdlFileReader::probe_index`] only has to
+/// walk into this many positions' worth of compressed bitstream to answer a single query,
+/// rather than the whole table.
+const BLOCK_SIZE: usize = 4096;
+
+/// Maximum number of compressed blocks kept in [`WdlFileReader`]'s cache.
+const BLOCK_CACHE_CAPACITY: usize = 16;
+
+/// Fold a material key and its table's positions into a checksum stored in the file header,
+/// so [`read_wdl_file`] can detect a corrupted file or one whose header no longer matches
+/// its content.
+fn wdl_table_checksum(material: &MaterialKey, positions: &[WdlScoreRange]) -> u64 {
+    let mut bytes = material.to_string().into_bytes();
+    bytes.extend(positions.iter().map(|&value| u8::from(value)));
+    zobrist::checksum(&bytes)
+}
+
+/// Write a block-compressed WDL table to a file.
+///
+/// Equivalent to [`write_wdl_file_with_options`] with `mask_unknown: false`, which preserves
+/// every `Unknown` position exactly as stored (the right choice whenever a table might still
+/// have genuinely-unresolved positions, e.g. one generated with missing child materials).
 pub fn write_wdl_file<P: AsRef<Path>>(path: P, wdl_table: &WdlTable) -> io::Result<()> {
+    write_wdl_file_with_options(path, wdl_table, false)
+}
+
+/// Write a block-compressed WDL table to a file.
+///
+/// The positions are split into fixed-size blocks, each compressed independently with
+/// [`compress_wdl_with_scheme`] (picking whichever of pair substitution or LZ77 compresses
+/// smaller), and preceded by a block-offset index so that [`WdlFileReader`] can seek directly
+/// to, and decompress, a single block without touching the rest of the file.
+///
+/// Set `mask_unknown` only when the caller knows every `Unknown` position really is free to
+/// rewrite, e.g. a table built with no missing child materials, where retrograde analysis
+/// should have resolved every legal position and any surviving `Unknown` carries no information
+/// worth preserving.
+pub fn write_wdl_file_with_options<P: AsRef<Path>>(
+    path: P,
+    wdl_table: &WdlTable,
+    mask_unknown: bool,
+) -> io::Result<()> {
     let mut file = File::create(path)?;
 
     // Header
@@ -21,25 +81,96 @@ pub fn write_wdl_file<P: AsRef<Path>>(path: P, wdl_table: &WdlTable) -> io::Resu
     file.write_all(&[mk_string.len() as u8])?;
     file.write_all(mk_string.as_bytes())?;
 
-    // WdlTable
-    file.write_all(&wdl_table.positions.len().to_le_bytes())?;
-    file.write_all(
-        wdl_table
-            .positions
-            .iter()
-            .map(|&wdl| wdl.into())
-            .collect::<Vec<u8>>()
-            .as_slice(),
-    )?;
+    // Integrity checksum over the material key and every position's value.
+    let checksum = wdl_table_checksum(&wdl_table.material, &wdl_table.positions);
+    file.write_all(&checksum.to_le_bytes())?;
+
+    // The symmetries actually used to canonicalize this material, so a reader can confirm they
+    // still agree with `TransformSet::for_material` rather than silently assuming so.
+    let allowed_transforms = TransformSet::for_material(&wdl_table.material).transforms();
+    file.write_all(&[allowed_transforms.len() as u8])?;
+    for &transform in allowed_transforms {
+        file.write_all(&[transform_tag(transform)])?;
+    }
+
+    // Table shape
+    let total = wdl_table.positions.len();
+    file.write_all(&(total as u64).to_le_bytes())?;
+    file.write_all(&(BLOCK_SIZE as u32).to_le_bytes())?;
+
+    let compressed_blocks: Vec<Vec<u8>> = wdl_table
+        .positions
+        .chunks(BLOCK_SIZE)
+        .map(|block| {
+            serialize_compressed(&compress_wdl_with_scheme(block, DeflateMode::Best, mask_unknown))
+        })
+        .collect();
+    file.write_all(&(compressed_blocks.len() as u32).to_le_bytes())?;
+
+    // Block-offset index: byte offset and length of each compressed block, so a reader can
+    // jump straight to any one of them.
+    let index_len = compressed_blocks.len() as u64 * (8 + 4);
+    let mut offset = file.stream_position()? + index_len;
+    for block in &compressed_blocks {
+        file.write_all(&offset.to_le_bytes())?;
+        file.write_all(&(block.len() as u32).to_le_bytes())?;
+        offset += block.len() as u64;
+    }
+
+    for block in &compressed_blocks {
+        file.write_all(block)?;
+    }
 
     Ok(())
 }
 
-/// Read a compressed WDL table from a file.
+/// Read a block-compressed WDL table from a file, fully materializing every position.
+///
+/// Use [`WdlFileReader`] instead when only a handful of positions are needed.
 pub fn read_wdl_file<P: AsRef<Path>>(path: P) -> io::Result<WdlTable> {
     let mut file = File::open(path)?;
+    let header = read_header(&mut file)?;
 
-    // Header
+    let mut positions = Vec::with_capacity(header.total_positions);
+    for &(offset, length) in &header.block_index {
+        let buf = read_block_bytes(&mut file, offset, length)?;
+        let compressed = deserialize_compressed(&buf)?;
+        positions.extend(decompress_wdl(&compressed));
+    }
+    positions.truncate(header.total_positions);
+
+    if wdl_table_checksum(&header.material, &positions) != header.checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch: file is corrupted or its header doesn't match its content",
+        ));
+    }
+
+    if header.allowed_transforms.as_slice()
+        != TransformSet::for_material(&header.material).transforms()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "allowed transforms mismatch: file's recorded symmetries no longer agree with \
+             TransformSet::for_material for this material key",
+        ));
+    }
+
+    Ok(WdlTable {
+        material: header.material,
+        positions,
+    })
+}
+
+struct WdlFileHeader {
+    material: MaterialKey,
+    checksum: u64,
+    allowed_transforms: Vec<Transform>,
+    total_positions: usize,
+    block_index: Vec<(u64, u32)>,
+}
+
+fn read_header(file: &mut File) -> io::Result<WdlFileHeader> {
     let mut magic = [0u8; 4];
     file.read_exact(&mut magic)?;
     if &magic != MAGIC {
@@ -66,35 +197,469 @@ pub fn read_wdl_file<P: AsRef<Path>>(path: P) -> io::Result<WdlTable> {
     let material = MaterialKey::from_string(&mk_string)
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
 
-    // WDL Table
-    let mut buf = [0u8; 8];
-    file.read_exact(&mut buf)?;
-    let wdl_table_len = u64::from_le_bytes(buf) as usize;
+    // Integrity checksum
+    let mut buf8 = [0u8; 8];
+    file.read_exact(&mut buf8)?;
+    let checksum = u64::from_le_bytes(buf8);
 
-    let mut buf = vec![0u8; wdl_table_len];
-    file.read_exact(&mut buf)?;
-    let positions = buf.iter().map(|&num| num.try_into().unwrap()).collect();
+    // Allowed transforms
+    let mut transforms_len = [0u8; 1];
+    file.read_exact(&mut transforms_len)?;
+    let mut allowed_transforms = Vec::with_capacity(transforms_len[0] as usize);
+    for _ in 0..transforms_len[0] {
+        let mut tag = [0u8; 1];
+        file.read_exact(&mut tag)?;
+        allowed_transforms.push(transform_from_tag(tag[0])?);
+    }
 
-    Ok(WdlTable {
+    // Table shape
+    file.read_exact(&mut buf8)?;
+    let total_positions = u64::from_le_bytes(buf8) as usize;
+
+    let mut buf4 = [0u8; 4];
+    file.read_exact(&mut buf4)?;
+    let _block_size = u32::from_le_bytes(buf4) as usize;
+
+    file.read_exact(&mut buf4)?;
+    let num_blocks = u32::from_le_bytes(buf4) as usize;
+
+    let mut block_index = Vec::with_capacity(num_blocks);
+    for _ in 0..num_blocks {
+        file.read_exact(&mut buf8)?;
+        let offset = u64::from_le_bytes(buf8);
+        file.read_exact(&mut buf4)?;
+        let length = u32::from_le_bytes(buf4);
+        block_index.push((offset, length));
+    }
+
+    Ok(WdlFileHeader {
         material,
-        positions,
+        checksum,
+        allowed_transforms,
+        total_positions,
+        block_index,
     })
 }
 
+/// Tag a [`Transform`] by its position in [`ALL_TRANSFORMS`], so the header can store it as a
+/// single byte rather than relying on the enum's in-memory representation.
+fn transform_tag(transform: Transform) -> u8 {
+    ALL_TRANSFORMS
+        .iter()
+        .position(|&t| t == transform)
+        .expect("ALL_TRANSFORMS lists every Transform variant") as u8
+}
+
+fn transform_from_tag(tag: u8) -> io::Result<Transform> {
+    ALL_TRANSFORMS
+        .get(tag as usize)
+        .copied()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid transform tag"))
+}
+
+/// Read one compressed block's raw bytes at the given offset/length from the block index.
+fn read_block_bytes(file: &mut File, offset: u64, length: u32) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; length as usize];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Per-block statistics surfaced by `heisenbase inspect`, decoded from [`CompressedWdl`]
+/// without decompressing the block's payload.
+pub struct BlockStats {
+    pub orig_len: usize,
+    pub scheme_name: &'static str,
+    pub base_symbols: usize,
+    pub generated_symbols: usize,
+    pub code_len_histogram: HashMap<u8, usize>,
+    pub bitstream_bytes: usize,
+}
+
+fn block_stats(compressed: &CompressedWdl) -> BlockStats {
+    let mut code_len_histogram = HashMap::new();
+    let mut tally = |code_lens: &[u8]| {
+        for &len in code_lens {
+            if len > 0 {
+                *code_len_histogram.entry(len).or_insert(0) += 1;
+            }
+        }
+    };
+
+    let (scheme_name, base_symbols, generated_symbols) = match &compressed.scheme {
+        CompressionScheme::PairSubstitution {
+            base_symbols,
+            sym_pairs,
+            code_lens,
+            ..
+        } => {
+            tally(code_lens);
+            ("pair_substitution", *base_symbols as usize, sym_pairs.len())
+        }
+        CompressionScheme::Lz77 {
+            literal_code_lens,
+            distance_code_lens,
+        } => {
+            tally(literal_code_lens);
+            tally(distance_code_lens);
+            ("lz77", 0, 0)
+        }
+    };
+
+    BlockStats {
+        orig_len: compressed.orig_len,
+        scheme_name,
+        base_symbols,
+        generated_symbols,
+        code_len_histogram,
+        bitstream_bytes: compressed.bitstream.len(),
+    }
+}
+
+/// Read every compressed block's metadata without decompressing its payload, for `heisenbase
+/// inspect`. Returns the table's material key, its total position count, and one [`BlockStats`]
+/// per compressed block.
+pub fn inspect_wdl_file<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<(MaterialKey, usize, Vec<BlockStats>)> {
+    let mut file = File::open(path)?;
+    let header = read_header(&mut file)?;
+
+    let mut blocks = Vec::with_capacity(header.block_index.len());
+    for &(offset, length) in &header.block_index {
+        let buf = read_block_bytes(&mut file, offset, length)?;
+        let compressed = deserialize_compressed(&buf)?;
+        blocks.push(block_stats(&compressed));
+    }
+
+    Ok((header.material, header.total_positions, blocks))
+}
+
+/// Decompress every block of a `.hbt` file and confirm each one decodes to exactly as many
+/// positions as it (and the file header) claims, for `heisenbase verify`.
+///
+/// Reading a block already validates its CRC-32 (see [`deserialize_compressed`]), so by the
+/// time `decompress_wdl` runs here the bitstream is known-good; this mainly exists to give a
+/// standalone, human-facing confirmation that a whole file is internally consistent. It does
+/// not check the header's whole-table checksum, which [`read_wdl_file`] does.
+pub fn verify_wdl_file<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+    let mut file = File::open(path)?;
+    let header = read_header(&mut file)?;
+
+    let mut total_positions = 0usize;
+    for &(offset, length) in &header.block_index {
+        let buf = read_block_bytes(&mut file, offset, length)?;
+        let compressed = deserialize_compressed(&buf)?;
+        let decompressed = decompress_wdl(&compressed);
+        if decompressed.len() != compressed.orig_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "block decoded to {} positions, expected {}",
+                    decompressed.len(),
+                    compressed.orig_len
+                ),
+            ));
+        }
+        total_positions += decompressed.len();
+    }
+
+    if total_positions != header.total_positions {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "file decoded to {total_positions} positions across all blocks, header claims {}",
+                header.total_positions
+            ),
+        ));
+    }
+
+    Ok(total_positions)
+}
+
+/// A position in a serialized `CompressedWdl` was truncated or otherwise malformed.
+fn invalid_block() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "truncated compressed block")
+}
+
+/// Tag byte identifying which [`CompressionScheme`] variant follows in a serialized block.
+const SCHEME_PAIR_SUBSTITUTION: u8 = 0;
+const SCHEME_LZ77: u8 = 1;
+
+fn serialize_compressed(compressed: &CompressedWdl) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    match &compressed.scheme {
+        CompressionScheme::PairSubstitution {
+            base_symbols,
+            sym_pairs,
+            code_lens,
+            mask_unknown,
+        } => {
+            buf.push(SCHEME_PAIR_SUBSTITUTION);
+            buf.extend_from_slice(&base_symbols.to_le_bytes());
+
+            buf.extend_from_slice(&(sym_pairs.len() as u32).to_le_bytes());
+            for &(a, b) in sym_pairs {
+                buf.extend_from_slice(&a.to_le_bytes());
+                buf.extend_from_slice(&b.to_le_bytes());
+            }
+
+            buf.extend_from_slice(&(code_lens.len() as u32).to_le_bytes());
+            buf.extend_from_slice(code_lens);
+
+            buf.push(*mask_unknown as u8);
+        }
+        CompressionScheme::Lz77 {
+            literal_code_lens,
+            distance_code_lens,
+        } => {
+            buf.push(SCHEME_LZ77);
+            buf.extend_from_slice(&(literal_code_lens.len() as u32).to_le_bytes());
+            buf.extend_from_slice(literal_code_lens);
+
+            buf.extend_from_slice(&(distance_code_lens.len() as u32).to_le_bytes());
+            buf.extend_from_slice(distance_code_lens);
+        }
+    }
+
+    buf.extend_from_slice(&(compressed.bit_len as u64).to_le_bytes());
+
+    buf.extend_from_slice(&(compressed.bitstream.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&compressed.bitstream);
+
+    buf.extend_from_slice(&(compressed.orig_len as u64).to_le_bytes());
+
+    buf.extend_from_slice(&compressed.checksum.to_le_bytes());
+
+    buf
+}
+
+fn deserialize_compressed(bytes: &[u8]) -> io::Result<CompressedWdl> {
+    let mut reader = ByteReader::new(bytes);
+
+    let scheme_tag = reader.take(1)?[0];
+    let scheme = match scheme_tag {
+        SCHEME_PAIR_SUBSTITUTION => {
+            let base_symbols = reader.take_u16()?;
+
+            let sym_pairs_len = reader.take_u32()? as usize;
+            let mut sym_pairs = Vec::with_capacity(sym_pairs_len);
+            for _ in 0..sym_pairs_len {
+                sym_pairs.push((reader.take_u16()?, reader.take_u16()?));
+            }
+
+            let code_lens_len = reader.take_u32()? as usize;
+            let code_lens = reader.take(code_lens_len)?.to_vec();
+
+            let mask_unknown = reader.take(1)?[0] != 0;
+
+            CompressionScheme::PairSubstitution {
+                base_symbols,
+                sym_pairs,
+                code_lens,
+                mask_unknown,
+            }
+        }
+        SCHEME_LZ77 => {
+            let literal_code_lens_len = reader.take_u32()? as usize;
+            let literal_code_lens = reader.take(literal_code_lens_len)?.to_vec();
+
+            let distance_code_lens_len = reader.take_u32()? as usize;
+            let distance_code_lens = reader.take(distance_code_lens_len)?.to_vec();
+
+            CompressionScheme::Lz77 {
+                literal_code_lens,
+                distance_code_lens,
+            }
+        }
+        _ => return Err(invalid_block()),
+    };
+
+    let bit_len = reader.take_u64()? as usize;
+
+    let bitstream_len = reader.take_u32()? as usize;
+    let bitstream = reader.take(bitstream_len)?.to_vec();
+
+    let orig_len = reader.take_u64()? as usize;
+
+    let checksum = reader.take_u32()?;
+
+    let compressed = CompressedWdl {
+        scheme,
+        bitstream,
+        bit_len,
+        orig_len,
+        checksum,
+    };
+
+    if compressed.compute_checksum() != checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "compressed block checksum mismatch: file is corrupted or truncated",
+        ));
+    }
+
+    Ok(compressed)
+}
+
+/// Minimal cursor over a byte slice used to deserialize a [`CompressedWdl`] block.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).ok_or_else(invalid_block)?;
+        let slice = self.bytes.get(self.pos..end).ok_or_else(invalid_block)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn take_u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn take_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn take_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// Random-access reader over a `.hbt` file.
+///
+/// Unlike [`read_wdl_file`], this only reads and CRC-checks the block containing a requested
+/// position, keeping a small LRU cache of recently-touched blocks' [`CompressedWdl`]s so that
+/// repeated probes into the same region of the table don't re-read or re-checksum their bytes.
+/// A cached block is never fully decompressed: each lookup within it goes through
+/// [`compression::probe`](crate::compression::probe), which only walks as far as the symbol
+/// covering the requested index. This is the form a long-lived prober should hold onto for
+/// large material keys whose full table doesn't comfortably fit in memory.
+pub struct WdlFileReader {
+    file: File,
+    material: MaterialKey,
+    allowed_transforms: Vec<Transform>,
+    total_positions: usize,
+    block_index: Vec<(u64, u32)>,
+    cache: HashMap<usize, CompressedWdl>,
+    cache_order: VecDeque<usize>,
+}
+
+impl WdlFileReader {
+    /// Open a block-compressed WDL file for random-access probing.
+    ///
+    /// Unlike [`read_wdl_file`], this never decompresses the whole table, so it can't check
+    /// the header's integrity checksum against the actual positions; only the header itself
+    /// (magic, version, material key, allowed transforms) is validated here — cheap since none
+    /// of it requires touching a single compressed block.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let header = read_header(&mut file)?;
+
+        if header.allowed_transforms.as_slice()
+            != TransformSet::for_material(&header.material).transforms()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "allowed transforms mismatch: file's recorded symmetries no longer agree with \
+                 TransformSet::for_material for this material key",
+            ));
+        }
+
+        Ok(Self {
+            file,
+            material: header.material,
+            allowed_transforms: header.allowed_transforms,
+            total_positions: header.total_positions,
+            block_index: header.block_index,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+        })
+    }
+
+    pub fn material(&self) -> &MaterialKey {
+        &self.material
+    }
+
+    /// The symmetries this file was written with, per [`TransformSet::for_material`] at write
+    /// time (and reconfirmed against it on [`Self::open`]).
+    pub fn allowed_transforms(&self) -> &[Transform] {
+        &self.allowed_transforms
+    }
+
+    pub fn total_positions(&self) -> usize {
+        self.total_positions
+    }
+
+    /// Look up a single position, walking only as far into its block's compressed bitstream as
+    /// [`compression::probe`](crate::compression::probe) needs rather than decompressing the
+    /// whole block.
+    pub fn probe_index(&mut self, index: usize) -> io::Result<WdlScoreRange> {
+        if index >= self.total_positions {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "index out of bounds",
+            ));
+        }
+
+        let block_num = index / BLOCK_SIZE;
+        let within_block = index % BLOCK_SIZE;
+
+        if !self.cache.contains_key(&block_num) {
+            self.load_block(block_num)?;
+        } else {
+            self.touch_block(block_num);
+        }
+
+        Ok(probe(&self.cache[&block_num], within_block))
+    }
+
+    fn load_block(&mut self, block_num: usize) -> io::Result<()> {
+        let (offset, length) = self.block_index[block_num];
+        let buf = read_block_bytes(&mut self.file, offset, length)?;
+        let compressed = deserialize_compressed(&buf)?;
+
+        self.cache.insert(block_num, compressed);
+        self.cache_order.push_back(block_num);
+        if self.cache_order.len() > BLOCK_CACHE_CAPACITY {
+            if let Some(evicted) = self.cache_order.pop_front() {
+                self.cache.remove(&evicted);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn touch_block(&mut self, block_num: usize) {
+        self.cache_order.retain(|&b| b != block_num);
+        self.cache_order.push_back(block_num);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io;
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    #[test]
-    fn read_wdl_file_rejects_bad_magic() {
+    fn temp_path(prefix: &str) -> std::path::PathBuf {
         let unique = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("system clock should be after UNIX_EPOCH")
             .as_nanos();
-        let path = std::env::temp_dir().join(format!("heisenbase_bad_magic_{unique}.hbt"));
+        std::env::temp_dir().join(format!("heisenbase_{prefix}_{unique}.hbt"))
+    }
+
+    #[test]
+    fn read_wdl_file_rejects_bad_magic() {
+        let path = temp_path("bad_magic");
 
         {
             let mut file = File::create(&path).expect("failed to create temporary file");
@@ -112,4 +677,210 @@ mod tests {
             Err(ref e) if e.kind() == io::ErrorKind::InvalidData
         ));
     }
+
+    fn sample_table() -> WdlTable {
+        use WdlScoreRange::*;
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let mut positions = Vec::new();
+        for i in 0..(BLOCK_SIZE * 2 + 17) {
+            positions.push(match i % 4 {
+                0 => Win,
+                1 => Draw,
+                2 => Loss,
+                _ => Unknown,
+            });
+        }
+        WdlTable {
+            material,
+            positions,
+        }
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        let path = temp_path("round_trip");
+        let table = sample_table();
+
+        write_wdl_file(&path, &table).unwrap();
+        let read_back = read_wdl_file(&path).unwrap();
+
+        assert_eq!(read_back.material, table.material);
+        assert_eq!(read_back.positions, table.positions);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_wdl_file_rejects_checksum_mismatch() {
+        let path = temp_path("checksum_mismatch");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        // The checksum sits right after the magic, version and material key bytes.
+        let mk_len = table.material.to_string().len();
+        let checksum_offset = 4 + 1 + 1 + mk_len;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[checksum_offset] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = read_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn corrupted_block_bytes_are_rejected_before_decoding_panics() {
+        let path = temp_path("corrupted_block");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        // Flip the very last byte of the file, which always falls inside the last compressed
+        // block's stored CRC-32: the mismatch should be caught by that block's CRC check and
+        // turned into an error before `decode_bitstream`/`expand_symbol` ever run.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = read_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn reader_probes_individual_positions_across_blocks() {
+        let path = temp_path("probe");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        let mut reader = WdlFileReader::open(&path).unwrap();
+        assert_eq!(reader.total_positions(), table.positions.len());
+
+        for &idx in &[0, 1, BLOCK_SIZE - 1, BLOCK_SIZE, BLOCK_SIZE * 2 + 16] {
+            assert_eq!(reader.probe_index(idx).unwrap(), table.positions[idx]);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reader_exposes_allowed_transforms_matching_for_material() {
+        let path = temp_path("allowed_transforms");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        let reader = WdlFileReader::open(&path).unwrap();
+        assert_eq!(
+            reader.allowed_transforms(),
+            TransformSet::for_material(&table.material).transforms()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_wdl_file_rejects_an_invalid_transform_tag() {
+        let path = temp_path("bad_transform_tag");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        // The first allowed-transform tag byte sits right after the 8-byte checksum and the
+        // 1-byte transforms count.
+        let mk_len = table.material.to_string().len();
+        let first_tag_offset = 4 + 1 + 1 + mk_len + 8 + 1;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[first_tag_offset] = 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = read_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn reader_rejects_out_of_bounds_index() {
+        let path = temp_path("oob");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        let mut reader = WdlFileReader::open(&path).unwrap();
+        assert!(reader.probe_index(table.positions.len()).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn uniform_table_compresses_to_a_small_fraction_of_its_size() {
+        let path = temp_path("uniform");
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let positions = vec![WdlScoreRange::Win; BLOCK_SIZE * 4];
+        let total_positions = positions.len();
+        let table = WdlTable {
+            material,
+            positions,
+        };
+
+        write_wdl_file(&path, &table).unwrap();
+        let on_disk = std::fs::metadata(&path).unwrap().len() as usize;
+        std::fs::remove_file(&path).unwrap();
+
+        // A naive one-byte-per-position encoding would take `total_positions` bytes; a table
+        // dominated by a single outcome should compress to a small fraction of that.
+        assert!(
+            on_disk < total_positions / 10,
+            "expected a long run of a single outcome to compress well, got {on_disk} bytes for {total_positions} positions"
+        );
+    }
+
+    #[test]
+    fn inspect_reports_material_and_block_stats() {
+        let path = temp_path("inspect");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        let (material, total_positions, blocks) = inspect_wdl_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(material, table.material);
+        assert_eq!(total_positions, table.positions.len());
+        assert_eq!(blocks.len(), 3); // BLOCK_SIZE * 2 + 17 positions span 3 blocks.
+        let decoded_positions: usize = blocks.iter().map(|b| b.orig_len).sum();
+        assert_eq!(decoded_positions, total_positions);
+        for block in &blocks {
+            assert!(!block.code_len_histogram.is_empty());
+        }
+    }
+
+    #[test]
+    fn verify_succeeds_on_an_intact_file_and_fails_on_a_corrupted_one() {
+        let path = temp_path("verify");
+        let table = sample_table();
+        write_wdl_file(&path, &table).unwrap();
+
+        assert_eq!(verify_wdl_file(&path).unwrap(), table.positions.len());
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = verify_wdl_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
 }
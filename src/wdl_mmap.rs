@@ -0,0 +1,344 @@
+//! Block-structured, memory-mapped random-access store for probing a [`WdlTable`]'s positions
+//! without materializing the whole table.
+//!
+//! [`crate::wdl_bitpack`] already shrinks a table on disk, but [`read_packed_wdl_file`] still
+//! decodes every position up front, which doesn't scale once a table is far larger than RAM.
+//! This format instead splits the bit-packed positions into fixed-size, independently
+//! byte-aligned blocks, preceded by a sparse index of each block's first position index, file
+//! offset, byte length and CRC-32. [`MmapWdlTable::open`] only reads that small index; each
+//! [`MmapWdlTable::probe`] binary-searches it, then touches just the owning block's bytes
+//! through the mmap.
+//!
+//! [`read_packed_wdl_file`]: crate::wdl_bitpack::read_packed_wdl_file
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::crc32;
+use crate::material_key::MaterialKey;
+use crate::wdl_bitpack::{BIT_WIDTH, BitReader, BitWriter};
+use crate::wdl_score_range::WdlScoreRange;
+use crate::wdl_table::WdlTable;
+
+const MAGIC: &[u8; 4] = b"HBPM";
+const VERSION: u8 = 1;
+
+/// Positions per block. Chosen so a block's bit-packed payload is a few KiB: small enough that
+/// probing one position only touches a small slice of the mmap, large enough that the sparse
+/// index doesn't dominate the file for modest tables.
+const BLOCK_SIZE: usize = 4096;
+
+/// One entry in the sparse block index: where a block starts (in position index and in file
+/// offset), how many bytes its payload spans, and its CRC-32 so a lookup can detect a corrupted
+/// block before decoding it.
+struct BlockIndexEntry {
+    first_index: u64,
+    offset: u64,
+    byte_len: u32,
+    checksum: u32,
+}
+
+/// Write `table` as a block-structured, mmap-friendly store.
+///
+/// Layout: magic, version, material key, total position count, bit width, block count, then
+/// the sparse index (one `(first_index: u64, offset: u64, byte_len: u32, checksum: u32)` tuple
+/// per block), then the blocks themselves, each independently bit-packed at [`BIT_WIDTH`] and
+/// byte-aligned so it can be decoded without any preceding block's bytes.
+pub fn write_mmap_wdl_file<P: AsRef<Path>>(path: P, table: &WdlTable) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    let mk_string = table.material.to_string();
+    let block_payloads: Vec<Vec<u8>> = table
+        .positions
+        .chunks(BLOCK_SIZE)
+        .map(|chunk| {
+            let mut writer = BitWriter::new();
+            for &score in chunk {
+                writer.write_bits(u8::from(score) as u32, BIT_WIDTH);
+            }
+            writer.finish()
+        })
+        .collect();
+
+    file.write_all(MAGIC)?;
+    file.write_all(&[VERSION])?;
+    file.write_all(&[mk_string.len() as u8])?;
+    file.write_all(mk_string.as_bytes())?;
+    file.write_all(&(table.positions.len() as u64).to_le_bytes())?;
+    file.write_all(&[BIT_WIDTH])?;
+    file.write_all(&(block_payloads.len() as u64).to_le_bytes())?;
+
+    // Header so far: 4 (magic) + 1 (version) + 1 (mk_len) + mk_len + 8 (total positions) + 1
+    // (bit width) + 8 (block count).
+    let header_len = 4 + 1 + 1 + mk_string.len() + 8 + 1 + 8;
+    let index_len = block_payloads.len() * (8 + 8 + 4 + 4);
+    let mut offset = (header_len + index_len) as u64;
+
+    let mut index_bytes = Vec::with_capacity(index_len);
+    for (i, payload) in block_payloads.iter().enumerate() {
+        let first_index = (i * BLOCK_SIZE) as u64;
+        let checksum = crc32::crc32(payload);
+        index_bytes.extend_from_slice(&first_index.to_le_bytes());
+        index_bytes.extend_from_slice(&offset.to_le_bytes());
+        index_bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        index_bytes.extend_from_slice(&checksum.to_le_bytes());
+        offset += payload.len() as u64;
+    }
+    file.write_all(&index_bytes)?;
+
+    for payload in &block_payloads {
+        file.write_all(payload)?;
+    }
+
+    Ok(())
+}
+
+/// A [`WdlTable`] probed directly from a memory-mapped [`write_mmap_wdl_file`] store, so only
+/// the blocks a caller actually probes are ever decoded.
+pub struct MmapWdlTable {
+    mmap: Mmap,
+    material: MaterialKey,
+    total_positions: usize,
+    bit_width: u8,
+    block_index: Vec<BlockIndexEntry>,
+}
+
+impl MmapWdlTable {
+    /// Open `path`, reading just its header and sparse index up front; the mmap itself is
+    /// lazily paged in by the OS as [`Self::probe`] touches individual blocks.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = File::open(path.as_ref())?;
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
+        }
+
+        let mut version = [0u8; 1];
+        file.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported version",
+            ));
+        }
+
+        let mut mk_len = [0u8; 1];
+        file.read_exact(&mut mk_len)?;
+        let mk_len = mk_len[0] as usize;
+        let mut mk_bytes = vec![0u8; mk_len];
+        file.read_exact(&mut mk_bytes)?;
+        let mk_string = String::from_utf8(mk_bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+        let material = MaterialKey::from_string(&mk_string)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid material key"))?;
+
+        let mut buf8 = [0u8; 8];
+        file.read_exact(&mut buf8)?;
+        let total_positions = u64::from_le_bytes(buf8) as usize;
+
+        let mut bit_width = [0u8; 1];
+        file.read_exact(&mut bit_width)?;
+        let bit_width = bit_width[0];
+
+        file.read_exact(&mut buf8)?;
+        let num_blocks = u64::from_le_bytes(buf8) as usize;
+
+        let mut block_index = Vec::with_capacity(num_blocks);
+        for _ in 0..num_blocks {
+            file.read_exact(&mut buf8)?;
+            let first_index = u64::from_le_bytes(buf8);
+            file.read_exact(&mut buf8)?;
+            let offset = u64::from_le_bytes(buf8);
+            let mut buf4 = [0u8; 4];
+            file.read_exact(&mut buf4)?;
+            let byte_len = u32::from_le_bytes(buf4);
+            file.read_exact(&mut buf4)?;
+            let checksum = u32::from_le_bytes(buf4);
+            block_index.push(BlockIndexEntry {
+                first_index,
+                offset,
+                byte_len,
+                checksum,
+            });
+        }
+
+        // SAFETY: the file isn't expected to be concurrently truncated or mutated out from under
+        // this process; this matches the standard caveat of the `memmap2` crate's `Mmap::map`.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Ok(Self {
+            mmap,
+            material,
+            total_positions,
+            bit_width,
+            block_index,
+        })
+    }
+
+    pub fn material(&self) -> &MaterialKey {
+        &self.material
+    }
+
+    pub fn total_positions(&self) -> usize {
+        self.total_positions
+    }
+
+    /// Binary-search the sparse index for the block owning `index`, i.e. the last block whose
+    /// `first_index` is `<= index`.
+    fn block_for(&self, index: usize) -> &BlockIndexEntry {
+        let index = index as u64;
+        let block = match self
+            .block_index
+            .binary_search_by_key(&index, |entry| entry.first_index)
+        {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        &self.block_index[block]
+    }
+
+    /// Probe a single position by its [`crate::position_indexer::PositionIndexer`] index,
+    /// touching only the mmapped bytes of the block that contains it.
+    pub fn probe(&self, index: usize) -> io::Result<WdlScoreRange> {
+        if index >= self.total_positions {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "index out of range",
+            ));
+        }
+
+        let entry = self.block_for(index);
+        let start = entry.offset as usize;
+        let end = start + entry.byte_len as usize;
+        let block_bytes = &self.mmap[start..end];
+
+        if crc32::crc32(block_bytes) != entry.checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "block checksum mismatch: file is corrupted",
+            ));
+        }
+
+        let within_block = (index as u64 - entry.first_index) as usize;
+        let mut reader = BitReader::new(block_bytes);
+        for _ in 0..within_block {
+            reader.read_bits(self.bit_width)?;
+        }
+        let code = reader.read_bits(self.bit_width)?;
+        WdlScoreRange::try_from(code as u8)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid packed WDL code"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_path(prefix: &str) -> std::path::PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after UNIX_EPOCH")
+            .as_nanos();
+        std::env::temp_dir().join(format!("heisenbase_{prefix}_{unique}.hbm"))
+    }
+
+    fn sample_table(len: usize) -> WdlTable {
+        let material = MaterialKey::from_string("KQvK").unwrap();
+        let states = [
+            WdlScoreRange::Unknown,
+            WdlScoreRange::WinOrDraw,
+            WdlScoreRange::DrawOrLoss,
+            WdlScoreRange::Win,
+            WdlScoreRange::Draw,
+            WdlScoreRange::Loss,
+            WdlScoreRange::IllegalPosition,
+            WdlScoreRange::CursedWin,
+            WdlScoreRange::BlessedLoss,
+        ];
+        let positions = (0..len).map(|i| states[i % states.len()]).collect();
+        WdlTable {
+            material,
+            positions,
+        }
+    }
+
+    #[test]
+    fn probes_every_position_across_several_blocks() {
+        let path = temp_path("probe_all");
+        let table = sample_table(BLOCK_SIZE * 3 + 7);
+
+        write_mmap_wdl_file(&path, &table).unwrap();
+        let mmap_table = MmapWdlTable::open(&path).unwrap();
+
+        assert_eq!(mmap_table.material(), &table.material);
+        assert_eq!(mmap_table.total_positions(), table.positions.len());
+        for (i, &expected) in table.positions.iter().enumerate() {
+            assert_eq!(mmap_table.probe(i).unwrap(), expected);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn probe_rejects_an_out_of_range_index() {
+        let path = temp_path("out_of_range");
+        let table = sample_table(10);
+
+        write_mmap_wdl_file(&path, &table).unwrap();
+        let mmap_table = MmapWdlTable::open(&path).unwrap();
+
+        let result = mmap_table.probe(10);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn probe_detects_a_corrupted_block() {
+        let path = temp_path("corrupted_block");
+        let table = sample_table(BLOCK_SIZE + 10);
+        write_mmap_wdl_file(&path, &table).unwrap();
+
+        // Flip a byte inside the first block's payload, which sits right after the header and
+        // the two-entry sparse index (first_index + offset + byte_len + checksum = 24 bytes per
+        // block).
+        let mk_len = table.material.to_string().len();
+        let header_len = 4 + 1 + 1 + mk_len + 8 + 1 + 8;
+        let first_block_byte = header_len + 2 * 24;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[first_block_byte] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mmap_table = MmapWdlTable::open(&path).unwrap();
+        let result = mmap_table.probe(0);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn read_mmap_wdl_file_rejects_bad_magic() {
+        let path = temp_path("bad_magic");
+        std::fs::write(&path, b"BAD!").unwrap();
+
+        let result = MmapWdlTable::open(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+}
@@ -1,10 +1,10 @@
-use crate::score::{DtzScore, DtzScoreRange};
-
 /// Range of win/draw/loss values stored in a table.
 ///
 /// The discriminants of this enum are important for compression as they are
-/// treated as the initial alphabet for the pair‑substitution algorithm.  Keep
-/// the values in sync with the `TryFrom<u8>` implementation below.
+/// treated as the initial alphabet for the pair‑substitution algorithm (see
+/// [`crate::compression`] for the RE-PAIR pass that merges them and the canonical Huffman
+/// code built on top of the merged alphabet). Keep the values in sync with the `TryFrom<u8>`
+/// implementation below.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WdlScoreRange {
@@ -17,6 +17,12 @@ pub enum WdlScoreRange {
     Loss = 5,
     /// This won't be used right now because the TableBuilder doesn't mark illegal positions
     IllegalPosition = 6,
+    /// A theoretical win that cannot zero the halfmove clock within the remaining ply
+    /// budget, so it is drawn under the 50-move rule.
+    CursedWin = 7,
+    /// A theoretical loss that cannot be converted within the remaining ply budget, so it
+    /// is drawn under the 50-move rule.
+    BlessedLoss = 8,
 }
 
 impl From<WdlScoreRange> for u8 {
@@ -38,29 +44,9 @@ impl core::convert::TryFrom<u8> for WdlScoreRange {
             4 => Draw,
             5 => Loss,
             6 => IllegalPosition,
+            7 => CursedWin,
+            8 => BlessedLoss,
             _ => return Err(()),
         })
     }
 }
-
-impl From<DtzScoreRange> for WdlScoreRange {
-    fn from(score: DtzScoreRange) -> Self {
-        use WdlScoreRange::*;
-
-        let zero = DtzScore::draw();
-
-        if score.min > zero {
-            Win
-        } else if score.max < zero {
-            Loss
-        } else if score.min == zero && score.max == zero {
-            Draw
-        } else if score.min >= zero && score.max > zero {
-            WinOrDraw
-        } else if score.min < zero && score.max == zero {
-            DrawOrLoss
-        } else {
-            Unknown
-        }
-    }
-}
@@ -1,12 +1,25 @@
+use crate::dtz_table::DtzTable;
 use crate::material_key::MaterialKey;
 use crate::table_builder::TableBuilder;
+use crate::wdl_mmap::MmapWdlTable;
 use crate::wdl_score_range::WdlScoreRange;
+use std::io;
+use std::path::Path;
 
 pub struct WdlTable {
     pub material: MaterialKey,
     pub positions: Vec<WdlScoreRange>,
 }
 
+impl WdlTable {
+    /// Open a block-structured, memory-mapped store written by
+    /// [`crate::wdl_mmap::write_mmap_wdl_file`] for random-access probing without fully
+    /// materializing the table, e.g. for a material class too large to comfortably fit in RAM.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MmapWdlTable> {
+        MmapWdlTable::open(path)
+    }
+}
+
 impl From<TableBuilder> for WdlTable {
     fn from(tb: TableBuilder) -> Self {
         let positions = tb.positions.into_iter().map(WdlScoreRange::from).collect();
@@ -17,3 +30,22 @@ impl From<TableBuilder> for WdlTable {
         }
     }
 }
+
+/// Collapse an already-built [`DtzTable`] down to its WDL buckets, instead of re-solving a
+/// [`TableBuilder`]: lets a caller keep the finer-grained DTZ table (e.g. to also call
+/// [`crate::dtz_file::write_dtz_file`]) while still producing the `.hbt` WDL artifact from the
+/// same solve.
+impl From<&DtzTable> for WdlTable {
+    fn from(dtz_table: &DtzTable) -> Self {
+        let positions = dtz_table
+            .positions
+            .iter()
+            .map(|&dtz| WdlScoreRange::from(dtz))
+            .collect();
+
+        Self {
+            material: dtz_table.material.clone(),
+            positions,
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Zobrist-style hashing: folds game state into a 64-bit key by XORing a table of random
+//! constants generated at compile time from a fixed seed, so the hash is stable across runs
+//! and machines without checking a generated table into the repo.
+
+use shakmaty::{Chess, Color, EnPassantMode, Position, Role, Square};
+
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// SplitMix64: a fast, well-distributed generator, used as a `const fn` to fill
+/// [`PIECE_SQUARE_KEYS`] and [`BLACK_TO_MOVE_KEY`] from [`SEED`] at compile time.
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z, state)
+}
+
+/// One slot per (role, color) combination.
+const PIECE_KINDS: usize = 12;
+
+pub(crate) const fn piece_key_index(role: Role, color: Color) -> usize {
+    let role_index = match role {
+        Role::Pawn => 0,
+        Role::Knight => 1,
+        Role::Bishop => 2,
+        Role::Rook => 3,
+        Role::Queen => 4,
+        Role::King => 5,
+    };
+    role_index * 2
+        + match color {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+}
+
+const fn generate_keys() -> ([[u64; PIECE_KINDS]; 64], u64, [u64; 8]) {
+    let mut table = [[0u64; PIECE_KINDS]; 64];
+    let mut state = SEED;
+    let mut square = 0;
+    while square < 64 {
+        let mut kind = 0;
+        while kind < PIECE_KINDS {
+            let (value, next_state) = splitmix64(state);
+            table[square][kind] = value;
+            state = next_state;
+            kind += 1;
+        }
+        square += 1;
+    }
+    let (black_to_move_key, mut state) = splitmix64(state);
+
+    let mut ep_file_keys = [0u64; 8];
+    let mut file = 0;
+    while file < 8 {
+        let (value, next_state) = splitmix64(state);
+        ep_file_keys[file] = value;
+        state = next_state;
+        file += 1;
+    }
+
+    (table, black_to_move_key, ep_file_keys)
+}
+
+const KEYS: ([[u64; PIECE_KINDS]; 64], u64, [u64; 8]) = generate_keys();
+
+/// Per-(square, piece) random constants, indexed `[square][piece_key_index(role, color)]`.
+pub const PIECE_SQUARE_KEYS: [[u64; PIECE_KINDS]; 64] = KEYS.0;
+
+/// XORed into the key whenever it's Black to move.
+pub const BLACK_TO_MOVE_KEY: u64 = KEYS.1;
+
+/// XORed into the key when an en-passant capture is available this turn, indexed by the
+/// capture-target square's file.
+pub const EP_FILE_KEYS: [u64; 8] = KEYS.2;
+
+/// More than this many same-(role, color) pieces never occurs: the board only has 16 pieces
+/// per side to begin with, all 8 pawns promoting to the same role still fits comfortably.
+pub(crate) const MAX_MATERIAL_COUNT: usize = 10;
+
+const fn generate_material_presence_keys() -> [[u64; MAX_MATERIAL_COUNT]; PIECE_KINDS] {
+    let mut table = [[0u64; MAX_MATERIAL_COUNT]; PIECE_KINDS];
+    // A state distinct from `generate_keys`' starting point, so this table isn't just a
+    // reshuffled prefix of `PIECE_SQUARE_KEYS`.
+    let mut state = SEED ^ 0xA5A5_A5A5_A5A5_A5A5;
+    let mut kind = 0;
+    while kind < PIECE_KINDS {
+        let mut i = 0;
+        while i < MAX_MATERIAL_COUNT {
+            let (value, next_state) = splitmix64(state);
+            table[kind][i] = value;
+            state = next_state;
+            i += 1;
+        }
+        kind += 1;
+    }
+    table
+}
+
+/// Per-(piece kind, occurrence) random constants for hashing squareless material counts, e.g.
+/// [`crate::material_key::MaterialKey::zobrist`] — unlike [`PIECE_SQUARE_KEYS`], "kind" here is
+/// whatever 0..[`PIECE_KINDS`] mapping the caller needs (material hashing distinguishes a
+/// light-squared bishop from a dark-squared one, which [`piece_key_index`]'s `shakmaty::Role`
+/// can't), and "occurrence" is the 0-based count of that kind already folded in.
+pub(crate) const MATERIAL_PRESENCE_KEYS: [[u64; MAX_MATERIAL_COUNT]; PIECE_KINDS] =
+    generate_material_presence_keys();
+
+/// Folds a position's piece placement, side to move and en-passant target file into a 64-bit
+/// Zobrist key.
+///
+/// Doesn't yet account for castling rights, since none of heisenbase's indexing tracks them
+/// either; this should grow alongside [`crate::position_indexer`] once it does.
+pub struct ZobristHasher;
+
+impl ZobristHasher {
+    pub fn hash(position: &Chess) -> u64 {
+        let board = position.board();
+        let mut key = 0u64;
+        for square in Square::ALL {
+            if let Some(piece) = board.piece_at(square) {
+                key ^= PIECE_SQUARE_KEYS[square.to_usize()][piece_key_index(piece.role, piece.color)];
+            }
+        }
+        if position.turn() == Color::Black {
+            key ^= BLACK_TO_MOVE_KEY;
+        }
+        if let Some(ep_square) = position.ep_square(EnPassantMode::Legal) {
+            key ^= EP_FILE_KEYS[ep_square.file() as usize];
+        }
+        key
+    }
+}
+
+/// Hash `position` in its canonical orientation, so that positions related by a symmetry of
+/// their material (see [`crate::transform::TransformSet::for_material`]) hash identically.
+///
+/// Falls back to [`ZobristHasher::hash`] of the raw position if `position`'s material doesn't
+/// normalize to a [`MaterialKey`] (this shouldn't happen for any legal [`Chess`] position).
+pub fn zobrist_position(position: &Chess) -> u64 {
+    use crate::material_key::MaterialKey;
+    use crate::transform::TransformSet;
+
+    let Some(material_key) = MaterialKey::from_position(position) else {
+        return ZobristHasher::hash(position);
+    };
+
+    let (canonical, _) = TransformSet::for_material(&material_key).canonicalize(position);
+    ZobristHasher::hash(&canonical)
+}
+
+/// Fold arbitrary bytes into a 64-bit checksum, using the same [`splitmix64`] construction
+/// as [`ZobristHasher`] so it stays stable across runs and machines. Used to detect a
+/// corrupted or mismatched `.hbt` file on read, not to hash chess positions.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut state = SEED;
+    let mut key = 0u64;
+    for &byte in bytes {
+        let (value, next_state) = splitmix64(state ^ byte as u64);
+        key ^= value;
+        state = next_state;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::{CastlingMode, fen::Fen};
+
+    #[test]
+    fn hash_is_deterministic() {
+        let position = Chess::default();
+        assert_eq!(
+            ZobristHasher::hash(&position),
+            ZobristHasher::hash(&position)
+        );
+    }
+
+    #[test]
+    fn different_positions_hash_differently() {
+        let start = Chess::default();
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        assert_ne!(ZobristHasher::hash(&start), ZobristHasher::hash(&after_e4));
+    }
+
+    #[test]
+    fn side_to_move_changes_the_hash() {
+        let white_to_move = "4k3/8/8/8/8/8/8/4K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let black_to_move = "4k3/8/8/8/8/8/8/4K3 b - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        assert_ne!(
+            ZobristHasher::hash(&white_to_move),
+            ZobristHasher::hash(&black_to_move)
+        );
+    }
+
+    #[test]
+    fn en_passant_availability_changes_the_hash() {
+        let board = "4k3/8/8/8/3pP3/8/8/4K3";
+        let without_ep = format!("{board} b - - 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let with_ep = format!("{board} b - e3 0 1")
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        assert_ne!(ZobristHasher::hash(&without_ep), ZobristHasher::hash(&with_ep));
+    }
+
+    #[test]
+    fn checksum_is_deterministic_and_sensitive_to_every_byte() {
+        assert_eq!(checksum(b"KQvK"), checksum(b"KQvK"));
+        assert_ne!(checksum(b"KQvK"), checksum(b"KRvK"));
+    }
+
+    #[test]
+    fn zobrist_position_is_invariant_under_material_symmetry() {
+        let position = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        // The horizontal mirror of `position`: a pawnless, bishopless material is
+        // invariant under the full 8-element symmetry group.
+        let mirrored = "3k4/8/8/8/8/8/8/3K3R w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+
+        assert_eq!(zobrist_position(&position), zobrist_position(&mirrored));
+    }
+
+    #[test]
+    fn zobrist_position_differs_for_different_material() {
+        let krvk = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+        let kqvk = "4k3/8/8/8/8/8/8/Q3K3 w - - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .into_position::<Chess>(CastlingMode::Standard)
+            .unwrap();
+
+        assert_ne!(zobrist_position(&krvk), zobrist_position(&kqvk));
+    }
+}